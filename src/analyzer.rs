@@ -0,0 +1,440 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::{HashMap, HashSet};
+
+use once_cell::sync::Lazy;
+use rust_stemmers::{Algorithm, Stemmer};
+use whatlang::{Detector, Lang};
+
+use crate::{config::CONF, normalize::fold_with, tfidf::is_numeric};
+
+/// Turns the words of a document or query into the terms stored in the
+/// content index: short and numeric words and stop words are dropped and
+/// the rest are stemmed so "running", "runs" and "run" are the same term.
+pub struct Analyzer {
+    language: Option<&'static Language>,
+    stemmer: Option<Stemmer>,
+    remove_stop_words: bool,
+    strip_diacritics: bool,
+}
+
+pub struct Language {
+    pub name: &'static str,
+    algorithm: Algorithm,
+    lang: Lang,
+    /// Separated by whitespace.
+    stop_words: &'static str,
+}
+
+const LANGUAGES: [Language; 18] = [
+    Language {
+        name: "english",
+        algorithm: Algorithm::English,
+        lang: Lang::Eng,
+        stop_words: "a about above after again against all am an and any are as at be because \
+            been before being below between both but by can could did do does doing down \
+            during each few for from further had has have having he her here hers herself \
+            him himself his how i if in into is it its itself just me more most my myself \
+            no nor not now of off on once only or other our ours ourselves out over own \
+            same she should so some such than that the their theirs them themselves then \
+            there these they this those through to too under until up very was we were \
+            what when where which while who whom why will with would you your yours \
+            yourself yourselves also may might must shall",
+    },
+    Language {
+        name: "german",
+        algorithm: Algorithm::German,
+        lang: Lang::Deu,
+        stop_words: "aber alle allem allen aller alles als also am an ander andere anderem \
+            anderen anderer anderes auch auf aus bei bin bis bist da damit dann das dass \
+            dem den denn der des dich die dies diese diesem diesen dieser dieses dir doch \
+            dort du durch ein eine einem einen einer eines er es etwas euch euer für hab \
+            habe haben hat hatte hatten hier hin hinter ich ihm ihn ihnen ihr ihre im in \
+            ist ja jede jedem jeden jeder jedes jetzt kann kein keine können man manche \
+            mein meine mich mir mit muss nach nicht nichts noch nun nur ob oder ohne sehr \
+            sein seine sich sie sind so solche soll sondern um und uns unser unter viel \
+            vom von vor war waren was weil welche wenn wer werde werden wie wieder will \
+            wir wird wo zu zum zur über",
+    },
+    Language {
+        name: "french",
+        algorithm: Algorithm::French,
+        lang: Lang::Fra,
+        stop_words: "ai au aux avec avoir ce ces cette dans de des du elle elles en est et été \
+            être eu il ils je la le les leur leurs lui ma mais me même mes moi mon ne nos \
+            notre nous on ont ou où par pas pour qu que qui sa sans se ses son sont sur ta \
+            te tes toi ton tu un une vos votre vous était étaient fait faire comme plus \
+            tout tous aussi bien très donc ici",
+    },
+    Language {
+        name: "spanish",
+        algorithm: Algorithm::Spanish,
+        lang: Lang::Spa,
+        stop_words: "al algo algunos ante antes como con contra cual cuando de del desde donde \
+            durante el ella ellas ellos en entre era es esa esas ese eso esos esta estaba \
+            estas este esto estos está están fue fueron ha han hasta hay la las le les lo \
+            los más me mi mis muy nada ni no nos nosotros o otra otros para pero poco por \
+            porque que quien se ser si sin sobre son su sus también tanto te tiene todo \
+            todos tu tus un una uno unos ya yo",
+    },
+    Language {
+        name: "italian",
+        algorithm: Algorithm::Italian,
+        lang: Lang::Ita,
+        stop_words: "ad al alla alle anche avere c che chi ci come con contro cui da dal dalla \
+            dei del della delle dello di dove e ed era essere gli ha hanno ho il in io la \
+            le lei lo loro lui ma mi mia mio ne negli nei nel nella noi non nostro o per \
+            perché più quale quando quella quello questa questo se sei si sia sono su sua \
+            suo sulla tra tu tutti tutto un una uno voi è",
+    },
+    Language {
+        name: "portuguese",
+        algorithm: Algorithm::Portuguese,
+        lang: Lang::Por,
+        stop_words: "a ao aos as até com como da das de dela dele deles depois do dos e ela \
+            elas ele eles em entre era essa esse esta este eu foi for foram há isso isto \
+            já lhe mais mas me mesmo meu minha muito na nas nem no nos nossa nosso não num \
+            numa o os ou para pela pelo por qual quando que quem se sem ser seu sua são só \
+            também te tem ter tu um uma você é",
+    },
+    Language {
+        name: "dutch",
+        algorithm: Algorithm::Dutch,
+        lang: Lang::Nld,
+        stop_words: "aan al alles als altijd andere ben bij daar dan dat de der deze die dit \
+            doch doen door dus een eens en er ge geen geweest haar had heb hebben heeft hem \
+            het hier hij hoe hun iemand iets ik in is ja je kan kon kunnen maar me meer men \
+            met mij mijn moet na naar niet niets nog nu of om omdat onder ons ook op over \
+            reeds te tegen toch toen tot u uit uw van veel voor want waren was wat werd \
+            wezen wie wil worden wordt zal ze zelf zich zij zijn zo zonder zou",
+    },
+    Language {
+        name: "russian",
+        algorithm: Algorithm::Russian,
+        lang: Lang::Rus,
+        stop_words: "и в во не что он на я с со как а то все она так его но да ты к у же вы \
+            за бы по только ее мне было вот от меня еще нет о из ему теперь когда даже ну \
+            вдруг ли если уже или ни быть был него до вас нибудь опять уж вам ведь там \
+            потом себя ничего ей может они тут где есть надо ней для мы тебя их чем была \
+            сам чтоб без будто чего раз тоже себе под будет тогда кто этот того потому \
+            этого какой совсем ним здесь этом один почти мой тем чтобы нее были куда зачем \
+            всех никогда можно при наконец два об другой хоть после над больше тот через \
+            эти нас про всего них какая много разве три эту моя впрочем хорошо свою этой \
+            перед иногда лучше чуть том нельзя такой им более всегда конечно всю между",
+    },
+    Language {
+        name: "swedish",
+        algorithm: Algorithm::Swedish,
+        lang: Lang::Swe,
+        stop_words: "och det att i en jag hon som han på den med var sig för så till är men ett \
+            om hade de av icke mig du henne då sin nu har inte hans honom skulle hennes där \
+            min man ej vid kunde något från ut när efter upp vi dem vara vad över än dig kan \
+            sina här ha mot alla under någon eller allt mycket sedan ju denna själv detta åt \
+            utan varit hur ingen mitt ni bli blev oss din dessa några deras blir mina samma \
+            vilken er sådan vår blivit dess inom mellan sådant varför varje vilka ditt vem \
+            vilket sådana vart dina vars vårt våra ert era",
+    },
+    Language {
+        name: "danish",
+        algorithm: Algorithm::Danish,
+        lang: Lang::Dan,
+        stop_words: "og i jeg det at en den til er som på de med han af for ikke der var mig sig \
+            men et har om vi min havde ham hun nu over da fra du ud sin dem os op man hans \
+            hvor eller hvad skal selv her alle vil blev kunne ind når være dog noget ville jo \
+            deres efter ned skulle denne end dette mit også under have dig anden hende mine \
+            alt meget sit sine vor mod disse hvis din nogle hos blive mange ad bliver hendes \
+            været thi jer sådan",
+    },
+    Language {
+        name: "norwegian",
+        algorithm: Algorithm::Norwegian,
+        lang: Lang::Nob,
+        stop_words: "og i jeg det at en et den til er som på de med han av ikke der så var meg \
+            seg men ett har om vi min mitt ha hadde hun nå over da ved fra du ut sin dem oss \
+            opp man kan hans hvor eller hva skal selv her alle vil bli ble blitt kunne inn \
+            når være kom noen noe ville dere deres kun ja etter ned skulle denne for deg si \
+            sine sitt mot å meget hvorfor dette disse uten hvordan ingen din ditt blir samme \
+            hvilken hvilke sånn inni mellom vår hver hvem hvis både bare enn fordi før mange \
+            også slik vært",
+    },
+    Language {
+        name: "finnish",
+        algorithm: Algorithm::Finnish,
+        lang: Lang::Fin,
+        stop_words: "olla olen olet on olemme olette ovat ole oli olin olit olimme olitte olivat \
+            olisi ollut ja se sen sitä että joka jotka mutta kun niin jos tai kuin myös vain \
+            sekä nyt sitten jo vielä ei en et emme ette eivät minä sinä hän me te he tämä \
+            tuo mikä mitä kuka ne nämä nuo siis kanssa mukaan koska hyvin kaikki itse ennen \
+            jälkeen yli alla",
+    },
+    Language {
+        name: "hungarian",
+        algorithm: Algorithm::Hungarian,
+        lang: Lang::Hun,
+        stop_words: "a az egy és hogy nem is de ha mint már csak meg még volt van vagy ami amely \
+            aki azt ezt ez azok ezek mert után között el fel le ki be sem sok igen lesz lett \
+            lehet kell kellett mind minden én te ő mi ti ők nagyon pedig vagyok vannak voltak \
+            vele neki őket akkor most itt ott így úgy",
+    },
+    Language {
+        name: "romanian",
+        algorithm: Algorithm::Romanian,
+        lang: Lang::Ron,
+        stop_words: "și în la de cu pe din care este sunt nu o un una unei unui ca că să se mai \
+            dar sau pentru fi fost a al ale ai lui lor ei el ea noi voi ele acest această \
+            acesta aceasta acestea acei aceste ce cum când unde dacă prin după între până \
+            foarte tot toate toți am are au avea fie",
+    },
+    Language {
+        name: "turkish",
+        algorithm: Algorithm::Turkish,
+        lang: Lang::Tur,
+        stop_words: "ve bir bu da de için ile ne o şu ama ancak gibi daha çok en her ki mi mı mu \
+            mü kadar değil ben sen biz siz onlar diye olan olarak ise ya veya hem sonra önce \
+            şey bunu bunun onun şöyle böyle nasıl neden niçin hiç tüm bazı göre kendi",
+    },
+    Language {
+        name: "greek",
+        algorithm: Algorithm::Greek,
+        lang: Lang::Ell,
+        stop_words: "και το η ο τα του της των τον την σε στο στη στην στον στα με για από που \
+            δεν θα να είναι ήταν ένα μια μία ένας αυτό αυτή αυτός αυτά αλλά ή ως όπως όταν αν \
+            μη μην πιο οι τους τις τι ότι έχει έχουν πολύ επί κατά μετά προς χωρίς ενώ",
+    },
+    Language {
+        name: "arabic",
+        algorithm: Algorithm::Arabic,
+        lang: Lang::Ara,
+        stop_words: "في من على إلى عن مع هذا هذه ذلك تلك التي الذي الذين هو هي هم أن إن كان \
+            كانت لا ما لم لن قد ثم أو كل بعد قبل بين عند حتى أي غير إذا منذ لكن أيضا هناك نحن \
+            أنا أنت",
+    },
+    Language {
+        name: "tamil",
+        algorithm: Algorithm::Tamil,
+        lang: Lang::Tam,
+        stop_words: "ஒரு மற்றும் இது அது இந்த அந்த என்று என்ற ஆனால் அல்லது உள்ள உள்ளது \
+            இருந்து இருக்கும் என்பது போன்ற மேலும் பல அவர் அவர்கள் நான் நாம் நீ இங்கு அங்கு \
+            எந்த எல்லா ஆகிய வரை பின்னர் முன்",
+    },
+];
+
+/// Stop words without diacritics, so they're found however they're typed.
+static STOP_WORDS: Lazy<HashMap<&'static str, HashSet<String>>> = Lazy::new(|| {
+    LANGUAGES
+        .iter()
+        .map(|l| {
+            let words = l.stop_words.split_whitespace();
+            (l.name, words.map(|word| fold_with(word, true)).collect())
+        })
+        .collect()
+});
+
+/// The languages `language = "auto"` picks between.
+static DETECTABLE: Lazy<Vec<&'static Language>> = Lazy::new(|| {
+    CONF.analyzer
+        .detect_languages
+        .iter()
+        .filter_map(|name| language(name))
+        .collect()
+});
+
+static DETECTOR: Lazy<Detector> =
+    Lazy::new(|| Detector::with_allowlist(DETECTABLE.iter().map(|l| l.lang).collect()));
+
+/// How much of a document is looked at to work out its language.
+const DETECTION_SAMPLE: usize = 4096;
+
+pub fn language(name: &str) -> Option<&'static Language> {
+    LANGUAGES
+        .iter()
+        .find(|l| l.name.eq_ignore_ascii_case(name.trim()))
+}
+
+pub fn is_auto(name: &str) -> bool {
+    name.trim().eq_ignore_ascii_case("auto")
+}
+
+impl Analyzer {
+    /// With no language, words are only filtered by length.
+    pub fn new(language: Option<&'static Language>) -> Self {
+        Self::with(
+            language,
+            CONF.analyzer.stemming,
+            CONF.analyzer.stop_words,
+            CONF.tokenizer.strip_diacritics,
+        )
+    }
+
+    pub fn with(
+        language: Option<&'static Language>,
+        stemming: bool,
+        stop_words: bool,
+        strip_diacritics: bool,
+    ) -> Self {
+        let stemmer = match language {
+            Some(language) if stemming => Some(Stemmer::create(language.algorithm)),
+            _ => None,
+        };
+
+        Self {
+            language,
+            stemmer,
+            remove_stop_words: stop_words,
+            strip_diacritics,
+        }
+    }
+
+    /// For the language set in the config, or the language `text` is
+    /// detected to be in. Text that can't be told apart reliably is taken
+    /// to be in the first of the detectable languages.
+    pub fn for_document(text: &str) -> Self {
+        if !is_auto(&CONF.analyzer.language) {
+            return Self::new(language(&CONF.analyzer.language));
+        }
+
+        let end = text
+            .char_indices()
+            .nth(DETECTION_SAMPLE)
+            .map_or(text.len(), |(i, _)| i);
+
+        let detected = DETECTOR
+            .detect(&text[..end])
+            .filter(|info| info.is_reliable())
+            .and_then(|info| DETECTABLE.iter().find(|l| l.lang == info.lang()));
+
+        Self::new(detected.or(DETECTABLE.first()).copied())
+    }
+
+    /// The terms a lowercase `token` is indexed as, if it's worth indexing
+    /// at all. Stemmers know the diacritics of their language, so they're
+    /// only stripped from the stem. The stem of the token without them is
+    /// kept as well, since that's all a query typed without them can give.
+    pub fn terms(&self, token: &str) -> Vec<String> {
+        let stripped = fold_with(token, true);
+        let stop_words = self.stop_words();
+
+        // Without a stop word list, three letter words are mostly things
+        // like "the" and "and".
+        let min_length = if stop_words.is_some() { 3 } else { 4 };
+        if stripped.len() < min_length || stripped.len() >= 32 || is_numeric(token) {
+            return vec![];
+        }
+
+        if stop_words.is_some_and(|words| words.contains(&stripped)) {
+            return vec![];
+        }
+
+        let stemmer = match &self.stemmer {
+            Some(stemmer) => stemmer,
+            None => return vec![fold_with(token, self.strip_diacritics)],
+        };
+
+        let mut terms = vec![fold_with(&stemmer.stem(token), self.strip_diacritics)];
+        if self.strip_diacritics && stripped != token {
+            let stem = stemmer.stem(&stripped).into_owned();
+            if stem != terms[0] {
+                terms.push(stem);
+            }
+        }
+
+        terms
+    }
+
+    fn stop_words(&self) -> Option<&'static HashSet<String>> {
+        if !self.remove_stop_words {
+            return None;
+        }
+
+        STOP_WORDS.get(self.language?.name)
+    }
+}
+
+/// Documents can be in any of the detectable languages so queries are
+/// analysed as each of them.
+fn query_analyzers() -> Vec<Analyzer> {
+    if !is_auto(&CONF.analyzer.language) {
+        return vec![Analyzer::new(language(&CONF.analyzer.language))];
+    }
+
+    match DETECTABLE.len() {
+        0 => vec![Analyzer::new(None)],
+        _ => DETECTABLE.iter().map(|l| Analyzer::new(Some(l))).collect(),
+    }
+}
+
+static QUERY_ANALYZERS: Lazy<Vec<Analyzer>> = Lazy::new(query_analyzers);
+
+/// Every term a word in a query could have been indexed as.
+pub fn query_terms(token: &str) -> Vec<String> {
+    let mut terms = QUERY_ANALYZERS
+        .iter()
+        .flat_map(|analyzer| analyzer.terms(token))
+        .collect::<Vec<String>>();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn english() {
+        let analyzer = Analyzer::with(language("English"), true, true, true);
+
+        assert_eq!(analyzer.terms("running"), vec!["run"]);
+        assert_eq!(analyzer.terms("runs"), vec!["run"]);
+        assert_eq!(analyzer.terms("run"), vec!["run"]);
+        assert!(analyzer.terms("the").is_empty());
+        assert!(analyzer.terms("because").is_empty());
+        assert!(analyzer.terms("2024").is_empty());
+    }
+
+    #[test]
+    fn without_language() {
+        let analyzer = Analyzer::with(None, true, true, true);
+
+        assert_eq!(analyzer.terms("running"), vec!["running"]);
+        assert_eq!(analyzer.terms("because"), vec!["because"]);
+        assert_eq!(analyzer.terms("réunion"), vec!["reunion"]);
+        assert!(analyzer.terms("the").is_empty());
+        assert!(language("klingon").is_none());
+    }
+
+    #[test]
+    fn diacritics() {
+        let analyzer = Analyzer::with(language("german"), false, true, true);
+        assert!(analyzer.terms("über").is_empty());
+        assert!(analyzer.terms("uber").is_empty());
+        assert_eq!(analyzer.terms("besprechung"), vec!["besprechung"]);
+
+        // Stemmed with its accent, and as typed without it.
+        let analyzer = Analyzer::with(language("spanish"), true, true, true);
+        assert_eq!(analyzer.terms("información"), vec!["inform", "informacion"]);
+        assert_eq!(analyzer.terms("informacion"), vec!["informacion"]);
+
+        let analyzer = Analyzer::with(language("spanish"), true, true, false);
+        assert_eq!(analyzer.terms("información"), vec!["inform"]);
+    }
+
+    #[test]
+    fn stop_word_lists() {
+        assert!(LANGUAGES.iter().all(|l| !STOP_WORDS[l.name].is_empty()));
+    }
+}
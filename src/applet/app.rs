@@ -28,9 +28,11 @@ use gtk::prelude::*;
 static CONTROL: AtomicBool = AtomicBool::new(false);
 
 use glimpse::{
-    biases::increment_bias,
+    biases::record_selection,
+    calibration::{self, CALIBRATION},
     config::{CONF, CONF_FILE_PATH, CSS},
     file_index::FileIndex,
+    filters,
 };
 use preview_window::{PreviewWindowShowing, SafeBox};
 use search_modules::{SearchModule, SearchResult};
@@ -50,6 +52,8 @@ pub static SEARCH_MODULES: Lazy<Vec<Box<dyn SearchModule + Sync + Send>>> =
     Lazy::new(|| search_modules::load_standard_modules(RUNTIME.clone()));
 
 pub static FAKE_FIRST_SELECTED: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::new(false));
+/// What was searched for last, for learning what gets selected for it.
+static QUERY: Mutex<String> = Mutex::new(String::new());
 
 pub type BoxedRuntime = Arc<Mutex<tokio::runtime::Runtime>>;
 
@@ -103,6 +107,10 @@ pub fn run_app() {
 
         let search_field = gtk::Entry::new();
 
+        if CONF.modules.files {
+            add_filter_completion(&search_field);
+        }
+
         let search_field_height = 30;
 
         container.add(&search_field);
@@ -157,7 +165,7 @@ pub fn run_app() {
             create_err_msg(error_title, err, &container);
         }
 
-        if CONF.modules.files && (needs_reindex() || PathBuf::from(&CONF.indexing.location).join("full_index_temp").exists()) {
+        if CONF.modules.files && files_being_indexed() {
             println!("locked");
             let box_ = gtk::Box::new(gtk::Orientation::Vertical, 0);
             let label = gtk::Label::new(Some("ⓘ Files are currently being indexed.\nPlease wait before searching for files."));
@@ -332,6 +340,65 @@ pub fn run_app() {
     application.run();
 }
 
+/// Offers filters like "ext:pdf" once "ext:" has been typed.
+fn add_filter_completion(search_field: &gtk::Entry) {
+    let store = gtk::ListStore::new(&[String::static_type(), String::static_type()]);
+    for (filter, description) in filters::completions() {
+        store.set(&store.append(), &[(0, &filter), (1, &description)]);
+    }
+
+    let completion = gtk::EntryCompletion::new();
+    completion.set_model(Some(&store));
+    completion.set_text_column(0);
+    completion.set_minimum_key_length(1);
+
+    let description = gtk::CellRendererText::new();
+    description.set_sensitive(false);
+    completion.pack_start(&description, true);
+    completion.add_attribute(&description, "text", 1);
+
+    completion.set_match_func(|completion, _, iter| {
+        let (Some(entry), Some(model)) = (completion.entry(), completion.model()) else {
+            return false;
+        };
+
+        let filter = model.value(iter, 0).get::<String>().unwrap_or_default();
+        filters::completes(last_word(&entry.text()), &filter)
+    });
+
+    // Only the word being typed is replaced rather than the whole query.
+    completion.connect_match_selected(|completion, model, iter| {
+        if let Some(entry) = completion.entry() {
+            let filter = model.value(iter, 0).get::<String>().unwrap_or_default();
+            let text = entry.text();
+            let start = text.len() - last_word(&text).len();
+
+            entry.set_text(&format!("{}{} ", &text[..start], filter));
+            entry.set_position(-1);
+        }
+
+        pango::glib::Propagation::Stop
+    });
+
+    search_field.set_completion(Some(&completion));
+}
+
+fn last_word(text: &str) -> &str {
+    text.rsplit(char::is_whitespace).next().unwrap_or(text)
+}
+
+fn files_being_indexed() -> bool {
+    let db_path = PathBuf::from(&CONF.indexing.location);
+
+    if CONF.scheduler.daemon {
+        // The daemon leaves the previous index searchable while it works,
+        // unless it has to be rebuilt in a new format.
+        return !db_path.join("files").exists() || FileIndex::is_outdated(&db_path);
+    }
+
+    needs_reindex() || db_path.join("full_index_temp").exists()
+}
+
 type PrevWindow = Arc<tokio::sync::Mutex<preview_window::PreviewWindow>>;
 
 fn connect_preview_events(
@@ -461,14 +528,23 @@ fn perform_search(
     current_task_handle: Arc<Mutex<Vec<AbortHandle>>>,
     rt: BoxedRuntime,
 ) {
+    *QUERY.lock().unwrap() = query.clone();
+
     for module in SEARCH_MODULES.iter() {
         let f = module.search(query.clone(), 10);
         let list = list.clone();
         let (task, handle) = abortable(async move {
             let search_time_benchmark = benchmark();
 
-            let results = f.await;
-            append_results(results, list.clone()).await;
+            let module_name = module.config_name();
+            let mut results = f.await;
+            {
+                let calibration = CALIBRATION.read().unwrap();
+                for result in &mut results {
+                    result.relevance = calibration.calibrate(&module_name, result.relevance);
+                }
+            }
+            append_results(results, list.clone(), module_name).await;
 
             if let Some(time) = search_time_benchmark {
                 println!(
@@ -484,12 +560,14 @@ fn perform_search(
 }
 
 fn perform_entry_action(row: gtk::ListBoxRow) {
+    let shown = shown_modules(&row);
     use_entry_data(
         &row,
-        Box::new(|data| {
+        Box::new(move |data| {
             if let Some(action) = data.action.as_ref() {
                 action();
-                increment_bias(data.id, 0.5);
+                record_selection(data.id, &QUERY.lock().unwrap());
+                calibration::record_selection(&shown);
 
                 FileIndex::remove_all_locks();
                 std::process::exit(0);
@@ -498,39 +576,45 @@ fn perform_entry_action(row: gtk::ListBoxRow) {
     );
 }
 
-fn get_entry_id(widget: &gtk::Widget) -> u64 {
-    unsafe {
-        if let Some(data_ptr) = widget.steal_data::<*mut ResultData>("dat") {
-            let data = Box::from_raw(data_ptr);
+/// The modules of the results from the top of the list down to `row`.
+fn shown_modules(row: &gtk::ListBoxRow) -> Vec<String> {
+    let list = match row.parent() {
+        Some(list) => list,
+        None => return vec![],
+    };
+    let list = match list.downcast_ref::<gtk::ListBox>() {
+        Some(list) => list,
+        None => return vec![],
+    };
+
+    (0..=row.index())
+        .filter_map(|index| list.row_at_index(index))
+        .filter_map(|row| get_entry_module(row.upcast_ref()))
+        .collect()
+}
 
-            let id = data.id;
+/// Reads the `ResultData` that `append_results` stored on a row.
+fn with_entry_data<T>(
+    widget: &impl IsA<gtk::glib::Object>,
+    read: impl FnOnce(&ResultData) -> T,
+) -> Option<T> {
+    unsafe {
+        let data_ptr = *widget.data::<*mut ResultData>("dat")?.as_ref();
+        Some(read(&*data_ptr))
+    }
+}
 
-            let data_ptr = Box::into_raw(data);
-            widget.set_data("dat", data_ptr);
+fn get_entry_module(widget: &gtk::Widget) -> Option<String> {
+    with_entry_data(widget, |data| data.module.clone())
+}
 
-            id
-        } else {
-            0
-        }
-    }
+fn get_entry_id(widget: &gtk::Widget) -> u64 {
+    with_entry_data(widget, |data| data.id).unwrap_or(0)
 }
 
 #[inline]
 fn get_entry_relevance(widget: gtk::Widget) -> f32 {
-    unsafe {
-        if let Some(data_ptr) = widget.steal_data::<*mut ResultData>("dat") {
-            let data = Box::from_raw(data_ptr);
-
-            let relevance = data.relevance;
-
-            let data_ptr = Box::into_raw(data);
-            widget.set_data("dat", data_ptr);
-
-            relevance
-        } else {
-            0.0
-        }
-    }
+    with_entry_data(&widget, |data| data.relevance).unwrap_or(0.0)
 }
 
 fn free_entry_data(widget: &gtk::Widget) {
@@ -543,14 +627,7 @@ fn free_entry_data(widget: &gtk::Widget) {
 }
 
 fn use_entry_data(widget: &gtk::ListBoxRow, action: Box<dyn Fn(&ResultData)>) {
-    unsafe {
-        if let Some(data_ptr) = widget.steal_data::<*mut ResultData>("dat") {
-            let data = Box::from_raw(data_ptr);
-            action(&data);
-            let data_ptr = Box::into_raw(data);
-            widget.set_data("dat", data_ptr);
-        }
-    }
+    with_entry_data(widget, |data| action(data));
 }
 
 fn handle_search_field_keypress(key: gdk::keys::Key, list: Arc<Mutex<SafeListBox>>) {
@@ -669,7 +746,11 @@ pub struct SafeListBox {
 unsafe impl Send for SafeListBox {}
 unsafe impl Sync for SafeListBox {}
 
-pub async fn append_results(results: Vec<SearchResult>, list: Arc<std::sync::Mutex<SafeListBox>>) {
+pub async fn append_results(
+    results: Vec<SearchResult>,
+    list: Arc<std::sync::Mutex<SafeListBox>>,
+    module: String,
+) {
     let results = results;
     // results.sort_by(|a, b| a.relevance.partial_cmp(&b.relevance).unwrap());
 
@@ -697,6 +778,7 @@ pub async fn append_results(results: Vec<SearchResult>, list: Arc<std::sync::Mut
                 id: result.id,
                 action: result.on_select,
                 preview_window_data: result.preview_window_data,
+                module: module.clone(),
             };
 
             unsafe {
@@ -765,6 +847,8 @@ struct ResultData {
     id: u64,
     action: Option<Box<dyn Fn() + Sync + Send>>,
     preview_window_data: PreviewWindowShowing,
+    /// The config name of the module it came from.
+    module: String,
 }
 
 #[inline]
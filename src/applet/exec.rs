@@ -26,6 +26,19 @@ pub fn execute_detached(name: String) -> Result<(), std::io::Error> {
     Ok(())
 }
 
+/// Runs `command` with "{path}" in it standing for `path`. The path is
+/// passed to the shell as an argument rather than pasted into the command,
+/// so nothing in it is run.
+pub fn execute_detached_with_path(command: &str, path: &str) -> Result<(), std::io::Error> {
+    let mut shell = Command::new("bash");
+    shell.arg("-c");
+    shell.arg(format!("{} & disown", command.replace("{path}", "\"$1\"")));
+    shell.arg("bash");
+    shell.arg(path);
+    shell.execute()?;
+    Ok(())
+}
+
 pub fn write_clipboard(s: &str) -> Result<(), std::io::Error> {
     let mut command = Command::new("xclip");
     command.arg("-sel");
@@ -40,9 +53,5 @@ pub fn write_clipboard(s: &str) -> Result<(), std::io::Error> {
 }
 
 pub fn xdg_open(name: &str) -> Result<(), std::io::Error> {
-    let mut command = Command::new("bash");
-    command.arg("-c");
-    command.arg(format!("xdg-open \"{}\" & disown", name));
-    command.execute()?;
-    Ok(())
+    execute_detached_with_path("xdg-open {path}", name)
 }
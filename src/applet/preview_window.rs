@@ -20,13 +20,19 @@ use std::{
 };
 
 use gdk::gdk_pixbuf;
-use glimpse::config::CONF;
+use glimpse::{
+    config::CONF,
+    extract::{html, media},
+    snippet::Snippet,
+};
 use gtk::traits::{ContainerExt, GridExt, LabelExt, ScrolledWindowExt, StyleContextExt, WidgetExt};
 use pango::{glib::idle_add_once, WrapMode};
 // use poppler::PopplerDocument;
 
 use glimpse::prelude::*;
 
+use crate::result_templates::snippet_markup;
+
 pub struct PreviewWindow {
     pub container: Arc<Mutex<SafeBox>>,
     pub showing: PreviewWindowShowing,
@@ -39,6 +45,15 @@ unsafe impl Send for PreviewWindow {}
 pub enum PreviewWindowShowing {
     None,
     File(PathBuf),
+    /// A file that matched on its contents.
+    FileWithMatches(PathBuf, Matches),
+}
+
+#[derive(PartialEq, Clone)]
+pub struct Matches {
+    pub snippets: Vec<Snippet>,
+    /// Text files are previewed from the first line containing this.
+    pub first_match: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -57,19 +72,31 @@ impl PreviewWindow {
 
         match self.showing.clone() {
             PreviewWindowShowing::None => unreachable!(),
-            PreviewWindowShowing::File(path) => {
-                if let Some(prev) = create_file_preview(path.clone()).await {
-                    let container_cpy = self.container.clone();
-                    idle_add_once(move || {
-                        create_file_preview_widget(container_cpy, prev, path);
-                    });
-                } else {
-                    self.hide();
-                }
+            PreviewWindowShowing::File(path) => self.show_file(path, None).await,
+            PreviewWindowShowing::FileWithMatches(path, matches) => {
+                self.show_file(path, Some(matches)).await
             }
         }
     }
 
+    async fn show_file(&mut self, path: PathBuf, matches: Option<Matches>) {
+        let first_match = matches.as_ref().and_then(|m| m.first_match.clone());
+        let prev = create_file_preview(path.clone(), first_match.as_deref()).await;
+        let metadata = media::read(&path);
+
+        // Files like PDFs that can't be previewed still show their matches,
+        // and music and videos their tags.
+        if prev.is_none() && matches.is_none() && metadata.is_none() {
+            self.hide();
+            return;
+        }
+
+        let container_cpy = self.container.clone();
+        idle_add_once(move || {
+            create_file_preview_widget(container_cpy, matches, prev, metadata, path);
+        });
+    }
+
     pub fn hide(&mut self) {
         let container = self.container.clone();
         idle_add_once(move || {
@@ -85,7 +112,9 @@ impl PreviewWindow {
 
 fn create_file_preview_widget(
     container_cpy: Arc<Mutex<SafeBox>>,
-    prev: PreviewWindowContents,
+    matches: Option<Matches>,
+    prev: Option<PreviewWindowContents>,
+    metadata: Option<media::Metadata>,
     path: PathBuf,
 ) -> Option<()> {
     let container = container_cpy.clone();
@@ -95,13 +124,24 @@ fn create_file_preview_widget(
         container.container.remove(child);
     });
 
-    let prev = prev.clone();
+    if let Some(matches) = matches {
+        container.container.add(&matches_section(&matches));
+    }
 
-    container.container.add(&match prev {
-        PreviewWindowContents::Image(path) => load_image(&path)?,
-        PreviewWindowContents::TextFile(text) => plain_text_preview(text),
-        PreviewWindowContents::Directory(path) => dir_listing(&path),
-    });
+    let widget = match prev {
+        Some(PreviewWindowContents::Image(path)) => load_image(&path),
+        Some(PreviewWindowContents::TextFile(text)) => Some(plain_text_preview(text)),
+        Some(PreviewWindowContents::Directory(path)) => Some(dir_listing(&path)),
+        None => None,
+    };
+
+    if let Some(widget) = widget {
+        container.container.add(&widget);
+    }
+
+    if let Some(metadata) = metadata {
+        container.container.add(&metadata_section(&metadata));
+    }
 
     let label = gtk::Label::new(Some(path.to_str().unwrap()));
     label.set_line_wrap(true);
@@ -129,22 +169,78 @@ async fn trunc_long_lines(text: String) -> String {
     new_text
 }
 
-async fn create_file_preview(path: PathBuf) -> Option<PreviewWindowContents> {
+async fn create_file_preview(
+    path: PathBuf,
+    first_match: Option<&str>,
+) -> Option<PreviewWindowContents> {
     if path.is_dir() {
         return Some(PreviewWindowContents::Directory(path.clone()));
     }
 
-    if let Some(widget) = try_from_infer(&path).await {
+    if let Some(widget) = create_html_preview(&path).await {
+        return Some(widget);
+    }
+
+    if let Some(widget) = try_from_infer(&path, first_match).await {
         return Some(widget);
     }
 
-    if let Some(widget) = create_plain_text_file_preview(&path).await {
+    if let Some(widget) = create_plain_text_file_preview(&path, first_match).await {
         return Some(widget);
     }
 
     None
 }
 
+/// The passages of the file that matched the query, above the preview.
+fn matches_section(matches: &Matches) -> gtk::Label {
+    let markup = matches
+        .snippets
+        .iter()
+        .map(snippet_markup)
+        .collect::<Vec<String>>()
+        .join("\n\n");
+
+    let label = gtk::Label::new(None);
+    label.set_markup(&markup);
+    label.set_line_wrap(true);
+    label.set_wrap_mode(WrapMode::WordChar);
+    label.set_max_width_chars(40);
+    label.set_halign(gtk::Align::Start);
+    label.set_xalign(0.);
+    label.set_margin_bottom(10);
+    label.style_context().add_class("preview-matches");
+    label
+}
+
+/// Camera, date taken and such below photos, and tags for music and videos.
+fn metadata_section(metadata: &media::Metadata) -> gtk::Grid {
+    let grid = gtk::Grid::new();
+    grid.set_column_spacing(8);
+    grid.set_margin_top(6);
+    grid.set_margin_bottom(6);
+    grid.style_context().add_class("preview-metadata");
+
+    for (row, (label, value)) in metadata.fields.iter().enumerate() {
+        let label = gtk::Label::new(Some(label));
+        label.set_halign(gtk::Align::End);
+        label.set_valign(gtk::Align::Start);
+        label.style_context().add_class("dim-label");
+
+        let value = gtk::Label::new(Some(value));
+        value.set_line_wrap(true);
+        value.set_wrap_mode(WrapMode::WordChar);
+        value.set_max_width_chars(30);
+        value.set_halign(gtk::Align::Start);
+        value.set_xalign(0.);
+
+        grid.attach(&label, 0, row as i32, 1, 1);
+        grid.attach(&value, 1, row as i32, 1, 1);
+    }
+
+    grid
+}
+
 fn dir_listing(path: &Path) -> gtk::Box {
     let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
     let dir = path.read_dir();
@@ -171,11 +267,14 @@ fn dir_listing(path: &Path) -> gtk::Box {
     outer_container
 }
 
-async fn try_from_infer(path: &PathBuf) -> Option<PreviewWindowContents> {
+async fn try_from_infer(
+    path: &PathBuf,
+    first_match: Option<&str>,
+) -> Option<PreviewWindowContents> {
     let kind = infer::get_from_path(path).ok()??;
 
     if kind.mime_type().starts_with("text") {
-        create_plain_text_file_preview(path).await?;
+        create_plain_text_file_preview(path, first_match).await?;
     }
 
     if kind.mime_type().starts_with("image") {
@@ -199,8 +298,51 @@ fn load_image(path: &Path) -> Option<gtk::Box> {
     Some(container)
 }
 
-async fn create_plain_text_file_preview(path: &Path) -> Option<PreviewWindowContents> {
-    let text = tokio::fs::read_to_string(path.to_str()?).await.ok()?;
+async fn create_plain_text_file_preview(
+    path: &Path,
+    first_match: Option<&str>,
+) -> Option<PreviewWindowContents> {
+    let mut text = tokio::fs::read_to_string(path.to_str()?).await.ok()?;
+
+    if let Some(first_match) = first_match {
+        text = from_first_match(text, first_match);
+    }
+
+    let text = trunc_long_lines(text).await;
+
+    let text = text.trunc(7000);
+
+    Some(PreviewWindowContents::TextFile(text))
+}
+
+/// Skips to a few lines before the first line containing `word`.
+fn from_first_match(text: String, word: &str) -> String {
+    const LINES_BEFORE: usize = 3;
+
+    let word = word.to_lowercase();
+    let line = match text.lines().position(|l| l.to_lowercase().contains(&word)) {
+        Some(line) => line,
+        None => return text,
+    };
+
+    let start = line.saturating_sub(LINES_BEFORE);
+    if start == 0 {
+        return text;
+    }
+
+    let rest = text.lines().skip(start).collect::<Vec<&str>>().join("\n");
+    format!("…\n{}", rest)
+}
+
+/// Shows pages and Markdown without their markup.
+async fn create_html_preview(path: &Path) -> Option<PreviewWindowContents> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+
+    let text = match ext.as_str() {
+        "html" | "htm" | "xhtml" | "shtml" => html::preview_text(&html::read(path)?),
+        "md" | "markdown" => html::parse(&tokio::fs::read_to_string(path).await.ok()?).body,
+        _ => return None,
+    };
 
     let text = trunc_long_lines(text).await;
 
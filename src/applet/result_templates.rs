@@ -14,18 +14,108 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use gtk::traits::{ContainerExt, GridExt, StyleContextExt, WidgetExt};
+use glimpse::{snippet::Snippet, string_similarity::match_positions};
+use gtk::{
+    glib::markup_escape_text,
+    traits::{ContainerExt, GridExt, LabelExt, StyleContextExt, WidgetExt},
+};
+use pango::EllipsizeMode;
 
 use glimpse::prelude::*;
 
+/// An entry with the characters of its name that matched `query` in bold.
 pub fn standard_entry(
     name: String,
     icon: Option<gtk::Image>,
     description: Option<String>,
+    query: &str,
 ) -> gtk::Box {
-    let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    let description_label = description.map(|description| {
+        let description = description.trunc(40);
+        gtk::Label::with_mnemonic(&description)
+    });
+
+    entry(name, icon, description_label, query)
+}
+
+/// An entry with the passage of a file that matched the query as its
+/// description.
+pub fn snippet_entry(
+    name: String,
+    icon: Option<gtk::Image>,
+    snippet: &Snippet,
+    query: &str,
+) -> gtk::Box {
+    let description_label = gtk::Label::new(None);
+    description_label.set_markup(&snippet_markup(snippet));
+    description_label.set_ellipsize(EllipsizeMode::End);
+    description_label.set_max_width_chars(40);
+
+    entry(name, icon, Some(description_label), query)
+}
+
+/// Pango markup for `snippet` with the matching words in bold.
+pub fn snippet_markup(snippet: &Snippet) -> String {
+    let mut markup = String::new();
+    let mut position = 0;
+
+    for highlight in &snippet.highlights {
+        let before = &snippet.text[position..highlight.start];
+        markup.push_str(&markup_escape_text(before));
+        markup.push_str("<b>");
+        markup.push_str(&markup_escape_text(&snippet.text[highlight.clone()]));
+        markup.push_str("</b>");
+        position = highlight.end;
+    }
+    markup.push_str(&markup_escape_text(&snippet.text[position..]));
+
+    markup
+}
+
+/// Pango markup for `text` with the characters at `positions` in bold.
+pub fn highlighted_markup(text: &str, positions: &[usize]) -> String {
+    let mut markup = String::new();
+    let mut bold = false;
+
+    for (i, c) in text.chars().enumerate() {
+        let highlighted = positions.binary_search(&i).is_ok();
+        if highlighted != bold {
+            markup.push_str(if highlighted { "<b>" } else { "</b>" });
+            bold = highlighted;
+        }
+        markup.push_str(&markup_escape_text(c.encode_utf8(&mut [0; 4])));
+    }
+
+    if bold {
+        markup.push_str("</b>");
+    }
+
+    markup
+}
+
+/// A small label at the end of an entry, e.g. "+2 copies".
+pub fn add_badge(entry: &gtk::Box, text: &str) {
+    let badge = gtk::Label::new(Some(text));
+    badge.set_hexpand(true);
+    badge.set_halign(gtk::Align::End);
+    badge.set_valign(gtk::Align::Center);
+    badge.set_margin_end(10);
+    badge.set_opacity(0.6);
+    badge.style_context().add_class("result-badge");
+    entry.add(&badge);
+}
+
+fn entry(
+    name: String,
+    icon: Option<gtk::Image>,
+    description: Option<gtk::Label>,
+    query: &str,
+) -> gtk::Box {
+    let container = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    let highlights = match_positions(query, &name);
     let name = name.trunc(40);
-    let label = gtk::Label::with_mnemonic(&name);
+    let label = gtk::Label::new(None);
+    label.set_markup(&highlighted_markup(&name, &highlights));
     label.style_context().add_class("result-title");
     label.set_halign(gtk::Align::Start);
 
@@ -34,9 +124,7 @@ pub fn standard_entry(
     let text_container = gtk::Box::new(gtk::Orientation::Vertical, 0);
     text_container.add(&label);
 
-    if let Some(description) = description {
-        let description = description.trunc(40);
-        let description_label = gtk::Label::with_mnemonic(&description);
+    if let Some(description_label) = description {
         description_label.set_halign(gtk::Align::Start);
         description_label.set_opacity(0.6);
         description_label
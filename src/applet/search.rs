@@ -14,7 +14,11 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use glimpse::string_similarity::word_similarity;
+use glimpse::{
+    normalize::normalize,
+    query::{self, Query},
+    string_similarity::{word_similarity, MIN_SIMILARITY},
+};
 
 use crate::utils::HashFn;
 
@@ -31,19 +35,21 @@ pub fn string_search(
         return results;
     }
 
-    let mut needle = needle.to_string();
-    if !case_sensitive {
-        needle = needle.to_lowercase();
-    }
+    let query = match query::parse(needle) {
+        Some(query) => query,
+        None => return results,
+    };
 
     let mut worst_sim: f32 = 0.0;
     for item_cased in haystack {
         let mut item = item_cased.clone();
         if !case_sensitive {
-            item = item.to_lowercase();
+            item = normalize(&item);
         }
 
-        let similarity = word_similarity(&needle, item, id_hash);
+        let similarity = query
+            .evaluate(&mut |term| term_similarity(term, &item, id_hash, case_sensitive))
+            .unwrap_or(0.);
 
         if results.len() < max_results as usize || similarity > worst_sim {
             if similarity == 0.0 {
@@ -62,6 +68,33 @@ pub fn string_search(
 
     results
         .into_iter()
-        .filter(|(_, relevance)| *relevance > 0.7)
+        .filter(|(_, relevance)| *relevance > MIN_SIMILARITY)
         .collect()
 }
+
+/// How well `item` matches a word or phrase of the query, if at all. Phrases
+/// have to be in it as they are.
+fn term_similarity(
+    term: &Query,
+    item: &str,
+    id_hash: &HashFn,
+    case_sensitive: bool,
+) -> Option<f32> {
+    let (text, is_phrase) = match term {
+        Query::Word(word) => (word, false),
+        Query::Phrase(phrase) => (phrase, true),
+        _ => return None,
+    };
+
+    let text = match case_sensitive {
+        true => text.clone(),
+        false => normalize(text),
+    };
+
+    if is_phrase && !item.contains(&text) {
+        return None;
+    }
+
+    let similarity = word_similarity(&text, item.to_string(), id_hash);
+    (similarity > MIN_SIMILARITY).then_some(similarity)
+}
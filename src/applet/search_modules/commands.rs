@@ -65,8 +65,9 @@ impl Commands {
         Commands { apps: apps_store }
     }
 
-    fn create_result(&self, name: String, relevance: f32) -> SearchResult {
+    fn create_result(&self, name: String, relevance: f32, query: &str) -> SearchResult {
         let name_cpy = name.clone();
+        let query = query.to_string();
         let render = move || {
             let name = name_cpy.clone();
 
@@ -77,7 +78,7 @@ impl Commands {
             } else {
                 None
             };
-            standard_entry(name, icon, desc)
+            standard_entry(name, icon, desc, &query)
         };
 
         let hash = simple_hash_nonce(&self.name());
@@ -111,7 +112,7 @@ impl SearchModule for Commands {
         if let Some(apps) = lock.as_ref() {
             string_search(&query, apps, max_results, &hash_fn, true)
                 .into_iter()
-                .map(|(s, r)| self.create_result(s, r * 1.4 + 0.3))
+                .map(|(s, r)| self.create_result(s, r, &query))
                 .collect()
         } else {
             vec![]
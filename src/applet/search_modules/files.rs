@@ -14,28 +14,49 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 
 use async_trait::async_trait;
 use glimpse::{
+    analyzer::query_terms,
+    archive,
     config::CONF,
-    file_index::{tokenize_string, FileIndex, FILE_DB_READ},
+    extract::{cache, mail},
+    file_index::{metadata_of, tokenize_string, FileIndex, MetadataMap, FILE_DB_READ},
+    filters::{self, Filters},
+    normalize::{fold, normalize},
+    path_match::{ancestor_token_matches, ancestors_match, components_match, query_segments},
     prelude::*,
-    tfidf::_tf_idf,
+    query::{self, Query},
+    snippet::{self, Snippet},
+    string_similarity::{word_similarity, MIN_SIMILARITY},
+    tfidf::{bm25, phrase_search, proximity, split_phrases},
+    tokenizer::split_terms,
 };
 
 use crate::{
     app::BoxedRuntime,
-    exec::{execute_detached, xdg_open},
+    exec::{execute_detached, execute_detached_with_path, xdg_open},
     icon,
-    result_templates::standard_entry,
-    utils::{benchmark, needs_reindex, simple_hash_nonce},
+    preview_window::{Matches, PreviewWindowShowing},
+    result_templates::{add_badge, snippet_entry, standard_entry},
+    utils::{benchmark, needs_reindex, simple_hash_nonce, HashFn},
 };
 
 use super::{SearchModule, SearchResult};
 
+type TextCache = Mutex<HashMap<String, Arc<String>>>;
+/// The paths each normalized word and phrase of a query was found in, by
+/// name or contents.
+type Contains = HashMap<String, HashSet<String>>;
+
 pub struct Files {
     index: Arc<tokio::sync::Mutex<Option<FileIndex>>>,
+    texts: TextCache,
 }
 
 #[derive(Debug)]
@@ -44,25 +65,247 @@ enum FileType {
     Dir,
 }
 
+struct ContentMatch {
+    subtitle: Snippet,
+    matches: Matches,
+}
+
 #[derive(Debug)]
 struct FileResult {
     relevance: f32,
     kind: FileType,
+    /// Other files with the same contents, shown as a badge.
+    copies: usize,
+}
+
+const ANCESTOR_MATCH_BONUS: f32 = 1.5;
+/// BM25 scores are between 0 and 1, this puts a perfect content match on
+/// par with a good file name match.
+const CONTENT_MATCH_WEIGHT: f32 = 2.;
+/// For documents with the words of the query close together.
+const PROXIMITY_WEIGHT: f32 = 1.;
+
+/// How many of the best content matches get snippets.
+const SNIPPET_RESULTS: usize = 8;
+const SUBTITLE_SNIPPET_WIDTH: usize = 60;
+const PREVIEW_SNIPPETS: usize = 3;
+const PREVIEW_SNIPPET_WIDTH: usize = 200;
+const MAX_CACHED_TEXTS: usize = 32;
+const PATH_MATCH_BONUS: f32 = 2.0;
+/// Duplicate sets listed by "dupes:" rank above anything else matching the
+/// query.
+const DUPES_RELEVANCE: f32 = 5.;
+/// How many of the indexed files and folders, newest first, are looked
+/// through for queries that are only filters, like "ext:pdf modified:<7d".
+const MAX_BROWSED: usize = 100_000;
+
+fn push(files: &mut HashMap<String, FileResult>, s: &str, r: f32, kind: FileType) {
+    let s = s.to_string();
+
+    if let Some(res) = files.get_mut(&s) {
+        res.relevance += r;
+        return;
+    }
+
+    files.insert(
+        s,
+        FileResult {
+            relevance: r,
+            kind,
+            copies: 0,
+        },
+    );
+}
+
+/// Whether `path` passes `filters`, going by the metadata stored in the
+/// index rather than reading it from disk.
+fn passes(filters: &Filters, metadata: &mut MetadataMap, path: &str, is_dir: bool) -> bool {
+    filters.matches(path, is_dir, || metadata_of(metadata, path))
+}
+
+/// Looks `word` up by file and folder name. Only what passes `filters`
+/// counts towards the number of matches looked up.
+fn search_names(
+    index: &mut FileIndex,
+    word: &str,
+    hash_fn: &HashFn,
+    filters: &Filters,
+    files: &mut HashMap<String, FileResult>,
+    contains: &mut Contains,
+) {
+    let found = contains.entry(word.to_string()).or_default();
+    let mut metadata = index.metadata.clone();
+
+    let dirs = index.dirs.get_matching(word, hash_fn, &mut |path| {
+        passes(filters, &mut metadata, path, true)
+    });
+    for (s, r) in dirs {
+        found.insert(s.clone());
+        push(files, &s, r * 1.5, FileType::Dir);
+    }
+
+    let matching_files = index.files.get_matching(word, hash_fn, &mut |path| {
+        passes(filters, &mut metadata, path, false)
+    });
+    for (s, r) in matching_files {
+        found.insert(s.clone());
+        push(files, &s, r * 1.4, FileType::File);
+    }
+}
+
+/// Whether the file at `path` matches a query of more than one word or
+/// phrase. Single words are all that results are looked up by.
+fn matches_query(boolean: &Option<Query>, path: &str, contains: &Contains) -> bool {
+    match boolean {
+        Some(boolean) if !boolean.is_term() => {
+            boolean.matches(&mut |term| term_matches(path, term, contains))
+        }
+        _ => true,
+    }
+}
+
+/// Whether the file at `path` has a word or phrase of the query in its path
+/// or, going by `contains`, in its name or contents.
+fn term_matches(path: &str, term: &Query, contains: &Contains) -> bool {
+    let text = match term.text() {
+        Some(text) => text,
+        None => return false,
+    };
+
+    if contains
+        .get(&text)
+        .is_some_and(|paths| paths.contains(path))
+    {
+        return true;
+    }
+
+    if text.contains('/') {
+        return query_segments(&text)
+            .is_some_and(|segments| components_match(Path::new(path), &segments));
+    }
+
+    normalize(path).contains(&text)
+}
+
+/// Handles queries like "proj/src/main". The last segment is looked up by
+/// name and the rest have to match the directories it is in, in order.
+fn search_path(
+    index: &mut FileIndex,
+    segments: &[String],
+    hash_fn: &HashFn,
+    filters: &Filters,
+    files: &mut HashMap<String, FileResult>,
+) {
+    let (name, ancestors) = match segments.split_last() {
+        Some(split) => split,
+        None => return,
+    };
+    let mut metadata = index.metadata.clone();
+
+    index
+        .dirs
+        .get_matching(name, hash_fn, &mut |path| {
+            passes(filters, &mut metadata, path, true)
+        })
+        .into_iter()
+        .filter(|(s, _)| ancestors_match(Path::new(s), ancestors))
+        .for_each(|(s, r)| push(files, &s, r * 1.5 + PATH_MATCH_BONUS, FileType::Dir));
+
+    index
+        .files
+        .get_matching(name, hash_fn, &mut |path| {
+            passes(filters, &mut metadata, path, false)
+        })
+        .into_iter()
+        .filter(|(s, _)| ancestors_match(Path::new(s), ancestors))
+        .for_each(|(s, r)| push(files, &s, r * 1.4 + PATH_MATCH_BONUS, FileType::File));
+
+    // There can be far more files with a common name like "main" than the
+    // index returns so also look inside the directories matching the parent.
+    let parent = match ancestors.last() {
+        Some(parent) => parent,
+        None => return,
+    };
+
+    for (dir, _) in index.dirs.get(parent, hash_fn) {
+        if !components_match(Path::new(&dir), ancestors) {
+            continue;
+        }
+
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.flatten() {
+            let entry_name = entry.file_name().to_str().unwrap_or_default().to_string();
+            let r = word_similarity(name, entry_name, hash_fn);
+            if r < MIN_SIMILARITY {
+                continue;
+            }
+
+            let kind = if entry.path().is_dir() {
+                FileType::Dir
+            } else {
+                FileType::File
+            };
+
+            let path = entry.path().to_str().unwrap_or_default().to_string();
+            push(files, &path, r * 1.4 + PATH_MATCH_BONUS, kind);
+        }
+    }
 }
 
 #[async_trait]
 impl SearchModule for Files {
-    async fn search(&self, mut query: String, _: u32) -> Vec<SearchResult> {
+    async fn search(&self, query: String, _: u32) -> Vec<SearchResult> {
         if query.is_empty() {
             return vec![];
         }
 
-        query = query.to_lowercase();
+        // Filters like "ext:pdf" narrow the results down rather than being
+        // searched for.
+        let (filters, query) = filters::parse(&query);
+        let mut boolean = query::parse(&query);
+
+        // Files are looked up by the words that aren't after a NOT and
+        // then have to match the query as a whole.
+        let searched_text = match &boolean {
+            Some(boolean) => boolean.searched_text(),
+            None => String::new(),
+        };
+        let mut query = normalize(&searched_text);
+        if query.is_empty() {
+            let index = self.index.lock().await;
+            let hash_fn = simple_hash_nonce(std::any::type_name::<Self>());
+            let keep = |path: &str| matches_query(&boolean, path, &Contains::new());
+            return match index.as_ref() {
+                Some(index) => self.browse(index, &filters, keep, &hash_fn),
+                None => vec![],
+            };
+        }
+
+        // Quoted phrases are looked for in the contents as they are, names are
+        // matched against the query without the quotes. The contents are
+        // searched with diacritics, which are only stripped after stemming.
+        let (phrases, unquoted) = split_phrases(&searched_text);
+        query = query.replace('"', "");
+
+        if query.trim().is_empty() {
+            return vec![];
+        }
 
         let mut index = self.index.lock().await;
 
         let hash_fn = simple_hash_nonce(std::any::type_name::<Self>());
 
+        if let Some(filter) = query.strip_prefix("dupes:") {
+            return match index.as_mut() {
+                Some(index) => self.duplicate_results(index, filter, &filters, &hash_fn),
+                None => vec![],
+            };
+        }
+
         if let Some(index) = index.as_mut() {
             // let query = query.to_lowercase();
 
@@ -76,85 +319,145 @@ impl SearchModule for Files {
             //     .map(|(s, r)| self.create_result(&s, r, FileType::Dir, hash_fn(&*s)))
             //     .collect::<Vec<SearchResult>>();
             let mut files: HashMap<String, FileResult> = HashMap::new();
+            let mut contains = Contains::new();
 
-            let push =
-                |files: &mut HashMap<String, FileResult>, s: &String, r: f32, kind: FileType| {
-                    let s = s.clone();
+            let mut tokens = tokenize_string(&query);
+            tokens.dedup();
 
-                    if let Some(res) = files.get_mut(&s) {
-                        res.relevance += r;
-                        return;
-                    }
+            if let Some(segments) = query_segments(&query) {
+                search_path(index, &segments, &hash_fn, &filters, &mut files);
+            } else {
+                search_names(index, &query, &hash_fn, &filters, &mut files, &mut contains);
 
-                    files.insert(s, FileResult { relevance: r, kind });
-                };
+                if tokens.len() > 1 {
+                    for token in &tokens {
+                        search_names(index, token, &hash_fn, &filters, &mut files, &mut contains);
+                    }
 
-            index
-                .dirs
-                .get(&query, &hash_fn)
-                .into_iter()
-                .for_each(|(s, r)| push(&mut files, &s, r * 1.5, FileType::Dir));
+                    // Favour results in folders named after the other words,
+                    // e.g. "work report" for ~/work/2024/report.pdf
+                    for (path, res) in files.iter_mut() {
+                        let matches = ancestor_token_matches(Path::new(path), &tokens);
+                        res.relevance += matches as f32 * ANCESTOR_MATCH_BONUS;
+                    }
+                }
+            }
 
-            index
-                .files
-                .get(&query, &hash_fn)
-                .into_iter()
-                .for_each(|(s, r)| push(&mut files, &s, r * 1.4, FileType::File));
+            // Files matching on their contents, kept apart to show snippets for.
+            let mut content: HashMap<String, FileResult> = HashMap::new();
 
-            let mut tokens = tokenize_string(&query);
-            tokens.dedup();
+            if CONF.search_file_contents {
+                for phrase in &phrases {
+                    let matches = phrase_search(index.tf_idf.clone(), phrase);
+                    let found = contains.entry(normalize(phrase)).or_default();
 
-            if tokens.len() > 1 {
-                for token in &tokens {
-                    index
-                        .dirs
-                        .get(token, &hash_fn)
-                        .into_iter()
-                        .for_each(|(s, r)| push(&mut files, &s, r * 1.5, FileType::Dir));
-
-                    index
-                        .files
-                        .get(token, &hash_fn)
-                        .into_iter()
-                        .for_each(|(s, r)| push(&mut files, &s, r * 1.4, FileType::File));
+                    for (r, s) in matches {
+                        found.insert(s.clone());
+                        push(&mut content, &s, r * CONTENT_MATCH_WEIGHT, FileType::File);
+                    }
                 }
-            }
 
-            if CONF.search_file_contents {
-                let corpus_size = index.tf_idf.corpus_size();
+                // Words are looked up as every term they could have been
+                // indexed as, e.g. "running" as "run".
+                let mut scored = HashSet::new();
 
-                tokens.into_iter().for_each(|token| {
-                    _tf_idf(corpus_size, index.tf_idf.clone(), &token)
-                        .iter()
-                        .for_each(|(r, s)| {
-                            let s = index.tf_idf.get_string(s);
-                            push(&mut files, &s, (*r / 17.).clamp(0., 2.), FileType::File);
-                        });
+                for token in split_terms(&unquoted) {
+                    let mut found = HashSet::new();
 
-                    let similar_terms = index.terms.get(&token, &hash_fn);
+                    for term in query_terms(&token) {
+                        // The same term is only counted once towards relevance.
+                        let score = scored.insert(term.clone());
 
-                    for (term, similarity) in similar_terms {
-                        if term == token {
-                            continue;
+                        for (r, s) in bm25(index.tf_idf.clone(), &term) {
+                            let s = index.tf_idf.get_string(&s);
+                            found.insert(s.clone());
+                            if score {
+                                push(&mut content, &s, r * CONTENT_MATCH_WEIGHT, FileType::File);
+                            }
                         }
 
-                        _tf_idf(corpus_size, index.tf_idf.clone(), &term)
-                            .iter()
-                            .for_each(|(r, s)| {
-                                let s = index.tf_idf.get_string(s);
-                                let r = ((*r * similarity) / 20.).clamp(0., 2.);
-                                push(&mut files, &s, r, FileType::File);
-                            });
+                        for (similar, similarity) in index.terms.get(&term, &hash_fn) {
+                            if similar == term {
+                                continue;
+                            }
+
+                            for (r, s) in bm25(index.tf_idf.clone(), &similar) {
+                                let s = index.tf_idf.get_string(&s);
+                                found.insert(s.clone());
+                                if score {
+                                    let r = r * similarity * CONTENT_MATCH_WEIGHT;
+                                    push(&mut content, &s, r, FileType::File);
+                                }
+                            }
+                        }
                     }
+
+                    contains.entry(fold(&token)).or_default().extend(found);
+                }
+
+                for (r, s) in proximity(index.tf_idf.clone(), &unquoted) {
+                    push(&mut content, &s, r * PROXIMITY_WEIGHT, FileType::File);
+                }
+
+                // Stop words aren't in the contents index, so files
+                // don't have to contain them.
+                boolean = boolean.and_then(|boolean| {
+                    boolean.retain(&|term| match term {
+                        Query::Word(_) => !term.text().is_some_and(|text| {
+                            let tokens = split_terms(&text);
+                            tokens.iter().all(|token| query_terms(token).is_empty())
+                        }),
+                        _ => true,
+                    })
                 });
+
+                // Words and phrases after NOT weren't looked up above.
+                for term in boolean.iter().flat_map(|boolean| boolean.terms()) {
+                    let text = term.text().unwrap_or_default();
+                    if let Entry::Vacant(entry) = contains.entry(text) {
+                        entry.insert(containing_documents(index, term));
+                    }
+                }
+            }
+
+            let mut metadata = index.metadata.clone();
+
+            content.retain(|s, res| {
+                let is_dir = matches!(res.kind, FileType::Dir);
+                passes(&filters, &mut metadata, s, is_dir) && matches_query(&boolean, s, &contains)
+            });
+
+            let mut by_relevance = content.iter().collect::<Vec<(&String, &FileResult)>>();
+            by_relevance.sort_by(|(_, a), (_, b)| b.relevance.total_cmp(&a.relevance));
+
+            let snippet_paths = by_relevance
+                .into_iter()
+                .take(SNIPPET_RESULTS)
+                .map(|(s, _)| s.clone())
+                .collect();
+            let mut content_matches = self.content_matches(index, snippet_paths, &query);
+
+            for (s, res) in content {
+                push(&mut files, &s, res.relevance, res.kind);
+            }
+
+            files.retain(|s, res| {
+                let is_dir = matches!(res.kind, FileType::Dir);
+                passes(&filters, &mut metadata, s, is_dir)
+                    && matches_query(&boolean, s, &contains)
+                    && exists(s)
+            });
+            if CONF.indexing.content_hashes {
+                files = group_copies(index, files);
             }
 
             merge_results(
                 files
                     .into_iter()
-                    .filter(|(s, _)| PathBuf::from(s).exists())
                     .map(|(s, res)| {
-                        self.create_result(&s, res.relevance / 2., res.kind, hash_fn(&s))
+                        let title = index.title(&s);
+                        let content_match = content_matches.remove(&s);
+                        self.create_result(&s, title, content_match, res, hash_fn(&s), &query)
                     })
                     .collect::<Vec<SearchResult>>(),
             )
@@ -164,6 +467,52 @@ impl SearchModule for Files {
     }
 }
 
+/// Documents with the exact word or phrase in their contents.
+fn containing_documents(index: &mut FileIndex, term: &Query) -> HashSet<String> {
+    match term {
+        Query::Phrase(phrase) => phrase_search(index.tf_idf.clone(), &normalize(phrase))
+            .into_iter()
+            .map(|(_, s)| s)
+            .collect(),
+        _ => split_terms(&term.text().unwrap_or_default())
+            .iter()
+            .flat_map(|token| query_terms(token))
+            .flat_map(|term| bm25(index.tf_idf.clone(), &term))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|(_, s)| index.tf_idf.get_string(&s))
+            .collect(),
+    }
+}
+
+/// Copies of the same file are shown as one result, the best ranked copy
+/// with a count of the others.
+fn group_copies(
+    index: &mut FileIndex,
+    files: HashMap<String, FileResult>,
+) -> HashMap<String, FileResult> {
+    let mut by_relevance = files.into_iter().collect::<Vec<(String, FileResult)>>();
+    by_relevance.sort_by(|(_, a), (_, b)| b.relevance.total_cmp(&a.relevance));
+
+    let mut grouped = HashMap::new();
+    let mut seen = HashSet::new();
+
+    for (path, mut res) in by_relevance {
+        if let Some(hash) = index.hash(&path) {
+            if !seen.insert(hash) {
+                continue;
+            }
+
+            let copies = index.copies_of(&path).iter().filter(|c| exists(c)).count();
+            res.copies = copies.saturating_sub(1);
+        }
+
+        grouped.insert(path, res);
+    }
+
+    grouped
+}
+
 fn merge_results(mut results: Vec<SearchResult>) -> Vec<SearchResult> {
     let mut relevances: HashMap<SearchResultId, Relevance> = HashMap::new();
     for result in results.iter_mut() {
@@ -191,18 +540,37 @@ impl Files {
     fn create_result(
         &self,
         name: &String,
-        relevance: f32,
-        kind: FileType,
+        title: Option<String>,
+        content_match: Option<ContentMatch>,
+        res: FileResult,
         id: u64,
+        query: &str,
     ) -> SearchResult {
+        let FileResult {
+            relevance,
+            kind,
+            copies,
+        } = res;
+        let is_mail = title.is_some();
+
+        let subtitle = content_match.as_ref().map(|m| m.subtitle.clone());
+        let preview_window_data = match content_match {
+            Some(content_match) => {
+                PreviewWindowShowing::FileWithMatches(PathBuf::from(name), content_match.matches)
+            }
+            None => PreviewWindowShowing::File(PathBuf::from(name)),
+        };
+
         let name_cpy = name.clone();
+        let query = query.to_string();
         let render = move || {
-            let name = match file_name(name_cpy.clone()) {
+            let name = match title.clone().or_else(|| file_name(name_cpy.clone())) {
                 Some(name) => name,
                 None => name_cpy.clone(),
             };
 
             let icon_name = match kind {
+                FileType::File if is_mail => "mail-message",
                 FileType::File => {
                     let ext = name.split('.').next_back().unwrap_or("");
                     find_file_icon_name(ext)
@@ -222,12 +590,30 @@ impl Files {
                 desc = Some("Run with wine".to_string());
             }
 
-            standard_entry(name, icon, desc)
+            if let Some((archive, member)) = archive::split_virtual_path(&name_cpy) {
+                desc = Some(format!("{} in {}", member, archive));
+            }
+
+            let entry = match &subtitle {
+                Some(subtitle) => snippet_entry(name, icon, subtitle, &query),
+                None => standard_entry(name, icon, desc, &query),
+            };
+
+            if copies > 0 {
+                let noun = if copies == 1 { "copy" } else { "copies" };
+                add_badge(&entry, &format!("+{} {}", copies, noun));
+            }
+
+            entry
         };
 
         let name_cpy = name.clone();
         let on_select = move || {
-            if CONF.misc.run_exes_with_wine && is_windows_application(&name_cpy) {
+            if is_mail {
+                open_mail(&name_cpy);
+            } else if let Some((archive, member)) = archive::split_virtual_path(&name_cpy) {
+                open_archive_member(archive.to_string(), member.to_string());
+            } else if CONF.misc.run_exes_with_wine && is_windows_application(&name_cpy) {
                 if let Some(dir) = PathBuf::from(&name_cpy).parent() {
                     let _ = std::env::set_current_dir(dir);
                 }
@@ -244,13 +630,181 @@ impl Files {
             relevance,
             id,
             on_select: Some(Box::new(on_select)),
-            preview_window_data: crate::preview_window::PreviewWindowShowing::File(PathBuf::from(
-                name,
-            )),
+            preview_window_data,
+        }
+    }
+
+    /// Every set of files with the same contents that has a path containing
+    /// each word of `filter` and a copy passing `filters`, those wasting the
+    /// most space first.
+    fn duplicate_results(
+        &self,
+        index: &mut FileIndex,
+        filter: &str,
+        filters: &Filters,
+        hash_fn: &HashFn,
+    ) -> Vec<SearchResult> {
+        let words = filter.split_whitespace().collect::<Vec<&str>>();
+        let mut metadata = index.metadata.clone();
+
+        let mut sets = index
+            .duplicates()
+            .into_iter()
+            .map(|set| set.into_iter().filter(|path| exists(path)).collect())
+            .filter(|set: &Vec<String>| {
+                set.len() > 1
+                    && set
+                        .iter()
+                        .any(|path| passes(filters, &mut metadata, path, false))
+                    && words
+                        .iter()
+                        .all(|word| set.iter().any(|path| normalize(path).contains(word)))
+            })
+            .collect::<Vec<Vec<String>>>()
+            .into_iter()
+            .map(|set| {
+                let size = metadata_of(&mut metadata, &set[0]).map_or(0, |m| m.size);
+                (size * (set.len() as u64 - 1), set)
+            })
+            .collect::<Vec<(u64, Vec<String>)>>();
+        sets.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        sets.into_iter()
+            .take(CONF.max_results)
+            .enumerate()
+            .map(|(i, (_, set))| {
+                let res = FileResult {
+                    relevance: DUPES_RELEVANCE - i as f32 * 0.01,
+                    kind: FileType::File,
+                    copies: set.len() - 1,
+                };
+                self.create_result(&set[0], None, None, res, hash_fn(&set[0]), filter)
+            })
+            .collect()
+    }
+
+    /// Files and folders passing `filters` and `keep`, newest first, for
+    /// queries with nothing else to search for. Goes through the index's
+    /// newest first listing, stopping once there are enough or the rest are
+    /// too old to pass.
+    fn browse(
+        &self,
+        index: &FileIndex,
+        filters: &Filters,
+        keep: impl Fn(&str) -> bool,
+        hash_fn: &HashFn,
+    ) -> Vec<SearchResult> {
+        if filters.is_empty() {
+            return vec![];
         }
+
+        index
+            .recent()
+            .take(MAX_BROWSED)
+            .take_while(|(_, metadata, _)| !filters.too_old(metadata))
+            .filter(|(path, metadata, is_dir)| {
+                filters.matches(path, *is_dir, || Some(*metadata)) && keep(path) && exists(path)
+            })
+            .take(CONF.max_results)
+            .enumerate()
+            .map(|(i, (path, _, is_dir))| {
+                let res = FileResult {
+                    relevance: 1. - i as f32 * 0.01,
+                    kind: if is_dir {
+                        FileType::Dir
+                    } else {
+                        FileType::File
+                    },
+                    copies: 0,
+                };
+                self.create_result(&path, None, None, res, hash_fn(&path), "")
+            })
+            .collect()
+    }
+
+    /// Snippets of the passages of `paths` that match `query`, from the text
+    /// the indexer extracted so PDFs and such get them too.
+    fn content_matches(
+        &self,
+        index: &mut FileIndex,
+        paths: Vec<String>,
+        query: &str,
+    ) -> HashMap<String, ContentMatch> {
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let text = indexed_text(&self.texts, index, &path)?;
+                let subtitle = snippet::find(&text, query, 1, SUBTITLE_SNIPPET_WIDTH).pop()?;
+
+                let matches = Matches {
+                    snippets: snippet::find(&text, query, PREVIEW_SNIPPETS, PREVIEW_SNIPPET_WIDTH),
+                    first_match: snippet::first_match(&text, query).map(String::from),
+                };
+
+                Some((path, ContentMatch { subtitle, matches }))
+            })
+            .collect()
     }
 }
 
+/// Kept in memory too, as the same few files tend to match query after
+/// query while typing.
+fn indexed_text(texts: &TextCache, index: &mut FileIndex, path: &str) -> Option<Arc<String>> {
+    if let Some(text) = texts.lock().unwrap().get(path) {
+        return Some(text.clone());
+    }
+
+    let text = Arc::new(cache::indexed_text(path, index.hash(path).as_deref())?);
+
+    let mut texts = texts.lock().unwrap();
+    if texts.len() >= MAX_CACHED_TEXTS {
+        texts.clear();
+    }
+    texts.insert(path.to_string(), text.clone());
+
+    Some(text)
+}
+
+/// Also accepts archive members and messages in an mbox.
+fn exists(path: &str) -> bool {
+    archive::exists(path) || mail::split_mbox_message_path(path).is_some()
+}
+
+/// Opens a message with the configured mail client. Messages in an mbox
+/// open the whole mbox.
+fn open_mail(path: &str) {
+    let file = match mail::split_mbox_message_path(path) {
+        Some((mbox, _)) => mbox.to_str().unwrap_or(path).to_string(),
+        None => path.to_string(),
+    };
+
+    let _ = execute_detached_with_path(&CONF.mail.client_command, &file);
+}
+
+/// Extracts the member to a temporary folder and opens it, or opens the
+/// archive if that doesn't work. The folder is left for whatever opened
+/// the member and removed a day later.
+fn open_archive_member(archive: String, member: String) {
+    std::thread::spawn(move || {
+        let dest = archive::extraction_dir();
+        let extracted = dest
+            .as_ref()
+            .and_then(|dest| archive::extract_member(Path::new(&archive), &member, dest));
+
+        match extracted {
+            Some(extracted) => {
+                let _ = xdg_open(extracted.to_str().unwrap_or(&archive));
+            }
+            None => {
+                if let Some(dest) = dest {
+                    let _ = std::fs::remove_dir_all(dest);
+                }
+                let _ = xdg_open(&archive);
+            }
+        }
+    });
+}
+
 fn is_windows_application(path: &String) -> bool {
     let path = PathBuf::from(path);
     let ext = path
@@ -327,13 +881,19 @@ impl Files {
             // This lock needs to be held until we are finish with initalisation
 
             let db_path = PathBuf::from(&CONF.indexing.location);
-            let index = FileIndex::open(&db_path, FILE_DB_READ).ok();
 
-            if needs_reindex() {
+            if CONF.scheduler.daemon {
+                // `glimpse-indexer --daemon` reindexes in the background so
+                // the current index can keep being used in the meantime,
+                // unless it is in an old format the daemon is rebuilding.
+                if !FileIndex::is_outdated(&db_path) {
+                    *lock = FileIndex::open(&db_path, FILE_DB_READ).ok();
+                }
+            } else if needs_reindex() {
                 println!("reindexing files");
                 let _ = execute_detached("glimpse-indexer".to_string());
             } else {
-                *lock = index;
+                *lock = FileIndex::open(&db_path, FILE_DB_READ).ok();
             }
 
             if let Some(benchmark) = benchmark {
@@ -341,6 +901,9 @@ impl Files {
             }
         });
 
-        Files { index }
+        Files {
+            index,
+            texts: TextCache::default(),
+        }
     }
 }
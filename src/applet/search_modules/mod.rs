@@ -38,6 +38,12 @@ pub trait SearchModule {
         std::any::type_name::<Self>().to_string()
     }
 
+    /// Its name under [modules] in the config, e.g. "files".
+    fn config_name(&self) -> String {
+        let name = self.name();
+        name.rsplit("::").nth(1).unwrap_or(&name).to_string()
+    }
+
     #[allow(unused)]
     fn uid(&self) -> u64 {
         crate::utils::simple_hash(&self.name())
@@ -66,17 +66,24 @@ impl SearchModule for SteamGames {
             false,
         )
         .into_iter()
-        .map(|(n, r)| create_result(data.as_ref().unwrap(), &n, r, hash_fn(&n)))
+        .map(|(n, r)| create_result(data.as_ref().unwrap(), &n, r, hash_fn(&n), &query))
         .collect::<Vec<SearchResult>>()
     }
 }
 
-fn create_result(data: &GamesData, name: &String, relevance: f32, id: u64) -> SearchResult {
+fn create_result(
+    data: &GamesData,
+    name: &String,
+    relevance: f32,
+    id: u64,
+    query: &str,
+) -> SearchResult {
     let steam_id = *data.game_ids.get(name).unwrap();
     let cased_name = data.cased_game_names.get(name).unwrap().clone();
+    let query = query.to_string();
     let render = move || {
         let icon = find_icon(steam_id);
-        standard_entry(cased_name.clone(), icon, None)
+        standard_entry(cased_name.clone(), icon, None, &query)
     };
 
     let on_select = move || {
@@ -27,15 +27,16 @@ impl SearchModule for Template {
     async fn search(&self, query: String, max_results: u32) -> Vec<SearchResult> {
         string_search(&query, _, max_results, Box::new(id_hash))
             .into_iter()
-            .map(|(s, r)| self.create_result(s, r))
+            .map(|(s, r)| self.create_result(s, r, &query))
             .collect::<Vec<SearchResult>>()
     }
 }
 
 impl SteamGames {
-    fn create_result(&self, name: String, relevance: f32) -> SearchResult {
+    fn create_result(&self, name: String, relevance: f32, query: &str) -> SearchResult {
+        let query = query.to_string();
         let render = move || {
-            standard_entry(name.clone(), None, None)
+            standard_entry(name.clone(), None, None, &query)
         };
 
         let on_select = move || {
@@ -46,7 +46,9 @@ impl SearchModule for WebBookmarks {
         if let Some(list) = list {
             string_search(&query, &list.titles, max_results, &hash_fn, false)
                 .into_iter()
-                .map(|(name, rel)| Self::create_result(&name, rel, &list.url_map, hash_fn(&name)))
+                .map(|(name, rel)| {
+                    Self::create_result(&name, rel, &list.url_map, hash_fn(&name), &query)
+                })
                 .collect()
         } else {
             vec![]
@@ -86,13 +88,15 @@ impl WebBookmarks {
         relevance: f32,
         url_map: &HashMap<String, String>,
         id: u64,
+        query: &str,
     ) -> SearchResult {
         // let icon = fetch_favicon(&list.url_map.get(&name).unwrap()).await;
 
         let name_cpy = name.clone();
+        let query = query.to_string();
         let render = move || {
             let icon = icon::from_gtk("emblem-web");
-            standard_entry(name_cpy.clone(), icon, None)
+            standard_entry(name_cpy.clone(), icon, None, &query)
         };
 
         let url = url_map.get(name).unwrap().clone();
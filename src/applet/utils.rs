@@ -14,9 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::path::PathBuf;
-
-use glimpse::{config::CONF, file_index::FileIndex};
+pub use glimpse::scheduler::needs_reindex;
 
 pub fn simple_hash(s: &str) -> u64 {
     let mut ret: u64 = 0;
@@ -60,16 +58,6 @@ pub fn benchmark() -> Option<BenchmarkTimer> {
     }
 }
 
-pub fn needs_reindex() -> bool {
-    let days = CONF.indexing.full_reindex_after_days;
-    let now = chrono::Utc::now().timestamp();
-    const HOUR: f32 = 60. * 60.;
-    const DAY: f32 = HOUR * 24.;
-
-    let db_path = PathBuf::from(&CONF.indexing.location);
-    now - FileIndex::last_indexed(&db_path).unwrap_or(0) > (DAY * days) as i64
-}
-
 pub fn is_cli_app(name: &str) -> bool {
     matches!(
         name,
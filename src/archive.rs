@@ -0,0 +1,349 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, DirBuilder, File},
+    io::Read,
+    os::unix::fs::DirBuilderExt,
+    path::{Component, Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use zip::ZipArchive;
+
+use crate::extract::{
+    command::{max_output, run},
+    limits_for,
+};
+
+/// Files inside archives are indexed as "backup.zip!/photos/cat.jpg".
+pub const MEMBER_SEPARATOR: &str = "!/";
+
+/// Archives with more entries than this only get the first ones indexed.
+const MAX_MEMBERS: usize = 10_000;
+
+/// Extraction directories older than this are removed when a new one is
+/// made, by then whatever opened their member should be done with it.
+const MAX_EXTRACTION_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+enum Kind {
+    Zip,
+    /// Compressed or not, `tar` works out which itself.
+    Tar,
+    SevenZip,
+}
+
+fn kind(path: &Path) -> Option<Kind> {
+    let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+
+    if name.ends_with(".zip") {
+        Some(Kind::Zip)
+    } else if [
+        ".tar", ".tar.gz", ".tgz", ".tar.xz", ".txz", ".tar.bz2", ".tbz2",
+    ]
+    .iter()
+    .any(|ext| name.ends_with(ext))
+    {
+        Some(Kind::Tar)
+    } else if name.ends_with(".7z") {
+        Some(Kind::SevenZip)
+    } else {
+        None
+    }
+}
+
+pub fn is_archive(path: &Path) -> bool {
+    kind(path).is_some()
+}
+
+pub fn virtual_path(archive: &Path, member: &str) -> Option<String> {
+    Some(format!(
+        "{}{}{}",
+        archive.to_str()?,
+        MEMBER_SEPARATOR,
+        member
+    ))
+}
+
+/// Splits "backup.zip!/photos/cat.jpg" into the archive and member. Real
+/// paths that happen to contain the separator aren't split.
+pub fn split_virtual_path(path: &str) -> Option<(&str, &str)> {
+    if Path::new(path).exists() {
+        return None;
+    }
+
+    let (archive, member) = path.split_once(MEMBER_SEPARATOR)?;
+    if is_archive(Path::new(archive)) {
+        Some((archive, member))
+    } else {
+        None
+    }
+}
+
+/// Whether `path` exists, or for members, whether their archive does.
+pub fn exists(path: &str) -> bool {
+    match split_virtual_path(path) {
+        Some((archive, _)) => Path::new(archive).exists(),
+        None => Path::new(path).exists(),
+    }
+}
+
+/// The files in an archive. Directories and members that would extract
+/// outside of the destination are left out.
+pub fn list_members(path: &Path) -> Vec<String> {
+    let members = match kind(path) {
+        Some(Kind::Zip) => zip_members(path),
+        Some(Kind::Tar) => command_output(Command::new("tar").arg("-tf").arg(path))
+            .map(|output| output.lines().map(String::from).collect()),
+        Some(Kind::SevenZip) => seven_zip_members(path),
+        None => None,
+    };
+
+    members
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|member| !member.ends_with('/') && is_safe(member))
+        .take(MAX_MEMBERS)
+        .collect()
+}
+
+fn zip_members(path: &Path) -> Option<Vec<String>> {
+    let archive = ZipArchive::new(File::open(path).ok()?).ok()?;
+    Some(archive.file_names().map(String::from).collect())
+}
+
+/// Parses the technical listing, which has a "Path = " and "Folder = "
+/// line for every entry.
+fn seven_zip_members(path: &Path) -> Option<Vec<String>> {
+    let output = command_output(
+        Command::new("7z")
+            .args(["l", "-ba", "-slt", "--"])
+            .arg(path),
+    )?;
+
+    let mut members = vec![];
+    let mut current = None;
+
+    for line in output.lines() {
+        if let Some(path) = line.strip_prefix("Path = ") {
+            current = Some(path.to_string());
+        } else if let Some(folder) = line.strip_prefix("Folder = ") {
+            if let Some(path) = current.take() {
+                if folder != "+" {
+                    members.push(path);
+                }
+            }
+        }
+    }
+
+    Some(members)
+}
+
+/// Archives are listed while crawling, so `tar` and `7z` get the same time
+/// and output limits as extractors, set under [extractors.limits.archive].
+fn command_output(command: &mut Command) -> Option<String> {
+    let limits = limits_for("archive");
+    let output = run(
+        command,
+        Duration::from_secs_f32(limits.timeout_secs),
+        max_output(&limits),
+    )?;
+
+    Some(String::from_utf8_lossy(&output).into_owned())
+}
+
+/// What `command` extracted to its output, unless that was more than
+/// `max_size` bytes.
+fn extracted_output(command: &mut Command, max_size: usize) -> Option<Vec<u8>> {
+    let limits = limits_for("archive");
+    let output = run(
+        command,
+        Duration::from_secs_f32(limits.timeout_secs),
+        max_size.saturating_add(1),
+    )?;
+
+    (output.len() <= max_size).then_some(output)
+}
+
+/// The size in a zip's header can't be trusted, so reading also stops past
+/// `max_size` bytes.
+fn zip_member(archive: &Path, member: &str, max_size: usize) -> Option<Vec<u8>> {
+    let mut archive = ZipArchive::new(File::open(archive).ok()?).ok()?;
+    let file = archive.by_name(member).ok()?;
+    if file.size() > max_size as u64 {
+        return None;
+    }
+
+    let mut contents = vec![];
+    file.take(max_size as u64 + 1)
+        .read_to_end(&mut contents)
+        .ok()?;
+
+    (contents.len() <= max_size).then_some(contents)
+}
+
+fn is_safe(member: &str) -> bool {
+    Path::new(member)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// A new directory only the current user can open, to extract members
+/// into. It's under $XDG_RUNTIME_DIR, or ~/.cache/glimpse without one, and
+/// randomly named so other users can't guess or plant it.
+pub fn extraction_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) => PathBuf::from(runtime_dir).join("glimpse"),
+        None => home::home_dir()?.join(".cache").join("glimpse"),
+    };
+    let base = base.join("archives");
+
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&base)
+        .ok()?;
+    remove_old_extractions(&base);
+
+    let dir = base.join(format!("{:016x}", rand::random::<u64>()));
+    DirBuilder::new().mode(0o700).create(&dir).ok()?;
+
+    Some(dir)
+}
+
+fn remove_old_extractions(base: &Path) {
+    let entries = match fs::read_dir(base) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let is_old = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > MAX_EXTRACTION_AGE);
+
+        if is_old {
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// Extracts a single member into `dest` and returns where it ended up.
+/// Members larger than the archive size limit aren't extracted, so a small
+/// archive can't fill the disk.
+pub fn extract_member(archive: &Path, member: &str, dest: &Path) -> Option<PathBuf> {
+    if !is_safe(member) {
+        return None;
+    }
+
+    // Everything matching the member counts towards the limit, in case
+    // an archive has it more than once.
+    let max_size = max_output(&limits_for("archive"));
+    let contents = match kind(archive)? {
+        Kind::Zip => zip_member(archive, member, max_size)?,
+        Kind::Tar => extracted_output(
+            Command::new("tar")
+                .arg("-xOf")
+                .arg(archive)
+                .arg("--")
+                .arg(member),
+            max_size,
+        )?,
+        Kind::SevenZip => extracted_output(
+            Command::new("7z")
+                .args(["x", "-so", "--"])
+                .arg(archive)
+                .arg(member),
+            max_size,
+        )?,
+    };
+
+    let extracted = dest.join(member);
+    fs::create_dir_all(extracted.parent()?).ok()?;
+    fs::write(&extracted, contents).ok()?;
+
+    Some(extracted)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::{write::FileOptions, ZipWriter};
+
+    use super::*;
+
+    #[test]
+    fn virtual_paths() {
+        let path = virtual_path(Path::new("/tmp/backup.zip"), "photos/cat.jpg").unwrap();
+        assert_eq!(path, "/tmp/backup.zip!/photos/cat.jpg");
+        assert_eq!(
+            split_virtual_path(&path),
+            Some(("/tmp/backup.zip", "photos/cat.jpg"))
+        );
+        assert_eq!(split_virtual_path("/tmp/wow!/cat.jpg"), None);
+        assert!(!is_safe("../../.bashrc"));
+        assert!(!is_safe("/etc/passwd"));
+    }
+
+    #[test]
+    fn extraction_dirs() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let first = extraction_dir().unwrap();
+        let second = extraction_dir().unwrap();
+        let mode = std::fs::metadata(&first).unwrap().permissions().mode();
+
+        std::fs::remove_dir(&first).unwrap();
+        std::fs::remove_dir(&second).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
+    #[test]
+    fn zip() {
+        let dir = std::env::temp_dir().join(format!("glimpse-members-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = &dir.join("members.zip");
+        let dest = &dir.join("extracted");
+
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        zip.add_directory("photos/", FileOptions::default())
+            .unwrap();
+        zip.start_file("photos/cat.jpg", FileOptions::default())
+            .unwrap();
+        zip.write_all(b"meow").unwrap();
+        zip.start_file("../escape.txt", FileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+
+        let members = list_members(path);
+        let extracted = extract_member(path, "photos/cat.jpg", dest);
+        let contents = extracted.as_ref().map(std::fs::read_to_string);
+        let too_large = zip_member(path, "photos/cat.jpg", 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(members, vec!["photos/cat.jpg"]);
+        assert_eq!(contents.unwrap().unwrap(), "meow");
+        assert_eq!(too_large, None);
+    }
+}
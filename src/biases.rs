@@ -14,66 +14,310 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::config::CONF;
+use crate::{config::CONF, normalize::normalize};
 use once_cell::sync::Lazy;
+use savefile::{Serialize, WithSchema};
 use savefile_derive::Savefile;
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-pub static BIASES: Lazy<Biases> = Lazy::new(|| Biases::load("biases"));
+pub static BIASES: Lazy<RwLock<Biases>> =
+    Lazy::new(|| RwLock::new(Biases::load(Path::new(&CONF.indexing.location))));
 
-#[derive(Savefile, Clone)]
+const FILE_NAME: &str = "frecency";
+/// Where biases were kept before selections were timed.
+const LEGACY_FILE_NAME: &str = "biases";
+
+/// What a selection is worth when it has just been made.
+const SELECTION_WEIGHT: f32 = 0.5;
+const MAX_BIAS: f32 = 2.5;
+/// Selections after typing the same start of a query count for more, so
+/// "f" can learn firefox without it taking over every other query.
+const PREFIX_WEIGHT: f32 = 1.;
+const MAX_PREFIX_BIAS: f32 = 4.;
+/// Only the latest selections of each result are kept.
+const MAX_SELECTIONS: usize = 10;
+/// Queries are learnt up to this many characters.
+const MAX_PREFIX_LENGTH: usize = 24;
+/// Selections that have decayed to less than this are forgotten.
+const MIN_WEIGHT: f32 = 0.01;
+
+/// Other launches can be saving at the same time.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+/// Locks older than this were left by a launch that died while saving.
+const STALE_LOCK: Duration = Duration::from_secs(10);
+
+#[derive(Savefile, Clone, Default)]
 pub struct Biases {
-    pub map: HashMap<u64, f32>,
+    /// When each result was selected, in seconds since the Unix epoch.
+    pub selections: HashMap<u64, Vec<u64>>,
+    /// When each result was selected after typing a query starting with
+    /// each prefix.
+    pub prefixes: HashMap<String, HashMap<u64, Vec<u64>>>,
+}
+
+#[derive(Savefile)]
+struct LegacyBiases {
+    map: HashMap<u64, f32>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default()
+}
+
+/// Each selection counts for half as much every `half_life_days`.
+fn decay(time: u64, now: u64, half_life_days: f32) -> f32 {
+    let half_life = half_life_days * 24. * 60. * 60.;
+    0.5f32.powf(now.saturating_sub(time) as f32 / half_life)
+}
+
+fn frecency(times: Option<&Vec<u64>>, now: u64, half_life_days: f32) -> f32 {
+    times.map_or(0., |times| {
+        times
+            .iter()
+            .map(|time| decay(*time, now, half_life_days))
+            .sum()
+    })
+}
+
+fn add_selection(times: &mut Vec<u64>, time: u64) {
+    times.push(time);
+    if times.len() > MAX_SELECTIONS {
+        times.remove(0);
+    }
+}
+
+fn prefix_key(query: &str) -> String {
+    normalize(query.trim())
+        .chars()
+        .take(MAX_PREFIX_LENGTH)
+        .collect()
 }
 
 impl Biases {
-    fn new() -> Biases {
+    fn path(dir: &Path, name: &str) -> PathBuf {
+        dir.join(name).with_extension("bin")
+    }
+
+    pub fn load(dir: &Path) -> Biases {
+        if let Ok(mut file) = fs::File::open(Self::path(dir, FILE_NAME)) {
+            return savefile::load(&mut file, 0).unwrap_or_default();
+        }
+
+        match fs::File::open(Self::path(dir, LEGACY_FILE_NAME)) {
+            Ok(mut file) => match savefile::load::<LegacyBiases>(&mut file, 0) {
+                Ok(legacy) => Self::from_legacy(legacy, now()),
+                Err(_) => Biases::default(),
+            },
+            Err(_) => Biases::default(),
+        }
+    }
+
+    /// Old biases were 0.5 per selection, those become selections made now.
+    fn from_legacy(legacy: LegacyBiases, now: u64) -> Biases {
+        let selections = legacy
+            .map
+            .into_iter()
+            .map(|(id, bias)| {
+                let count = (bias / SELECTION_WEIGHT).round() as usize;
+                (id, vec![now; count.min(MAX_SELECTIONS)])
+            })
+            .collect();
+
         Biases {
-            map: HashMap::new(),
+            selections,
+            prefixes: HashMap::new(),
         }
     }
 
-    pub fn load(name: &str) -> Biases {
-        match std::fs::File::open(
-            PathBuf::from(&CONF.indexing.location)
-                .join(name)
-                .with_extension("bin"),
-        ) {
-            Ok(mut file) => match savefile::load(&mut file, 0) {
-                Ok(biases) => biases,
-                Err(_) => Biases::new(),
-            },
-            Err(_) => Biases::new(),
+    /// How much to favour result `id` when searching for `query`.
+    pub fn bias(&self, id: u64, query: &str) -> f32 {
+        self.bias_at(id, query, now(), CONF.frecency.half_life_days)
+    }
+
+    fn bias_at(&self, id: u64, query: &str, now: u64, half_life_days: f32) -> f32 {
+        let overall = frecency(self.selections.get(&id), now, half_life_days) * SELECTION_WEIGHT;
+        let for_query = frecency(
+            self.prefixes
+                .get(&prefix_key(query))
+                .and_then(|selections| selections.get(&id)),
+            now,
+            half_life_days,
+        ) * PREFIX_WEIGHT;
+
+        overall.min(MAX_BIAS) + for_query.min(MAX_PREFIX_BIAS)
+    }
+
+    /// Notes the selection for the query and every start of it, so picking
+    /// firefox after typing "fir" is also learnt for "f" and "fi".
+    fn add(&mut self, id: u64, query: &str, time: u64) {
+        add_selection(self.selections.entry(id).or_default(), time);
+
+        let query = prefix_key(query);
+        let ends = query.char_indices().skip(1).map(|(i, _)| i);
+
+        for end in ends.chain([query.len()]).filter(|end| *end > 0) {
+            let selections = self.prefixes.entry(query[..end].to_string()).or_default();
+            add_selection(selections.entry(id).or_default(), time);
         }
     }
 
-    pub fn save(&self, name: &str) {
-        let path = PathBuf::from(&CONF.indexing.location)
-            .join(name)
-            .with_extension("bin");
-        let mut file = std::fs::File::create(path).unwrap();
-        savefile::save(&mut file, 0, self).unwrap();
+    fn forget_old(&mut self, now: u64, half_life_days: f32) {
+        let recent = |times: &mut Vec<u64>| {
+            times.retain(|time| decay(*time, now, half_life_days) >= MIN_WEIGHT);
+            !times.is_empty()
+        };
+
+        self.selections.retain(|_, times| recent(times));
+        self.prefixes.retain(|_, selections| {
+            selections.retain(|_, times| recent(times));
+            !selections.is_empty()
+        });
+    }
+
+    /// Reads what's saved again under a lock, so selections from launches
+    /// saving at the same time aren't lost, adds this one and saves it.
+    fn record(
+        dir: &Path,
+        id: u64,
+        query: &str,
+        time: u64,
+    ) -> Result<Biases, Box<dyn std::error::Error>> {
+        let _lock = Lock::acquire(&dir.join(FILE_NAME).with_extension("lock"))?;
+
+        let mut biases = Biases::load(dir);
+        biases.add(id, query, time);
+        biases.forget_old(time, CONF.frecency.half_life_days);
+        save_atomically(&Self::path(dir, FILE_NAME), &biases)?;
+
+        Ok(biases)
     }
 }
 
-pub fn increment_bias(id: u64, amount: f32) {
-    let mut biases = BIASES.clone();
+/// Remembers that result `id` was selected after searching for `query`.
+pub fn record_selection(id: u64, query: &str) {
+    match Biases::record(Path::new(&CONF.indexing.location), id, query, now()) {
+        Ok(biases) => *BIASES.write().unwrap() = biases,
+        Err(e) => eprintln!("Failed to save selection: {}", e),
+    }
+}
+
+/// Written to a temporary file first so nothing reading it ever sees half
+/// of it.
+pub(crate) fn save_atomically<T: WithSchema + Serialize>(
+    path: &Path,
+    value: &T,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let temporary = path.with_extension(format!("bin.{}", std::process::id()));
+
+    let mut file = fs::File::create(&temporary)?;
+    savefile::save(&mut file, 0, value)?;
+    file.sync_all()?;
+    fs::rename(temporary, path)?;
 
-    let mut bias = 0.0;
+    Ok(())
+}
+
+/// Held while reading, changing and saving a file other launches may be
+/// changing too.
+pub(crate) struct Lock {
+    path: PathBuf,
+}
 
-    if let Some(current) = biases.map.get(&id) {
-        bias = *current;
+impl Lock {
+    pub(crate) fn acquire(path: &Path) -> Result<Lock, Box<dyn std::error::Error>> {
+        let start = SystemTime::now();
+
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+            {
+                Ok(_) => {
+                    return Ok(Lock {
+                        path: path.to_owned(),
+                    })
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            let age = fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok());
+            if age.is_some_and(|age| age > STALE_LOCK) {
+                let _ = fs::remove_file(path);
+                continue;
+            }
+
+            if start.elapsed().unwrap_or_default() > LOCK_TIMEOUT {
+                return Err(format!("{} is locked.", path.display()).into());
+            }
+
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
 
-    bias += amount;
+    #[test]
+    fn frecency() {
+        let now = now();
+        let mut biases = Biases::default();
+        biases.add(1, "Fir", now - 14 * DAY);
+        biases.add(1, "fir", now);
+        biases.add(2, "code", now);
 
-    if bias > 2.5 {
-        bias = 2.5;
+        assert!((biases.bias_at(1, "xyz", now, 14.) - 0.75).abs() < 0.01);
+        assert!((biases.bias_at(1, "f", now, 14.) - 2.25).abs() < 0.01);
+        assert!((biases.bias_at(2, "f", now, 14.) - 0.5).abs() < 0.01);
+
+        biases.add(1, "fir", now - 1000 * DAY);
+        biases.forget_old(now, 14.);
+        assert_eq!(biases.selections[&1].len(), 2);
     }
 
-    biases.map.insert(id, bias);
+    #[test]
+    fn concurrent_selections() {
+        let dir = std::env::temp_dir().join(format!("glimpse-frecency-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let threads = (0..8)
+            .map(|id| {
+                let dir = dir.clone();
+                std::thread::spawn(move || Biases::record(&dir, id, "query", now()).unwrap())
+            })
+            .collect::<Vec<_>>();
+        threads.into_iter().for_each(|thread| {
+            thread.join().unwrap();
+        });
 
-    println!("Incremented bias for {} to {}", id, bias);
+        let biases = Biases::load(&dir);
+        assert_eq!(biases.selections.len(), 8);
+        assert_eq!(biases.prefixes["q"].len(), 8);
 
-    biases.save("biases");
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
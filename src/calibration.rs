@@ -0,0 +1,227 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use once_cell::sync::Lazy;
+use savefile_derive::Savefile;
+
+use crate::{
+    biases::{save_atomically, Lock},
+    config::CONF,
+};
+
+pub static CALIBRATION: Lazy<RwLock<Calibration>> =
+    Lazy::new(|| RwLock::new(Calibration::load(Path::new(&CONF.indexing.location))));
+
+const FILE_NAME: &str = "calibration";
+
+/// How often a result is expected to be picked over those below it. Modules
+/// picked more often than this rank higher, less often lower.
+const EXPECTED_PICK_RATE: f32 = 0.1;
+/// Until a module has been seen this many times its pick rate is mostly the
+/// expected one.
+const PRIOR_SHOWN: f32 = 50.;
+const MIN_PICK_FACTOR: f32 = 0.5;
+const MAX_PICK_FACTOR: f32 = 2.;
+/// Counts are halved past this so the pick rate follows recent use.
+const MAX_SHOWN: f32 = 1000.;
+
+/// What each module's scores mean. A result scoring `midpoint` is as likely
+/// to be wanted as not, and `spread` is how much more it takes to be
+/// noticeably more or less likely.
+struct Prior {
+    midpoint: f32,
+    spread: f32,
+    /// Modules with results that are read rather than picked, like the
+    /// calculator, don't learn from what's picked.
+    learns: bool,
+}
+
+fn prior(module: &str) -> Prior {
+    match module {
+        // Scored by how well their names match, where the whole query
+        // matching the start of a word scores about 8.
+        "commands" | "web_bookmarks" | "steam_games" => Prior {
+            midpoint: 6.5,
+            spread: 1.,
+            learns: true,
+        },
+        // Names matching count for more and content matches add up.
+        "files" => Prior {
+            midpoint: 4.,
+            spread: 3.,
+            learns: true,
+        },
+        "dictionary" => Prior {
+            midpoint: 3.,
+            spread: 1.,
+            learns: false,
+        },
+        // Only answers queries that are sums, with infinite relevance.
+        "calculator" => Prior {
+            midpoint: 0.,
+            spread: 1.,
+            learns: false,
+        },
+        _ => Prior {
+            midpoint: 2.,
+            spread: 1.5,
+            learns: true,
+        },
+    }
+}
+
+/// Puts the relevance of results from different modules on one scale, from
+/// 0 to 1 times the module's weight, so they can be ordered together.
+#[derive(Savefile, Clone, Default)]
+pub struct Calibration {
+    /// How many results of each module were passed over or picked, counting
+    /// those ranked above and including a picked result.
+    pub shown: HashMap<String, f32>,
+    pub picked: HashMap<String, f32>,
+}
+
+impl Calibration {
+    fn path(dir: &Path) -> PathBuf {
+        dir.join(FILE_NAME).with_extension("bin")
+    }
+
+    pub fn load(dir: &Path) -> Calibration {
+        match fs::File::open(Self::path(dir)) {
+            Ok(mut file) => savefile::load(&mut file, 0).unwrap_or_default(),
+            Err(_) => Calibration::default(),
+        }
+    }
+
+    pub fn calibrate(&self, module: &str, relevance: f32) -> f32 {
+        let weight = CONF.calibration.weights.get(module).copied().unwrap_or(1.);
+        self.calibrate_with(
+            module,
+            relevance,
+            weight,
+            CONF.calibration.learn_from_selections,
+        )
+    }
+
+    fn calibrate_with(&self, module: &str, relevance: f32, weight: f32, learn: bool) -> f32 {
+        let prior = prior(module);
+        let likelihood = 1. / (1. + (-(relevance - prior.midpoint) / prior.spread).exp());
+
+        // Raised to a power rather than multiplied so it stays below 1, and
+        // below results that are certain like the calculator's.
+        likelihood.powf(1. / self.pick_factor(module, learn)) * weight
+    }
+
+    /// How much more or less often `module`'s results are picked than
+    /// expected.
+    fn pick_factor(&self, module: &str, learn: bool) -> f32 {
+        if !learn || !prior(module).learns {
+            return 1.;
+        }
+
+        let shown = self.shown.get(module).copied().unwrap_or_default();
+        let picked = self.picked.get(module).copied().unwrap_or_default();
+        let rate = (picked + PRIOR_SHOWN * EXPECTED_PICK_RATE) / (shown + PRIOR_SHOWN);
+
+        (rate / EXPECTED_PICK_RATE).clamp(MIN_PICK_FACTOR, MAX_PICK_FACTOR)
+    }
+
+    /// `shown` are the modules of the results from the top of the list down
+    /// to the picked one, which is last.
+    fn add(&mut self, shown: &[String]) {
+        let picked = match shown.last() {
+            Some(picked) => picked,
+            None => return,
+        };
+
+        for module in shown {
+            *self.shown.entry(module.clone()).or_default() += 1.;
+        }
+        *self.picked.entry(picked.clone()).or_default() += 1.;
+
+        for (module, shown) in self.shown.iter_mut() {
+            if *shown > MAX_SHOWN {
+                *shown /= 2.;
+                if let Some(picked) = self.picked.get_mut(module) {
+                    *picked /= 2.;
+                }
+            }
+        }
+    }
+
+    fn record(dir: &Path, shown: &[String]) -> Result<Calibration, Box<dyn std::error::Error>> {
+        let _lock = Lock::acquire(&dir.join(FILE_NAME).with_extension("lock"))?;
+
+        let mut calibration = Calibration::load(dir);
+        calibration.add(shown);
+        save_atomically(&Self::path(dir), &calibration)?;
+
+        Ok(calibration)
+    }
+}
+
+/// Learns from a result being picked. `shown` are the modules of the results
+/// from the top of the list down to the picked one, which is last.
+pub fn record_selection(shown: &[String]) {
+    if !CONF.calibration.learn_from_selections {
+        return;
+    }
+
+    match Calibration::record(Path::new(&CONF.indexing.location), shown) {
+        Ok(calibration) => *CALIBRATION.write().unwrap() = calibration,
+        Err(e) => eprintln!("Failed to save the calibration of search modules: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modules(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn calibration() {
+        let mut calibration = Calibration::default();
+        let calibrate = |calibration: &Calibration, module, relevance| {
+            calibration.calibrate_with(module, relevance, 1., true)
+        };
+
+        assert_eq!(calibrate(&calibration, "calculator", f32::INFINITY), 1.);
+        assert!((calibrate(&calibration, "files", 4.) - 0.5).abs() < 0.01);
+        assert!(calibrate(&calibration, "commands", 9.) > calibrate(&calibration, "files", 7.));
+        assert_eq!(calibration.calibrate_with("files", 4., 2., true), 1.);
+
+        // Files picked over the commands above them.
+        for _ in 0..50 {
+            calibration.add(&modules(&["commands", "commands", "files"]));
+        }
+
+        let files = calibrate(&calibration, "files", 5.);
+        let commands = calibrate(&calibration, "commands", 7.);
+        assert!(files > commands);
+        assert_eq!(calibration.pick_factor("commands", true), MIN_PICK_FACTOR);
+        assert_eq!(calibration.pick_factor("commands", false), 1.);
+        assert_eq!(calibration.pick_factor("calculator", true), 1.);
+    }
+}
@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::{
+    collections::{BTreeMap, HashMap},
     error::Error,
     fs,
     io::{BufRead, BufReader},
@@ -24,6 +25,8 @@ use std::{
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
+use crate::analyzer::{is_auto, language};
+
 pub static CONF: Lazy<Config> = Lazy::new(|| match load_config() {
     Ok(mut config) => {
         config.error = None;
@@ -64,6 +67,8 @@ static DEFAULT_CSS: &str = ".search-field {}
 
 .result-icon {}
 
+.result-badge {}
+
 .odd-row {}
 
 .even-row {}
@@ -75,6 +80,10 @@ scrollbar slider {}
 .preview-window {}
 
 .preview-text {}
+
+.preview-matches {}
+
+.preview-metadata {}
 ";
 
 pub static CSS: Lazy<String> = Lazy::new(|| {
@@ -104,6 +113,22 @@ pub struct Config {
     pub window: Window,
     pub preview_window: PreviewWindow,
     pub misc: Misc,
+    #[serde(default)]
+    pub scheduler: Scheduler,
+    #[serde(default)]
+    pub tokenizer: Tokenizer,
+    #[serde(default)]
+    pub analyzer: Analyzer,
+    #[serde(default)]
+    pub extractors: Extractors,
+    #[serde(default)]
+    pub mail: Mail,
+    #[serde(default)]
+    pub ocr: Ocr,
+    #[serde(default)]
+    pub frecency: Frecency,
+    #[serde(default)]
+    pub calibration: Calibration,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -116,6 +141,16 @@ pub struct Modules {
     pub dictionary: bool,
 }
 
+/// The names of the modules under [modules].
+pub const MODULE_NAMES: [&str; 6] = [
+    "commands",
+    "files",
+    "steam_games",
+    "web_bookmarks",
+    "calculator",
+    "dictionary",
+];
+
 #[derive(Serialize, Deserialize)]
 pub struct Misc {
     pub display_command_paths: bool,
@@ -144,6 +179,10 @@ pub struct Indexing {
     pub location: String,
     pub size_upper_bound_GiB: f32,
     pub full_reindex_after_days: f32,
+    #[serde(default)]
+    pub archive_members: bool,
+    #[serde(default)]
+    pub content_hashes: bool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -153,6 +192,208 @@ pub struct PreviewWindow {
     pub image_size: u32,
 }
 
+/// A week, a longer interval would hardly ever index anything.
+const MAX_CHECK_INTERVAL_MINUTES: f32 = 7. * 24. * 60.;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scheduler {
+    pub daemon: bool,
+    pub check_interval_minutes: f32,
+    pub start_hour: u32,
+    pub end_hour: u32,
+    pub max_load_average: f32,
+    pub require_ac_power: bool,
+    pub niceness: i32,
+    pub idle_io_priority: bool,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Scheduler {
+            daemon: false,
+            check_interval_minutes: 10.0,
+            start_hour: 0,
+            end_hour: 24,
+            max_load_average: 1.5,
+            require_ac_power: true,
+            niceness: 19,
+            idle_io_priority: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tokenizer {
+    pub extra_separators: Vec<String>,
+    pub word_characters: Vec<char>,
+    pub keep_whole: Vec<String>,
+    pub strip_diacritics: bool,
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Tokenizer {
+            extra_separators: vec![],
+            word_characters: vec![],
+            keep_whole: vec![],
+            strip_diacritics: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Analyzer {
+    pub stemming: bool,
+    pub stop_words: bool,
+    /// A language name or "auto".
+    pub language: String,
+    pub detect_languages: Vec<String>,
+}
+
+impl Default for Analyzer {
+    fn default() -> Self {
+        Analyzer {
+            stemming: true,
+            stop_words: true,
+            language: String::from("auto"),
+            detect_languages: ["english", "german", "french", "spanish"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+#[allow(non_snake_case)]
+pub struct Extractors {
+    pub source_code: bool,
+    pub media: bool,
+    pub sandbox: bool,
+    pub max_memory_MiB: u32,
+    pub extensions: HashMap<String, String>,
+    pub mime_types: HashMap<String, String>,
+    pub limits: HashMap<String, ExtractorLimits>,
+    pub commands: Vec<CommandExtractor>,
+}
+
+/// A day, far longer than any extraction should take.
+const MAX_TIMEOUT_SECS: f32 = 24. * 60. * 60.;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+#[allow(non_snake_case)]
+pub struct ExtractorLimits {
+    pub max_size_MiB: f32,
+    pub timeout_secs: f32,
+}
+
+impl Default for Extractors {
+    fn default() -> Self {
+        Extractors {
+            source_code: false,
+            media: true,
+            sandbox: true,
+            max_memory_MiB: 1024,
+            extensions: HashMap::new(),
+            mime_types: HashMap::new(),
+            limits: HashMap::new(),
+            commands: vec![],
+        }
+    }
+}
+
+impl Default for ExtractorLimits {
+    fn default() -> Self {
+        ExtractorLimits {
+            max_size_MiB: 64.0,
+            timeout_secs: 20.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CommandExtractor {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub mime_types: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mail {
+    pub index_mail: bool,
+    pub client_command: String,
+}
+
+impl Default for Mail {
+    fn default() -> Self {
+        Mail {
+            index_mail: false,
+            client_command: String::from("xdg-open {path}"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ocr {
+    pub use_tesseract: bool,
+    /// Passed to `tesseract -l`, e.g. "eng+deu".
+    pub tesseract_languages: String,
+    pub max_pages: u32,
+}
+
+impl Default for Ocr {
+    fn default() -> Self {
+        Ocr {
+            use_tesseract: false,
+            tesseract_languages: String::from("eng"),
+            max_pages: 10,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Frecency {
+    pub half_life_days: f32,
+}
+
+impl Default for Frecency {
+    fn default() -> Self {
+        Frecency {
+            half_life_days: 14.,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Calibration {
+    pub learn_from_selections: bool,
+    /// By module name, 1 if not given.
+    pub weights: BTreeMap<String, f32>,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration {
+            learn_from_selections: true,
+            weights: MODULE_NAMES
+                .iter()
+                .map(|name| (name.to_string(), 1.))
+                .collect(),
+        }
+    }
+}
+
 pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
     if let Some(home) = home::home_dir() {
         let mut config_path = home.join(".config").join("glimpse").join("config.toml");
@@ -185,6 +426,80 @@ pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
                 }
             }
 
+            if conf.scheduler.start_hour > 24 || conf.scheduler.end_hour > 24 {
+                return Err("Scheduler hours must be between 0 and 24."
+                    .to_string()
+                    .into());
+            }
+
+            let check_interval = conf.scheduler.check_interval_minutes;
+            if !(0.0..=MAX_CHECK_INTERVAL_MINUTES).contains(&check_interval) {
+                return Err(format!(
+                    "Scheduler check interval must be between 0 and {} minutes.",
+                    MAX_CHECK_INTERVAL_MINUTES
+                )
+                .into());
+            }
+
+            // Also rules out NaN and infinity, which durations can't be made
+            // from.
+            for (name, limits) in &conf.extractors.limits {
+                if !(0.0..=f32::MAX).contains(&limits.max_size_MiB)
+                    || !(0.0..=MAX_TIMEOUT_SECS).contains(&limits.timeout_secs)
+                {
+                    return Err(format!(
+                        "Extractor limits for \"{}\" must be at least 0 MiB and 0 to {} seconds.",
+                        name, MAX_TIMEOUT_SECS
+                    )
+                    .into());
+                }
+            }
+
+            if conf.extractors.max_memory_MiB == 0 {
+                return Err("Extractor memory limit can't be 0.".to_string().into());
+            }
+
+            if conf.frecency.half_life_days <= 0.0 {
+                return Err("Frecency half life must be more than 0 days."
+                    .to_string()
+                    .into());
+            }
+
+            for (name, weight) in &conf.calibration.weights {
+                if !MODULE_NAMES.contains(&name.as_str()) {
+                    return Err(format!(
+                        "Unknown module \"{}\" under [calibration.weights], expected one of {}.",
+                        name,
+                        MODULE_NAMES.join(", ")
+                    )
+                    .into());
+                }
+
+                if *weight < 0.0 {
+                    return Err(format!("Weight for \"{}\" can't be negative.", name).into());
+                }
+            }
+
+            let tesseract_languages = &conf.ocr.tesseract_languages;
+            if tesseract_languages.is_empty()
+                || !tesseract_languages
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '+')
+            {
+                return Err(format!(
+                    "Invalid tesseract languages \"{}\", expected e.g. \"eng+deu\".",
+                    tesseract_languages
+                )
+                .into());
+            }
+
+            let languages = conf.analyzer.detect_languages.iter();
+            for name in languages.chain([&conf.analyzer.language]) {
+                if !is_auto(name) && language(name).is_none() {
+                    return Err(format!("Unsupported language \"{}\".", name).into());
+                }
+            }
+
             if !PathBuf::from(&conf.indexing.location).exists() {
                 std::fs::create_dir_all(&conf.indexing.location).unwrap();
             }
@@ -251,7 +566,7 @@ fn create_new_config_file(home: PathBuf, config_path: PathBuf) -> Result<Config,
     let toml = add_comment_to("search_paths", "Directory to search for files from", toml);
     let toml = add_comment_to("location", "Where to store the file database.", toml);
     let toml = add_comment_to("size_upper_bound_GiB", "The maximum size of the file index database in GiB. If this is exceeded, new data will not be added until there has been a full reindex.", toml);
-    let toml = add_comment_to("search_file_contents", "Index and search files by keywords they contain. Works for pdf, docx, txt and a few other plaintext filetypes. Will take considerably longer to index. It is recommended that full reindexes are done infrequently with this option.", toml);
+    let toml = add_comment_to("search_file_contents", "Index and search files by keywords they contain. Works for pdf, docx, xlsx, pptx, csv, OpenDocument, epub, txt and a few other plaintext filetypes, more can be added under [extractors]. Will take considerably longer to index. It is recommended that full reindexes are done infrequently with this option.", toml);
     let toml = add_comment_to(
         "run_exes_with_wine",
         "Open files with a .exe extension with wine.",
@@ -268,6 +583,152 @@ fn create_new_config_file(home: PathBuf, config_path: PathBuf) -> Result<Config,
         toml,
     );
 
+    let toml = add_comment_to(
+        "archive_members",
+        "Also index the files inside zip, tar (including .tar.gz, .tar.xz and .tar.bz2) and 7z archives. Selecting one extracts it to a temporary folder and opens it. 7z archives need the 7z command.",
+        toml,
+    );
+    let toml = add_comment_to(
+        "content_hashes",
+        "Fingerprint files by their contents when indexing. Copies of the same file are shown as one result, \"dupes:\" lists every set of copies and files whose contents haven't changed aren't read again on reindex.",
+        toml,
+    );
+
+    let toml = add_comment_to(
+        "daemon",
+        "Reindex in the background with `glimpse-indexer --daemon` instead of when the launcher is opened. The daemon only starts work between start_hour and end_hour, when the load average is below max_load_average and, if require_ac_power is set, when plugged in.",
+        toml,
+    );
+
+    let toml = add_comment_to(
+        "extra_separators",
+        "Extra strings to split file names and queries into words on. Words are always split on punctuation, spaces, camelCase and between letters and digits.",
+        toml,
+    );
+    let toml = add_comment_to(
+        "word_characters",
+        "Punctuation that should be treated as part of a word, e.g. [\"+\", \"#\"] to search for \"c++\" and \"c#\".",
+        toml,
+    );
+    let toml = add_comment_to(
+        "keep_whole",
+        "Words that should never be split up, e.g. \"iPhone\".",
+        toml,
+    );
+    let toml = add_comment_to(
+        "strip_diacritics",
+        "Ignore accents when matching, so \"cafe\" finds \"café\". Changing this requires a reindex.",
+        toml,
+    );
+
+    let toml = add_comment_to(
+        "[analyzer]",
+        "How the contents of files are broken into search terms. Changing any of these requires a reindex.",
+        toml,
+    );
+    let toml = add_comment_to(
+        "stemming",
+        "Reduce words to their stem so \"running\" also finds \"runs\" and \"run\".",
+        toml,
+    );
+    let toml = add_comment_to(
+        "stop_words",
+        "Leave out very common words like \"the\" and \"and\".",
+        toml,
+    );
+    let toml = add_comment_to(
+        "language",
+        "The language of your files, or \"auto\" to detect it for each file from detect_languages. Supported are english, german, french, spanish, italian, portuguese, dutch, russian, swedish, danish, norwegian, finnish, hungarian, romanian, turkish, greek, arabic and tamil.",
+        toml,
+    );
+    let toml = add_comment_to(
+        "detect_languages",
+        "Languages \"auto\" picks between. Queries are matched as each of them, so keep this to the languages you have files in.",
+        toml,
+    );
+
+    let toml = add_comment_to(
+        "[extractors]",
+        "How file contents are read when search_file_contents is on. Map extra extensions or MIME types to an extractor by name, e.g. extensions = { rst = \"text\" }. The built-in extractors are \"pdf\", \"docx\", \"opendocument\", \"epub\", \"xlsx\", \"pptx\", \"csv\", \"html\", \"mail\", \"media\", \"code\" and \"text\". External converters can be added under [[extractors.commands]] with a name, a command such as \"pdftotext {path} -\" or \"pandoc -t plain\", and the extensions and mime_types it handles. Size and time limits can be set per extractor under [extractors.limits.<name>], and for listing archive contents under [extractors.limits.archive].",
+        toml,
+    );
+
+    let toml = add_comment_to(
+        "source_code",
+        "Index the contents of source files by identifier, e.g. \"parse config\" finds where parse_config is defined. Comments and strings are indexed as text and keywords are ignored.",
+        toml,
+    );
+
+    let toml = add_comment_to(
+        "media",
+        "Index photo EXIF (camera, date taken, place and description), music tags (artist, album, title and genre) and video container tags. Only the headers are read so media files aren't subject to the size limit by default.",
+        toml,
+    );
+
+    let toml = add_comment_to(
+        "sandbox",
+        "Read files in a separate process when indexing, so one that crashes, hangs or uses too much memory is skipped rather than stopping the reindex. Skipped files are listed by glimpse-indexer --skipped. The process is only limited in memory and time, it can still read and write the same files you can.",
+        toml,
+    );
+    let toml = add_comment_to(
+        "max_memory_MiB",
+        "Memory the sandboxed process may use, set with ulimit -v so it counts address space rather than memory in use.",
+        toml,
+    );
+
+    let toml = add_comment_to(
+        "index_mail",
+        "Index mail in Maildir folders and mbox files found in search_paths by subject, and by sender, recipients and body when search_file_contents is on.",
+        toml,
+    );
+    let toml = add_comment_to(
+        "client_command",
+        "Command to open a message with, {path} is replaced with the message file or mbox, e.g. \"neomutt -f {path}\".",
+        toml,
+    );
+
+    let toml = add_comment_to(
+        "[ocr]",
+        "Read the text of scanned PDFs and of images with a locally installed tesseract when search_file_contents is on. PDFs are only read this way when they have next to no text of their own, and need pdftoppm from poppler-utils. Results are cached by file contents so nothing is read twice. The time allowed per file can be set under [extractors.limits.ocr].",
+        toml,
+    );
+    let toml = add_comment_to(
+        "use_tesseract",
+        "Off by default as it's slow, expect a few seconds per page.",
+        toml,
+    );
+    let toml = add_comment_to(
+        "tesseract_languages",
+        "Installed tesseract language data to read with, e.g. \"eng+deu\".",
+        toml,
+    );
+    let toml = add_comment_to("max_pages", "Pages read from each scanned PDF.", toml);
+    let toml = add_comment_to(
+        "[frecency]",
+        "Results picked often and recently rank higher, and more so for what was typed before picking them.",
+        toml,
+    );
+    let toml = add_comment_to(
+        "half_life_days",
+        "How many days until a pick counts for half as much.",
+        toml,
+    );
+    let toml = add_comment_to(
+        "[calibration]",
+        "Every module scores its results differently, these are put on the same scale before results from different modules are ordered.",
+        toml,
+    );
+    let toml = add_comment_to(
+        "learn_from_selections",
+        "Favour modules whose results get picked over those ranked above them.",
+        toml,
+    );
+    let toml = add_comment_to(
+        "[calibration.weights]",
+        "Multiplies the relevance of each module's results, e.g. 2 ranks files higher and 0.5 lower.",
+        toml,
+    );
+
     let config_folder = home.join(".config").join("glimpse");
     std::fs::create_dir_all(config_folder)?;
     std::fs::write(config_path, toml)?;
@@ -335,6 +796,8 @@ impl Default for Config {
                 location: String::from(""),
                 size_upper_bound_GiB: 5.0,
                 full_reindex_after_days: 0.6,
+                archive_members: false,
+                content_hashes: false,
             },
             modules: Modules {
                 commands: true,
@@ -369,6 +832,14 @@ impl Default for Config {
                 preferred_terminal: String::from("xterm"),
                 run_exes_with_wine: true,
             },
+            scheduler: Scheduler::default(),
+            tokenizer: Tokenizer::default(),
+            analyzer: Analyzer::default(),
+            extractors: Extractors::default(),
+            mail: Mail::default(),
+            ocr: Ocr::default(),
+            frecency: Frecency::default(),
+            calibration: Calibration::default(),
         }
     }
 }
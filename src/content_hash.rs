@@ -0,0 +1,100 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+/// Files bigger than this are fingerprinted from samples rather than read
+/// in full, which would make indexing a folder of videos take hours.
+const SAMPLE_ABOVE: u64 = 64 * 1024 * 1024;
+const SAMPLES: u64 = 16;
+const SAMPLE_LENGTH: usize = 1024 * 1024;
+
+/// Identifies a file by what's in it rather than where it is, so work done
+/// on a file isn't repeated for copies of it or after it's moved.
+pub fn of_file(path: &Path) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let mut context = md5::Context::new();
+    let mut buffer = vec![0; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer).ok()?;
+        if read == 0 {
+            break;
+        }
+        context.consume(&buffer[..read]);
+    }
+
+    Some(format!("{:x}", context.compute()))
+}
+
+/// Like `of_file`, but large files are hashed from their size and evenly
+/// spaced samples. Two different large files are only taken to be the same
+/// if they're the same size and every sample matches, which for photos,
+/// videos and archives doesn't happen in practice. Empty files have none.
+pub fn fingerprint(path: &Path) -> Option<String> {
+    let size = path.metadata().ok()?.len();
+
+    if size == 0 {
+        return None;
+    }
+
+    if size <= SAMPLE_ABOVE {
+        return of_file(path);
+    }
+
+    let mut file = File::open(path).ok()?;
+    let mut context = md5::Context::new();
+    let mut buffer = vec![0; SAMPLE_LENGTH];
+
+    context.consume(size.to_le_bytes());
+    for i in 0..SAMPLES {
+        let offset = (size - SAMPLE_LENGTH as u64) / (SAMPLES - 1) * i;
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        file.read_exact(&mut buffer).ok()?;
+        context.consume(&buffer);
+    }
+
+    Some(format!("{:x}", context.compute()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_contents() {
+        let dir = std::env::temp_dir().join(format!("glimpse-hashes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (a, b) = (dir.join("a"), dir.join("b"));
+        std::fs::write(&a, "receipt").unwrap();
+        std::fs::write(&b, "receipt").unwrap();
+
+        let hashes = (of_file(&a), of_file(&b));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(hashes.0, hashes.1);
+        assert_eq!(
+            fingerprint(Path::new("Cargo.toml")),
+            of_file(Path::new("Cargo.toml"))
+        );
+        assert_eq!(hashes.0.unwrap(), "1e11b989ba2f5e161cdad604bf3de90b");
+    }
+}
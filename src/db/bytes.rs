@@ -0,0 +1,40 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{allocator::SerializableDBPointer, session::DBSession};
+
+/// Raw bytes, for things like compressed lists that don't need to be
+/// looked up by value.
+#[repr(C)]
+#[derive(Clone)]
+pub struct DBBytes(SerializableDBPointer<u8>);
+
+impl DBBytes {
+    /// `bytes` can't be empty.
+    pub fn new(db: &mut DBSession, bytes: Vec<u8>) -> Self {
+        let chunk = db.alloc(bytes);
+        Self(chunk.into_serializable())
+    }
+
+    pub fn load(&self, db: &mut DBSession) -> Vec<u8> {
+        let ptr = self.0.to_ptr();
+        db.borrow_mut(&ptr).into_iter().map(|b| *b).collect()
+    }
+
+    pub fn dealloc(&self, db: &mut DBSession) {
+        db.dealloc(self.0.to_ptr());
+    }
+}
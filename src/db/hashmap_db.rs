@@ -23,9 +23,11 @@ use std::{
 use crate::db::allocator::SaveableDBPointer;
 
 use super::allocator::SerializableDBPointer;
+use super::bytes::DBBytes;
 use super::hashmap::{CompareWith, EqWithDBAccess, HashWithDBAccess};
 use super::list::DBList;
 use super::string::DBString;
+use super::value::DBValue;
 use super::{hashmap::DBHashMap, session::DBSession};
 
 #[derive(Clone)]
@@ -37,6 +39,7 @@ where
     db: Arc<Mutex<DBSession>>,
     map: DBHashMap<K, V>,
     corpus_size: SerializableDBPointer<usize>,
+    total_length: SerializableDBPointer<usize>,
 }
 
 impl<KInDb, V> HashMapDB<KInDb, V>
@@ -47,7 +50,7 @@ where
     pub fn open(path: PathBuf, buckets_count: usize) -> Self {
         let mut db = DBSession::open(path);
 
-        let (map, corpus_size) = if db.meta.pointer_store.len() == 2 {
+        let (map, corpus_size) = if db.meta.pointer_store.len() >= 2 {
             let map_ptr = db.meta.pointer_store[0].to_ptr::<DBHashMap<KInDb, V>>();
             let map_borrowed = db.borrow_mut(&map_ptr);
             assert!(map_borrowed.len() == 1);
@@ -74,10 +77,27 @@ where
             (map, corpus_size)
         };
 
+        // Added after the other two so older databases won't have it yet.
+        let total_length = if db.meta.pointer_store.len() == 3 {
+            db.meta.pointer_store[2]
+                .to_ptr::<usize>()
+                .into_serializable()
+        } else {
+            let total_length = db.alloc(vec![0]).into_serializable();
+
+            db.meta
+                .pointer_store
+                .push(SaveableDBPointer::from_ptr(total_length.clone().to_ptr()));
+
+            db.meta.save();
+            total_length
+        };
+
         Self {
             db: Arc::new(Mutex::new(db)),
             map,
             corpus_size,
+            total_length,
         }
     }
 
@@ -94,11 +114,22 @@ where
         self.map.get(&mut db, key)
     }
 
+    /// Every key and value, in no particular order.
+    pub fn entries(&mut self) -> Vec<(KInDb, V)> {
+        let mut db = self.db.lock().unwrap();
+        self.map.flatten(&mut db)
+    }
+
     pub fn save_meta(&mut self) {
         let db = self.db.lock().unwrap();
         db.meta.save();
     }
 
+    pub fn flush(&mut self) {
+        let mut db = self.db.lock().unwrap();
+        db.flush();
+    }
+
     pub fn reset(path: PathBuf) {
         DBSession::reset(path);
     }
@@ -113,12 +144,39 @@ where
         *(*db).borrow_mut(&self.corpus_size.to_ptr())[0] += 1;
     }
 
+    /// Sum of the lengths of every document in the corpus.
+    pub fn total_length(&mut self) -> usize {
+        let mut db = self.db.lock().unwrap();
+        *(*db).borrow_mut(&self.total_length.to_ptr())[0]
+    }
+
+    pub fn add_to_total_length(&mut self, length: usize) {
+        let mut db = self.db.lock().unwrap();
+        *(*db).borrow_mut(&self.total_length.to_ptr())[0] += length;
+    }
+
     pub fn alloc_string(&mut self, string: String) -> DBString {
         let mut db = self.db.lock().unwrap();
 
         DBString::new(&mut db, string)
     }
 
+    pub fn alloc_bytes(&mut self, bytes: Vec<u8>) -> DBBytes {
+        let mut db = self.db.lock().unwrap();
+
+        DBBytes::new(&mut db, bytes)
+    }
+
+    pub fn get_bytes(&mut self, bytes: &DBBytes) -> Vec<u8> {
+        let mut db = self.db.lock().unwrap();
+        bytes.load(&mut db)
+    }
+
+    pub fn dealloc_bytes(&mut self, bytes: &DBBytes) {
+        let mut db = self.db.lock().unwrap();
+        bytes.dealloc(&mut db);
+    }
+
     pub fn new_list<T: Clone>(&mut self) -> DBList<T> {
         let mut db = self.db.lock().unwrap();
 
@@ -134,9 +192,24 @@ where
         &mut self,
         list: &mut DBList<(U, T)>,
         value: &T,
-    ) {
+    ) -> Option<(U, T)> {
+        let mut db = self.db.lock().unwrap();
+        list.remove(&mut db, |v, db| v.1.compare_with(value, db))
+    }
+
+    pub fn alloc_value<T: Clone>(&mut self, value: T) -> DBValue<T> {
+        let mut db = self.db.lock().unwrap();
+        DBValue::new(&mut db, value)
+    }
+
+    pub fn get_value<T: Clone>(&mut self, value: &DBValue<T>) -> T {
+        let mut db = self.db.lock().unwrap();
+        value.load(&mut db)
+    }
+
+    pub fn set_value<T: Clone>(&mut self, value: &DBValue<T>, new_value: T) {
         let mut db = self.db.lock().unwrap();
-        list.remove(&mut db, |v, db| v.1.compare_with(value, db));
+        value.store(&mut db, new_value);
     }
 
     pub fn get_string(&mut self, string: &DBString) -> String {
@@ -72,7 +72,12 @@ where
         self.set_head(db, new_head.into_serializable());
     }
 
-    pub fn remove(&mut self, db: &mut DBSession, cmp: impl Fn(&T, &mut DBSession) -> bool)
+    /// Removes the first value `cmp` matches and returns it.
+    pub fn remove(
+        &mut self,
+        db: &mut DBSession,
+        cmp: impl Fn(&T, &mut DBSession) -> bool,
+    ) -> Option<T>
     where
         T: Clone,
     {
@@ -88,7 +93,9 @@ where
             let node = &borrowed[0];
             let next_ptr = node.next.clone();
 
-            if cmp(&node.value.clone(), db) {
+            let value = node.value.clone();
+
+            if cmp(&value, db) {
                 if prev.is_null {
                     self.set_head(db, next_ptr);
                 } else {
@@ -101,12 +108,14 @@ where
                 }
 
                 db.dealloc(ptr);
-                return;
+                return Some(value);
             }
 
             prev = current;
             current = next_ptr;
         }
+
+        None
     }
 
     fn set_head(&mut self, db: &mut DBSession, node: SerializableDBPointer<DBListNode<T>>) {
@@ -15,10 +15,12 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 
+pub mod bytes;
 pub mod hashmap_db;
 pub mod list;
 pub mod string;
 pub mod string_search_db;
+pub mod value;
 
 mod allocator;
 mod hashmap;
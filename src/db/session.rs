@@ -76,6 +76,15 @@ impl DBSession {
         remove_if_exists(&meta_path(&path));
     }
 
+    /// Writes the mapped memory and metadata out to disk so the files can be
+    /// copied while the session is still open.
+    pub fn flush(&mut self) {
+        if let Some(mmap) = self.mmap.as_mut() {
+            mmap.flush().unwrap();
+        }
+        self.meta.save();
+    }
+
     pub fn resize(&mut self, new_capacity: BytesLength) {
         self.meta.save();
         // self.mmap.as_mut().unwrap().flush().unwrap();
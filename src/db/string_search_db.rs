@@ -19,7 +19,9 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-use crate::{db::allocator::SaveableDBPointer, string_similarity::word_similarity};
+use crate::{
+    db::allocator::SaveableDBPointer, normalize::normalize, string_similarity::word_similarity,
+};
 
 use super::{session::DBSession, trie::DBTrie};
 
@@ -56,13 +58,28 @@ impl StringSearchDb {
     }
 
     pub fn insert(&mut self, word: String, points_to: Option<String>) {
+        let word = normalize(&word);
         let mut db = self.db.lock().unwrap();
         self.trie
             .insert(&mut db, word.as_str(), &points_to.unwrap());
     }
 
     pub fn get(&mut self, word: &str, id_hash: &dyn Fn(&str) -> u64) -> Vec<(String, f32)> {
-        if word.len() < 3 {
+        self.get_matching(word, id_hash, &mut |_| true)
+    }
+
+    /// Like `get`, leaving out what `keep` returns false for before the
+    /// number of results is limited.
+    pub fn get_matching(
+        &mut self,
+        word: &str,
+        id_hash: &dyn Fn(&str) -> u64,
+        keep: &mut dyn FnMut(&str) -> bool,
+    ) -> Vec<(String, f32)> {
+        let word = normalize(word);
+        let word = word.as_str();
+
+        if word.chars().count() < 3 {
             return vec![];
         }
 
@@ -72,7 +89,7 @@ impl StringSearchDb {
 
         results.extend(
             self.trie
-                .fuzzy_get(&mut db, word)
+                .fuzzy_get(&mut db, word, keep)
                 .into_iter()
                 .map(|s| (s.clone(), word_similarity(word, s, id_hash))),
         );
@@ -81,10 +98,11 @@ impl StringSearchDb {
     }
 
     pub fn insert_if_new(&mut self, word: &str, points_to: Option<String>) {
+        let word = normalize(word);
         let mut db = self.db.lock().unwrap();
-        if self.trie.get(&mut db, word).is_empty() {
+        if self.trie.get(&mut db, &word).is_empty() {
             self.trie
-                .insert(&mut db, word, &points_to.unwrap());
+                .insert(&mut db, &word, &points_to.unwrap());
         }
     }
 
@@ -93,6 +111,11 @@ impl StringSearchDb {
         // db.meta.save();
     }
 
+    pub fn flush(&mut self) {
+        let mut db = self.db.lock().unwrap();
+        db.flush();
+    }
+
     /// Database size in bytes
     pub fn size(&self) -> usize {
         let db = self.db.lock().unwrap();
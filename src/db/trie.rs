@@ -90,24 +90,39 @@ impl DBTrie {
 
         let points_to = current.points_to;
 
-        push_matches(points_to, db, &mut matches);
+        push_matches(points_to, db, &mut matches, &mut |_| true);
 
         matches
     }
 
-    pub fn fuzzy_get(&self, db: &mut DBSession, word: &str) -> Vec<String> {
+    /// Only what `keep` returns true for counts towards the number of
+    /// matches looked for.
+    pub fn fuzzy_get(
+        &self,
+        db: &mut DBSession,
+        word: &str,
+        keep: &mut dyn FnMut(&str) -> bool,
+    ) -> Vec<String> {
         let mut matches = vec![];
 
-        fuzzy_get(self.root.clone(), db, &mut matches, word);
+        fuzzy_get(self.root.clone(), db, &mut matches, word, keep);
 
         matches
     }
 }
 
-fn push_matches(points_to: DBList<DBString>, db: &mut DBSession, matches: &mut Vec<String>) {
+fn push_matches(
+    points_to: DBList<DBString>,
+    db: &mut DBSession,
+    matches: &mut Vec<String>,
+    keep: &mut dyn FnMut(&str) -> bool,
+) {
     let points_to = points_to.iter(db).collect::<Vec<_>>();
 
-    let points_to = points_to.into_iter().map(|s| s.load_string(db));
+    let points_to = points_to
+        .into_iter()
+        .map(|s| s.load_string(db))
+        .filter(|s| keep(s));
 
     matches.extend(points_to);
 }
@@ -180,7 +195,13 @@ impl DBTrieNode {
     //     if there are no nodes for the current char, branch into
     //     all of other children with the current char
 
-    pub fn fuzzy_get(&self, db: &mut DBSession, word: &str, matches: &mut Vec<String>) {
+    pub fn fuzzy_get(
+        &self,
+        db: &mut DBSession,
+        word: &str,
+        matches: &mut Vec<String>,
+        keep: &mut dyn FnMut(&str) -> bool,
+    ) {
         if matches.len() > 20 {
             return;
         }
@@ -196,34 +217,39 @@ impl DBTrieNode {
             let rest = chars.as_str();
 
             if let Some(node) = self.get_child_from_char(db, c) {
-                fuzzy_get(node, db, matches, rest);
+                fuzzy_get(node, db, matches, rest, keep);
             } else {
                 // There were no nodes for the current char.
 
                 if children.len(db) < 4 {
                     for (_, child) in children.iter(db) {
-                        fuzzy_get(child, db, matches, rest)
+                        fuzzy_get(child, db, matches, rest, keep)
                     }
                 }
 
                 // Correct extra char
                 if !rest.is_empty() {
-                    self.fuzzy_get(db, rest, matches);
+                    self.fuzzy_get(db, rest, matches, keep);
                 }
             }
         } else {
-            self.get_all_matches(db, matches);
+            self.get_all_matches(db, matches, keep);
         }
     }
 
-    fn get_all_matches(&self, db: &mut DBSession, matches: &mut Vec<String>) {
+    fn get_all_matches(
+        &self,
+        db: &mut DBSession,
+        matches: &mut Vec<String>,
+        keep: &mut dyn FnMut(&str) -> bool,
+    ) {
         let children = self.children.to_ptr();
         let children = db.borrow_mut(&children);
         assert!(children.len() == 1);
         let children = children[0].clone();
 
         let ptr = self.points_to.clone();
-        push_matches(ptr, db, matches);
+        push_matches(ptr, db, matches, keep);
 
         // if children.len(db) > 3 {
         //     return;
@@ -240,7 +266,7 @@ impl DBTrieNode {
             let borrow = db.borrow_mut(&child);
             assert!(borrow.len() == 1);
             let child = borrow[0].clone();
-            child.get_all_matches(db, matches);
+            child.get_all_matches(db, matches, keep);
         }
     }
 
@@ -263,13 +289,14 @@ fn fuzzy_get(
     db: &mut DBSession,
     matches: &mut Vec<String>,
     rest: &str,
+    keep: &mut dyn FnMut(&str) -> bool,
 ) {
     let ptr = node.to_ptr();
     let borrow = db.borrow_mut(&ptr);
     assert!(borrow.len() == 1);
     let node = borrow[0].clone();
 
-    node.fuzzy_get(db, rest, matches);
+    node.fuzzy_get(db, rest, matches, keep);
 }
 
 #[cfg(test)]
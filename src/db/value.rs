@@ -0,0 +1,42 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::{allocator::SerializableDBPointer, session::DBSession};
+
+/// A single value that can be changed in place, for counters kept
+/// alongside other entries.
+#[repr(C)]
+pub struct DBValue<T>(SerializableDBPointer<T>);
+
+impl<T> Clone for DBValue<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: Clone> DBValue<T> {
+    pub fn new(db: &mut DBSession, value: T) -> Self {
+        Self(db.alloc(vec![value]).into_serializable())
+    }
+
+    pub fn load(&self, db: &mut DBSession) -> T {
+        db.borrow_mut(&self.0.to_ptr())[0].clone()
+    }
+
+    pub fn store(&self, db: &mut DBSession, value: T) {
+        *db.borrow_mut(&self.0.to_ptr())[0] = value;
+    }
+}
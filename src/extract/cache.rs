@@ -0,0 +1,125 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{config::CONF, file_index::INDEX_VERSION};
+
+use super::{Document, REGISTRY};
+
+/// Extracts `path`, keeping the text so search can show snippets of it
+/// without reading the file again. Files with a content fingerprint have
+/// their text kept by it, so files that were only touched, moved or copied
+/// aren't read again on the next reindex. Others have it kept by path.
+pub fn extract(path: &Path, hash: Option<&str>) -> Option<Document> {
+    let cache_path = match hash {
+        Some(hash) => {
+            let cache_path = cache_dir().join(hash_key(path, hash)?);
+
+            if let Ok(text) = std::fs::read_to_string(&cache_path) {
+                let emphasized_path = emphasized_path(&cache_path);
+                let emphasized = std::fs::read_to_string(&emphasized_path).unwrap_or_default();
+
+                // Marks it as still in use, see `prune`.
+                touch(&cache_path);
+                touch(&emphasized_path);
+
+                return Some(Document { text, emphasized });
+            }
+
+            cache_path
+        }
+        None => cache_dir().join(path_key(path.to_str()?)),
+    };
+
+    let document = REGISTRY.extract(path)?;
+    write(&cache_path, &document.text);
+    if !document.emphasized.is_empty() {
+        write(&emphasized_path(&cache_path), &document.emphasized);
+    }
+
+    Some(document)
+}
+
+/// Keeps the text of documents that aren't files of their own, like
+/// messages in an mbox.
+pub fn store(document: &str, text: &str) {
+    write(&cache_dir().join(path_key(document)), text);
+}
+
+/// The text kept for `document` when it was indexed. Nothing is extracted,
+/// so this is cheap enough to call while searching.
+pub fn indexed_text(document: &str, hash: Option<&str>) -> Option<String> {
+    let name = match hash {
+        Some(hash) => hash_key(Path::new(document), hash)?,
+        None => path_key(document),
+    };
+
+    std::fs::read_to_string(cache_dir().join(name)).ok()
+}
+
+/// Includes the extractor, which can change with the config, and the index
+/// version, which changes with what extractors produce.
+fn hash_key(path: &Path, hash: &str) -> Option<String> {
+    let extractor = REGISTRY.extractor_name(path)?;
+    Some(format!("{}-{}-v{}", hash, extractor, INDEX_VERSION))
+}
+
+fn path_key(document: &str) -> String {
+    format!("path-{:x}", md5::compute(document))
+}
+
+fn emphasized_path(cache_path: &Path) -> PathBuf {
+    cache_path.with_extension("emphasized")
+}
+
+fn touch(cache_path: &Path) {
+    let _ = File::options()
+        .append(true)
+        .open(cache_path)
+        .and_then(|file| file.set_modified(SystemTime::now()));
+}
+
+/// Written then renamed so a half written entry is never read back.
+fn write(cache_path: &Path, text: &str) {
+    let temp_path = cache_path.with_extension("tmp");
+    if std::fs::create_dir_all(cache_dir()).is_ok() && std::fs::write(&temp_path, text).is_ok() {
+        let _ = std::fs::rename(&temp_path, cache_path);
+    }
+}
+
+/// Removes the text of files that haven't been indexed since `since`.
+pub fn prune(since: SystemTime) {
+    let entries = match std::fs::read_dir(cache_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.flatten() {
+        let modified = entry.metadata().and_then(|m| m.modified());
+        if modified.is_ok_and(|modified| modified < since) {
+            let _ = std::fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn cache_dir() -> PathBuf {
+    PathBuf::from(&CONF.indexing.location).join("text_cache")
+}
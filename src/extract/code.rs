@@ -0,0 +1,383 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use super::{ContentExtractor, Document};
+
+/// Source files. Identifiers are kept and split up by the tokenizer later,
+/// keywords are dropped and the names of functions, types and such are
+/// emphasized so searching for them finds where they're defined.
+pub struct Code;
+
+struct Language {
+    extensions: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    /// Line comments only start a word, as in shells where `$#` isn't one.
+    comments_start_words: bool,
+    block_comments: &'static [(&'static str, &'static str)],
+    quotes: &'static [char],
+    /// `'` starts a character literal when one closes it right after, and
+    /// is a lifetime or label otherwise.
+    char_literals: bool,
+    /// Separated by whitespace.
+    keywords: &'static str,
+    /// Keywords followed by the name of the thing being defined.
+    definitions: &'static str,
+}
+
+const RUST: Language = Language {
+    extensions: &["rs"],
+    line_comments: &["//"],
+    comments_start_words: false,
+    block_comments: &[("/*", "*/")],
+    quotes: &['"'],
+    char_literals: true,
+    keywords: "as async await break const continue crate dyn else enum extern false fn for if \
+        impl in let loop match mod move mut pub ref return self Self static struct \
+        super trait true type unsafe use where while Some None Ok Err Option Result \
+        String Vec Box usize isize u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 bool str char \
+        derive unwrap clone",
+    definitions: "fn struct enum trait type mod union macro_rules",
+};
+
+const PYTHON: Language = Language {
+    extensions: &["py", "pyw", "pyi"],
+    line_comments: &["#"],
+    comments_start_words: false,
+    block_comments: &[],
+    quotes: &['"', '\''],
+    char_literals: false,
+    keywords: "and as assert async await break class continue def del elif else except False \
+        finally for from global if import in is lambda None nonlocal not or pass raise \
+        return True try while with yield self cls print len range str int float list \
+        dict set tuple",
+    definitions: "def class",
+};
+
+const JAVASCRIPT: Language = Language {
+    extensions: &["js", "jsx", "mjs", "cjs", "ts", "tsx"],
+    line_comments: &["//"],
+    comments_start_words: false,
+    block_comments: &[("/*", "*/")],
+    quotes: &['"', '\'', '`'],
+    char_literals: false,
+    keywords: "async await break case catch class const continue debugger default delete do \
+        else export extends false finally for from function if import in instanceof \
+        let new null of return static super switch this throw true try typeof \
+        undefined var void while with yield interface type enum implements private \
+        protected public readonly string number boolean any console log document \
+        window",
+    definitions: "function class interface type enum",
+};
+
+const C: Language = Language {
+    extensions: &[
+        "c", "h", "cc", "cpp", "cxx", "hh", "hpp", "hxx", "java", "cs", "kt", "swift",
+    ],
+    line_comments: &["//"],
+    comments_start_words: false,
+    block_comments: &[("/*", "*/")],
+    quotes: &['"', '\''],
+    char_literals: false,
+    keywords: "auto break case char const continue default do double else enum extern float \
+        for goto if inline int long register return short signed sizeof static struct \
+        switch typedef union unsigned void volatile while bool true false class \
+        namespace template typename public private protected virtual override new \
+        delete this using nullptr NULL include define ifdef ifndef endif std import \
+        package extends implements interface final abstract throws throw try catch \
+        null String var val fun let func self",
+    definitions: "struct class enum union namespace interface define typedef fun func",
+};
+
+const GO: Language = Language {
+    extensions: &["go"],
+    line_comments: &["//"],
+    comments_start_words: false,
+    block_comments: &[("/*", "*/")],
+    quotes: &['"', '`'],
+    char_literals: false,
+    keywords: "break case chan const continue default defer else fallthrough for func go goto \
+        if import interface map package range return select struct switch type var nil \
+        true false err error string int int64 bool byte fmt",
+    definitions: "func type",
+};
+
+const SHELL: Language = Language {
+    extensions: &["sh", "bash", "zsh", "fish"],
+    line_comments: &["#"],
+    comments_start_words: true,
+    block_comments: &[],
+    quotes: &['"', '\''],
+    char_literals: false,
+    keywords: "if then else elif fi for while until do done case esac in function return \
+        local export echo exit set unset true false end",
+    definitions: "function",
+};
+
+const RUBY: Language = Language {
+    extensions: &["rb"],
+    line_comments: &["#"],
+    comments_start_words: false,
+    block_comments: &[("=begin", "=end")],
+    quotes: &['"', '\''],
+    char_literals: false,
+    keywords: "alias and begin break case class def defined do else elsif end ensure false \
+        for if in module next nil not or redo rescue retry return self super then true \
+        undef unless until when while yield require puts attr_accessor",
+    definitions: "def class module",
+};
+
+const LANGUAGES: [&Language; 7] = [&RUST, &PYTHON, &JAVASCRIPT, &C, &GO, &SHELL, &RUBY];
+
+/// Every extension with a language, used when source code indexing is on.
+pub fn extensions() -> Vec<&'static str> {
+    LANGUAGES
+        .iter()
+        .flat_map(|l| l.extensions.iter().copied())
+        .collect()
+}
+
+impl ContentExtractor for Code {
+    fn name(&self) -> &str {
+        "code"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        Some(self.extract_document(path)?.text)
+    }
+
+    fn extract_document(&self, path: &Path) -> Option<Document> {
+        let source = std::fs::read_to_string(path).ok()?;
+
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        // Types mapped to "code" in the config fall back to C-like rules.
+        let language = LANGUAGES
+            .iter()
+            .find(|l| l.extensions.contains(&ext.as_str()))
+            .copied()
+            .unwrap_or(&C);
+
+        Some(code_text(&source, language))
+    }
+}
+
+/// The comments, strings and identifiers of `source`, with the defined
+/// names emphasized.
+fn code_text(source: &str, language: &Language) -> Document {
+    let mut document = Document::default();
+    let mut previous = String::new();
+
+    for span in spans(source, language) {
+        match span {
+            Span::Text(prose) => {
+                document.text.push_str(prose);
+                document.text.push('\n');
+            }
+            Span::Identifier(identifier) => {
+                if !is_one_of(identifier, language.keywords) {
+                    document.text.push_str(identifier);
+                    document.text.push(' ');
+
+                    if is_one_of(&previous, language.definitions) {
+                        document.emphasized.push_str(identifier);
+                        document.emphasized.push(' ');
+                    }
+                }
+
+                previous = identifier.to_string();
+            }
+        }
+    }
+
+    document
+}
+
+fn is_one_of(word: &str, words: &str) -> bool {
+    words.split_whitespace().any(|w| w == word)
+}
+
+#[derive(Debug, PartialEq)]
+enum Span<'a> {
+    /// Comments and the contents of strings.
+    Text(&'a str),
+    Identifier(&'a str),
+}
+
+fn spans<'a>(source: &'a str, language: &Language) -> Vec<Span<'a>> {
+    let mut spans = vec![];
+    let mut rest = source;
+
+    while let Some(c) = rest.chars().next() {
+        let comment = language
+            .line_comments
+            .iter()
+            .find(|p| rest.starts_with(**p))
+            .filter(|_| !language.comments_start_words || starts_word(source, rest));
+
+        if let Some(prefix) = comment {
+            let end = rest.find('\n').unwrap_or(rest.len());
+            spans.push(Span::Text(&rest[prefix.len()..end]));
+            rest = &rest[end..];
+        } else if let Some((open, close)) = language
+            .block_comments
+            .iter()
+            .find(|(open, _)| rest.starts_with(open))
+        {
+            let body = &rest[open.len()..];
+            let end = body.find(close).unwrap_or(body.len());
+            spans.push(Span::Text(&body[..end]));
+            rest = &body[(end + close.len()).min(body.len())..];
+        } else if let Some(len) = char_literal_len(rest, language) {
+            rest = &rest[len..];
+        } else if language.quotes.contains(&c) {
+            let body = &rest[1..];
+            let end = string_end(body, c);
+            spans.push(Span::Text(&body[..end]));
+            rest = &body[(end + 1).min(body.len())..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            spans.push(Span::Identifier(&rest[..end]));
+            rest = &rest[end..];
+        } else {
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    spans
+}
+
+/// Whether `rest`, a suffix of `source`, starts after whitespace or an
+/// operator rather than in the middle of a word.
+fn starts_word(source: &str, rest: &str) -> bool {
+    let before = &source[..source.len() - rest.len()];
+    before
+        .chars()
+        .next_back()
+        .is_none_or(|c| c.is_whitespace() || ";|&()".contains(c))
+}
+
+/// The length of the character literal `rest` starts with, like `'"'` or
+/// `'\n'`.
+fn char_literal_len(rest: &str, language: &Language) -> Option<usize> {
+    let body = rest.strip_prefix('\'').filter(|_| language.char_literals)?;
+
+    let end = if body.starts_with('\\') {
+        string_end(body, '\'')
+    } else {
+        body.chars().next()?.len_utf8()
+    };
+
+    // The longest escape is `\u{10FFFF}`.
+    (end <= 10 && body[end..].starts_with('\'')).then_some(end + 2)
+}
+
+/// Where the string closed by `quote` ends, skipping escaped quotes.
+fn string_end(body: &str, quote: char) -> usize {
+    let mut escaped = false;
+
+    for (i, c) in body.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return i;
+        }
+    }
+
+    body.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rust() {
+        let document = code_text(
+            "/// Reads the config.\npub fn parse_config(path: &str) -> Config {\n    let msg = \"can't \\\"open\\\"\"; // fallback\n}",
+            &RUST,
+        );
+
+        let text = document.text;
+        assert_eq!(text.matches("parse_config").count(), 1);
+        assert_eq!(document.emphasized.trim(), "parse_config");
+        assert!(text.contains("Reads the config."));
+        assert!(text.contains("can't \\\"open\\\""));
+        assert!(text.contains("Config"));
+        assert!(!text.contains("pub"));
+        assert!(!text.contains("let"));
+    }
+
+    #[test]
+    fn rust_literals() {
+        let spans = spans(
+            "fn quote<'a>(s: &'a str) -> char { if s == \"x\" { '\"' } else { '\\'' } }",
+            &RUST,
+        );
+
+        assert_eq!(
+            spans,
+            vec![
+                Span::Identifier("fn"),
+                Span::Identifier("quote"),
+                Span::Identifier("a"),
+                Span::Identifier("s"),
+                Span::Identifier("a"),
+                Span::Identifier("str"),
+                Span::Identifier("char"),
+                Span::Identifier("if"),
+                Span::Identifier("s"),
+                Span::Text("x"),
+                Span::Identifier("else"),
+            ]
+        );
+    }
+
+    #[test]
+    fn shell() {
+        let spans = spans("echo $# args # count\nshift", &SHELL);
+
+        assert_eq!(
+            spans,
+            vec![
+                Span::Identifier("echo"),
+                Span::Identifier("args"),
+                Span::Text(" count"),
+                Span::Identifier("shift"),
+            ]
+        );
+    }
+
+    #[test]
+    fn python() {
+        let spans = spans("class ParseConfig:  # parser\n    x = 'a'\n", &PYTHON);
+
+        assert_eq!(
+            spans,
+            vec![
+                Span::Identifier("class"),
+                Span::Identifier("ParseConfig"),
+                Span::Text(" parser"),
+                Span::Identifier("x"),
+                Span::Text("a"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,160 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    io::Read,
+    path::Path,
+    process::{Command as Process, Stdio},
+    time::{Duration, Instant},
+};
+
+use crate::config::{CommandExtractor, ExtractorLimits};
+
+use super::ContentExtractor;
+
+/// Runs an external converter, e.g. `pdftotext {path} -`, and takes whatever
+/// it prints as the file's text.
+pub struct Command {
+    name: String,
+    script: String,
+    timeout: Duration,
+    max_output: usize,
+}
+
+impl Command {
+    pub fn new(config: &CommandExtractor, limits: &ExtractorLimits) -> Self {
+        Command {
+            name: config.name.clone(),
+            script: to_script(&config.command),
+            timeout: Duration::from_secs_f32(limits.timeout_secs),
+            max_output: max_output(limits),
+        }
+    }
+}
+
+/// The path is passed as an argument rather than pasted into the command so
+/// it doesn't need escaping. Commands without `{path}` get it at the end.
+fn to_script(command: &str) -> String {
+    if command.contains("{path}") {
+        command.replace("{path}", "\"$1\"")
+    } else {
+        format!("{} \"$1\"", command)
+    }
+}
+
+impl ContentExtractor for Command {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        let output = run(
+            Process::new("bash")
+                .arg("-c")
+                .arg(&self.script)
+                .arg("glimpse")
+                .arg(path),
+            self.timeout,
+            self.max_output,
+        )?;
+
+        Some(String::from_utf8_lossy(&output).into_owned())
+    }
+}
+
+/// Commands can print as much as the extractor would read from a file.
+pub fn max_output(limits: &ExtractorLimits) -> usize {
+    (limits.max_size_MiB as f64 * 1024. * 1024.) as usize
+}
+
+/// Runs `process` and gives what it printed, or nothing if it failed or
+/// took longer than `timeout`, in which case it's killed. Output past
+/// `max_output` bytes is cut off and the process stopped.
+pub fn run(process: &mut Process, timeout: Duration, max_output: usize) -> Option<Vec<u8>> {
+    let mut child = process
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    let stdout = child.stdout.take()?;
+    let reader = std::thread::spawn(move || {
+        let mut output = vec![];
+        stdout
+            .take(max_output as u64)
+            .read_to_end(&mut output)
+            .map(|_| output)
+    });
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().ok()? {
+            break status;
+        }
+
+        if start.elapsed() > timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+
+        std::thread::sleep(Duration::from_millis(20));
+    };
+
+    let output = reader.join().ok()?.ok()?;
+
+    // Cut off output ends in the command being killed for writing to a
+    // closed pipe, rather than failing.
+    if !status.success() && output.len() < max_output {
+        return None;
+    }
+
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_placeholder() {
+        assert_eq!(to_script("pdftotext {path} -"), "pdftotext \"$1\" -");
+        assert_eq!(to_script("pandoc -t plain"), "pandoc -t plain \"$1\"");
+    }
+
+    #[test]
+    fn runs_command() {
+        let extractor = Command::new(
+            &CommandExtractor {
+                name: "echo".to_string(),
+                command: "echo hello {path}".to_string(),
+                extensions: vec![],
+                mime_types: vec![],
+            },
+            &ExtractorLimits::default(),
+        );
+
+        let text = extractor.extract(Path::new("it's a file")).unwrap();
+        assert_eq!(text, "hello it's a file\n");
+    }
+
+    #[test]
+    fn cuts_off_output() {
+        let output = run(&mut Process::new("yes"), Duration::from_secs(5), 10).unwrap();
+        assert_eq!(output, b"y\ny\ny\ny\ny\n");
+    }
+}
@@ -0,0 +1,105 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use crate::tfidf::is_numeric;
+
+use super::ContentExtractor;
+
+/// Comma, semicolon or tab separated values. Only fields with some text in
+/// them are kept.
+pub struct Csv;
+
+impl ContentExtractor for Csv {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        let contents = String::from_utf8_lossy(&std::fs::read(path).ok()?).into_owned();
+
+        let mut text = String::new();
+        for field in fields(&contents, delimiter(&contents)) {
+            if !is_numeric(&field) {
+                text.push_str(&field);
+                text.push('\n');
+            }
+        }
+
+        Some(text)
+    }
+}
+
+/// Whichever of the usual delimiters shows up most in the first line.
+fn delimiter(contents: &str) -> char {
+    let header = contents.lines().next().unwrap_or_default();
+
+    [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|d| header.matches(*d).count())
+        .unwrap_or(',')
+}
+
+/// Splits on `delimiter` and newlines, except inside double quotes.
+fn fields(contents: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if !quoted && (c == delimiter || c == '\n' || c == '\r') => {
+                fields.push(std::mem::take(&mut field));
+            }
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields.retain(|f| !f.trim().is_empty());
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_fields() {
+        let csv = "name,amount,note\n\"Smith, Jane\",12.50,\"said \"\"hi\"\"\"\n";
+
+        assert_eq!(delimiter(csv), ',');
+        assert_eq!(
+            fields(csv, ','),
+            vec![
+                "name",
+                "amount",
+                "note",
+                "Smith, Jane",
+                "12.50",
+                "said \"hi\""
+            ]
+        );
+        assert_eq!(delimiter("a;b;c\n1,5;2;3"), ';');
+    }
+}
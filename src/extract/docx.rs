@@ -0,0 +1,68 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use docx_rs::*;
+
+use super::ContentExtractor;
+
+pub struct Docx;
+
+impl ContentExtractor for Docx {
+    fn name(&self) -> &str {
+        "docx"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        let bytes = std::fs::read(path).ok()?;
+
+        let doc = docx_rs::read_docx(&bytes).ok()?.document;
+        let contents = get_doc_text(doc.children);
+
+        Some(contents)
+    }
+}
+
+fn get_doc_text(doc: Vec<DocumentChild>) -> String {
+    // This just has to be like this...
+    // This crate isn't really meant to be used like this I think.
+    let mut ret = String::new();
+    for child in doc {
+        if let DocumentChild::Paragraph(paragraph) = child {
+            for child in paragraph.children {
+                handle_paragraph_child(child, &mut ret);
+            }
+        }
+    }
+    ret
+}
+
+#[inline]
+fn handle_paragraph_child(child: ParagraphChild, ret: &mut String) {
+    if let ParagraphChild::Run(run) = child {
+        handle_run(run, ret);
+    }
+}
+
+#[inline]
+fn handle_run(run: Box<Run>, ret: &mut String) {
+    for child in run.children {
+        if let RunChild::Text(text) = child {
+            *ret += text.text.to_string().as_str();
+        }
+    }
+}
@@ -0,0 +1,161 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{collections::HashMap, path::Path};
+
+use super::{
+    zipped_xml::{self, Archive, Markup},
+    ContentExtractor,
+};
+
+pub struct Epub;
+
+const MARKUP: Markup = Markup {
+    breaks: &[
+        "p", "div", "br", "li", "td", "th", "h1", "h2", "h3", "h4", "h5", "h6",
+    ],
+    // The head of every chapter usually just repeats the book's title.
+    skip: &["head", "script", "style"],
+};
+
+impl ContentExtractor for Epub {
+    fn name(&self) -> &str {
+        "epub"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        let mut archive = zipped_xml::open(path)?;
+
+        let chapters = match reading_order(&mut archive) {
+            Some(chapters) if !chapters.is_empty() => chapters,
+            _ => archive
+                .file_names()
+                .filter(|name| is_html(name))
+                .map(String::from)
+                .collect(),
+        };
+
+        let mut text = String::new();
+        for chapter in chapters {
+            if let Some(xhtml) = zipped_xml::read_member(&mut archive, &chapter) {
+                text.push_str(&zipped_xml::xml_to_text(&xhtml, &MARKUP));
+            }
+        }
+
+        Some(text)
+    }
+}
+
+fn is_html(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with(".xhtml") || name.ends_with(".html") || name.ends_with(".htm")
+}
+
+/// The chapters in the order the package document's spine lists them.
+fn reading_order(archive: &mut Archive) -> Option<Vec<String>> {
+    let container = zipped_xml::read_member(archive, "META-INF/container.xml")?;
+    let package_path = zipped_xml::start_elements(&container, "rootfile")
+        .find_map(|e| zipped_xml::attribute(&e, "full-path").map(String::from))?;
+
+    let package = zipped_xml::read_member(archive, &package_path)?;
+    let base = match package_path.rfind('/') {
+        Some(index) => &package_path[..=index],
+        None => "",
+    };
+
+    let manifest = zipped_xml::start_elements(&package, "item")
+        .filter_map(|e| {
+            let id = zipped_xml::attribute(&e, "id")?;
+            let href = zipped_xml::attribute(&e, "href")?;
+            Some((id.to_string(), format!("{}{}", base, unescape_href(href))))
+        })
+        .collect::<HashMap<String, String>>();
+
+    let chapters = zipped_xml::start_elements(&package, "itemref")
+        .filter_map(|e| manifest.get(zipped_xml::attribute(&e, "idref")?).cloned())
+        .collect();
+
+    Some(chapters)
+}
+
+/// Hrefs are URLs so spaces and such in file names are percent encoded.
+fn unescape_href(href: &str) -> String {
+    let bytes = href.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let decoded = match bytes[i] {
+            b'%' => href
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+
+        match decoded {
+            Some(byte) => {
+                unescaped.push(byte);
+                i += 3;
+            }
+            None => {
+                unescaped.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spine_order() {
+        let path = &std::env::temp_dir().join(format!("glimpse-book-{}.epub", std::process::id()));
+
+        zipped_xml::tests::write_archive(
+            path,
+            &[
+                ("mimetype", "application/epub+zip"),
+                (
+                    "META-INF/container.xml",
+                    r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#,
+                ),
+                (
+                    "OEBPS/content.opf",
+                    r#"<package><manifest>
+<item id="a" href="chapter%201.xhtml"/><item id="b" href="two.xhtml"/>
+</manifest><spine><itemref idref="b"/><itemref idref="a"/></spine></package>"#,
+                ),
+                (
+                    "OEBPS/chapter 1.xhtml",
+                    "<html><head><title>Manual</title></head><body><p>Installation</p></body></html>",
+                ),
+                (
+                    "OEBPS/two.xhtml",
+                    "<html><body><h1>Preface</h1><p>Thanks&nbsp;all</p></body></html>",
+                ),
+            ],
+        );
+
+        let text = Epub.extract(path);
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(text.unwrap(), "Preface\nThanks all\nInstallation\n");
+    }
+}
@@ -0,0 +1,435 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use encoding_rs::{Encoding, WINDOWS_1252};
+
+use super::{ContentExtractor, Document};
+
+/// Saved web pages and Markdown, which can have HTML mixed in.
+pub struct Html;
+
+const SKIPPED_ELEMENTS: [&str; 4] = ["script", "style", "template", "svg"];
+
+const BLOCK_ELEMENTS: [&str; 24] = [
+    "p",
+    "div",
+    "br",
+    "li",
+    "ul",
+    "ol",
+    "tr",
+    "td",
+    "th",
+    "table",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "pre",
+    "blockquote",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "nav",
+    "hr",
+];
+
+impl ContentExtractor for Html {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        Some(self.extract_document(path)?.text)
+    }
+
+    /// Words in the title say more about the page than words in the body.
+    fn extract_document(&self, path: &Path) -> Option<Document> {
+        let page = parse(&read(path)?);
+        let text = format!("{}\n{}\n{}", page.title, page.description, page.body);
+
+        Some(Document {
+            text,
+            emphasized: page.title,
+        })
+    }
+}
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Page {
+    pub title: String,
+    pub description: String,
+    pub body: String,
+}
+
+/// Reads a page in whatever encoding its `<meta charset>` says, falling back
+/// to Windows-1252 like browsers do.
+pub fn read(path: &Path) -> Option<String> {
+    let bytes = std::fs::read(path).ok()?;
+
+    if let Ok(text) = std::str::from_utf8(&bytes) {
+        return Some(text.to_string());
+    }
+
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).to_ascii_lowercase();
+    let encoding = head
+        .find("charset=")
+        .and_then(|i| {
+            let label = head[i + "charset=".len()..]
+                .trim_start_matches(['"', '\''])
+                .split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+                .next()?;
+            Encoding::for_label(label.as_bytes())
+        })
+        .unwrap_or(WINDOWS_1252);
+
+    Some(encoding.decode(&bytes).0.into_owned())
+}
+
+/// Readable text of an HTML page for the preview window.
+pub fn preview_text(source: &str) -> String {
+    let page = parse(source);
+
+    let title = page.title.trim();
+    let lines = [title, ""]
+        .into_iter()
+        .chain(page.body.lines().map(str::trim));
+
+    // Indentation from the source and runs of blank lines are dropped.
+    let mut text = String::new();
+    let mut blank = false;
+    for line in lines {
+        if line.is_empty() {
+            blank = !text.is_empty();
+        } else {
+            if blank {
+                text.push('\n');
+            }
+            text.push_str(line);
+            text.push('\n');
+            blank = false;
+        }
+    }
+
+    text
+}
+
+/// Drops tags, comments, scripts and styles and decodes entities. Text that
+/// isn't markup is left as it is, so this also works on Markdown.
+pub fn parse(source: &str) -> Page {
+    let mut page = Page::default();
+    let mut in_title = false;
+
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        if c == '&' {
+            let (decoded, len) = decode_entity(rest);
+            push(&mut page, in_title, &decoded);
+            rest = &rest[len..];
+            continue;
+        }
+
+        if c != '<' {
+            push(&mut page, in_title, &rest[..c.len_utf8()]);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = match comment.find("-->") {
+                Some(end) => &comment[end + 3..],
+                None => "",
+            };
+            continue;
+        }
+
+        let tag = match parse_tag(rest) {
+            Some(tag) => tag,
+            None => {
+                // A lone "<", as in "a < b".
+                push(&mut page, in_title, "<");
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        rest = &rest[tag.len..];
+
+        if tag.closing {
+            if tag.name == "title" {
+                in_title = false;
+            }
+        } else if SKIPPED_ELEMENTS.contains(&tag.name.as_str()) {
+            rest = skip_element(rest, &tag.name);
+        } else if tag.name == "title" {
+            in_title = true;
+        } else if tag.name == "meta" {
+            let name = tag.attribute("name").or(tag.attribute("property"));
+            if matches!(name, Some("description" | "og:description")) {
+                page.description = decode_entities(tag.attribute("content").unwrap_or_default());
+            }
+        }
+
+        if BLOCK_ELEMENTS.contains(&tag.name.as_str()) {
+            push(&mut page, in_title, "\n");
+        }
+    }
+
+    page
+}
+
+fn push(page: &mut Page, in_title: bool, text: &str) {
+    if in_title {
+        page.title.push_str(text);
+    } else {
+        page.body.push_str(text);
+    }
+}
+
+struct Tag {
+    name: String,
+    closing: bool,
+    attributes: Vec<(String, String)>,
+    len: usize,
+}
+
+impl Tag {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Parses the tag at the start of `source`. Doctypes and processing
+/// instructions come back as tags with no name.
+fn parse_tag(source: &str) -> Option<Tag> {
+    let inner = source.strip_prefix('<')?;
+    let (closing, inner) = match inner.strip_prefix('/') {
+        Some(inner) => (true, inner),
+        None => (false, inner),
+    };
+
+    let first = inner.chars().next()?;
+    if !(first.is_ascii_alphabetic() || (!closing && (first == '!' || first == '?'))) {
+        return None;
+    }
+
+    let mut quote = None;
+    let end = inner.char_indices().find_map(|(i, c)| {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+        None
+    })?;
+
+    let body = &inner[..end];
+    let name_len = body
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+        .unwrap_or(body.len());
+
+    let name = if first.is_ascii_alphabetic() {
+        body[..name_len].to_ascii_lowercase()
+    } else {
+        String::new()
+    };
+
+    Some(Tag {
+        name,
+        closing,
+        attributes: attributes(&body[name_len..]),
+        len: source.len() - inner.len() + end + 1,
+    })
+}
+
+fn attributes(source: &str) -> Vec<(String, String)> {
+    let mut attributes = vec![];
+    let mut rest = source.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+
+    while !rest.is_empty() {
+        let name_len = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_len].to_ascii_lowercase();
+        rest = rest[name_len..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (v, remaining) = match after.chars().next() {
+                Some(q @ ('"' | '\'')) => match after[1..].find(q) {
+                    Some(end) => (&after[1..end + 1], &after[end + 2..]),
+                    None => (&after[1..], ""),
+                },
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = v.to_string();
+            rest = remaining;
+        }
+
+        if name_len == 0 && value.is_empty() {
+            // Stray character, skip it so the loop moves on.
+            rest = &rest[rest.chars().next().map_or(0, char::len_utf8)..];
+        } else {
+            attributes.push((name, value));
+        }
+
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+    }
+
+    attributes
+}
+
+/// Skips to after the closing tag of a `<script>` or such, whose contents
+/// aren't markup.
+fn skip_element<'a>(source: &'a str, name: &str) -> &'a str {
+    let closing = format!("</{}", name);
+    let lowercase = source.to_ascii_lowercase();
+
+    match lowercase.find(&closing) {
+        Some(start) => match source[start..].find('>') {
+            Some(end) => &source[start + end + 1..],
+            None => "",
+        },
+        None => "",
+    }
+}
+
+fn decode_entities(str: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = str;
+
+    while let Some(i) = rest.find('&') {
+        decoded.push_str(&rest[..i]);
+        let (entity, len) = decode_entity(&rest[i..]);
+        decoded.push_str(&entity);
+        rest = &rest[i + len..];
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+/// Decodes the entity at the start of `source` and gives how many bytes it
+/// took up. Unknown entities are left alone.
+fn decode_entity(source: &str) -> (String, usize) {
+    let not_entity = ("&".to_string(), 1);
+
+    let end = match source[1..].find(';') {
+        Some(end) if end <= 10 => end + 1,
+        _ => return not_entity,
+    };
+    let entity = &source[1..end];
+
+    let decoded = match entity.strip_prefix('#') {
+        Some(number) => {
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => number.parse::<u32>().ok(),
+            };
+            code.and_then(char::from_u32).map(String::from)
+        }
+        None => named_entity(entity).map(String::from),
+    };
+
+    match decoded {
+        Some(decoded) => (decoded, end + 1),
+        None => not_entity,
+    }
+}
+
+fn named_entity(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "amp" => "&",
+        "lt" => "<",
+        "gt" => ">",
+        "quot" => "\"",
+        "apos" => "'",
+        "nbsp" => " ",
+        "shy" => "",
+        "ndash" => "–",
+        "mdash" => "—",
+        "hellip" => "…",
+        "lsquo" => "‘",
+        "rsquo" => "’",
+        "ldquo" => "“",
+        "rdquo" => "”",
+        "laquo" => "«",
+        "raquo" => "»",
+        "copy" => "©",
+        "reg" => "®",
+        "trade" => "™",
+        "middot" => "·",
+        "bull" => "•",
+        "eacute" => "é",
+        "egrave" => "è",
+        "aacute" => "á",
+        "agrave" => "à",
+        "ouml" => "ö",
+        "uuml" => "ü",
+        "auml" => "ä",
+        "szlig" => "ß",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page() {
+        let page = parse(
+            r#"<!DOCTYPE html><html><head><title>Tom &amp; Jerry</title>
+<meta name="description" content="A cat &amp; a mouse">
+<style>div { color: red; }</style>
+<script type="text/javascript">function f() { return 1 < 2; }</script></head>
+<body><!-- nav --><div class="x">Caf&eacute; &#8212; open<br/>daily</div></body></html>"#,
+        );
+
+        assert_eq!(page.title, "Tom & Jerry");
+        assert_eq!(page.description, "A cat & a mouse");
+        assert!(page.body.contains("Café — open\ndaily"));
+        assert!(!page.body.contains("function"));
+        assert!(!page.body.contains("color"));
+        assert!(!page.body.contains("class"));
+    }
+
+    #[test]
+    fn markdown() {
+        let page = parse("# Notes\n\nif a < b && c\n<details>hidden &copy;</details>\n");
+        assert_eq!(page.body, "# Notes\n\nif a < b && c\nhidden ©\n");
+    }
+
+    #[test]
+    fn preview() {
+        let text = preview_text(
+            "<html><head><title>Docs</title></head>\n  <body>\n\n\n    <p>One</p>\n    <p>Two</p></body></html>",
+        );
+        assert_eq!(text, "Docs\n\nOne\n\nTwo\n");
+    }
+}
@@ -0,0 +1,611 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use base64::{
+    alphabet,
+    engine::{general_purpose::GeneralPurposeConfig, DecodePaddingMode, GeneralPurpose},
+    Engine,
+};
+use encoding_rs::{Encoding, UTF_8};
+
+use super::{html, limits_for, ContentExtractor, Document};
+
+/// Parts nested deeper than this, in multiparts or forwarded messages, are
+/// left out.
+const MAX_NESTING: usize = 8;
+
+/// Messages in a Maildir or saved as .eml files.
+pub struct Mail;
+
+impl ContentExtractor for Mail {
+    fn name(&self) -> &str {
+        "mail"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        Some(self.extract_document(path)?.text)
+    }
+
+    fn extract_document(&self, path: &Path) -> Option<Document> {
+        Some(Message::parse(&std::fs::read(path).ok()?).document())
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct Message {
+    pub subject: String,
+    pub from: String,
+    pub to: String,
+    pub date: String,
+    pub body: String,
+}
+
+impl Message {
+    pub fn parse(raw: &[u8]) -> Message {
+        Self::parse_nested(raw, 0)
+    }
+
+    fn parse_nested(raw: &[u8], depth: usize) -> Message {
+        let (headers, body) = split_headers(raw);
+
+        Message {
+            subject: header(&headers, "subject"),
+            from: header(&headers, "from"),
+            to: header(&headers, "to"),
+            date: header(&headers, "date"),
+            body: part_text(&headers, body, depth).unwrap_or_default(),
+        }
+    }
+
+    /// Everything worth searching for, headers first.
+    pub fn text(&self) -> String {
+        format!(
+            "{}\n{}\n{}\n{}\n\n{}",
+            self.subject, self.from, self.to, self.date, self.body
+        )
+    }
+
+    /// The text with the subject emphasized.
+    pub fn document(&self) -> Document {
+        Document {
+            text: self.text(),
+            emphasized: self.subject.clone(),
+        }
+    }
+}
+
+/// Files in the "cur" or "new" folder of a Maildir.
+pub fn is_maildir_message(path: &Path) -> bool {
+    let parent = match path.parent() {
+        Some(parent) => parent,
+        None => return false,
+    };
+
+    let in_mail_folder = parent
+        .file_name()
+        .is_some_and(|name| name == "cur" || name == "new");
+
+    in_mail_folder
+        && parent
+            .parent()
+            .is_some_and(|maildir| maildir.join("tmp").is_dir())
+}
+
+/// mbox files either have the extension or start with a "From " line.
+pub fn is_mbox(path: &Path) -> bool {
+    match path.extension() {
+        Some(ext) => ext == "mbox" || ext == "mbx",
+        None => {
+            let mut start = [0; 5];
+            std::fs::File::open(path)
+                .and_then(|mut file| file.read_exact(&mut start))
+                .is_ok_and(|_| &start == b"From ")
+        }
+    }
+}
+
+/// Messages in an mbox are indexed as "Inbox#3".
+pub fn mbox_message_path(mbox: &Path, index: usize) -> Option<String> {
+    Some(format!("{}#{}", mbox.to_str()?, index))
+}
+
+pub fn split_mbox_message_path(path: &str) -> Option<(PathBuf, usize)> {
+    let (mbox, index) = path.rsplit_once('#')?;
+    let index = index.parse().ok()?;
+    let mbox = PathBuf::from(mbox);
+
+    if mbox.is_file() && !Path::new(path).exists() {
+        Some((mbox, index))
+    } else {
+        None
+    }
+}
+
+/// Parses the messages of an mbox one at a time, cut off at the mail
+/// extractor's size limit.
+pub fn read_mbox(path: &Path, each: &mut dyn FnMut(Document)) -> Option<()> {
+    let mbox = BufReader::new(File::open(path).ok()?);
+
+    let limits = limits_for("mail");
+    let max_message_size = (limits.max_size_MiB as f64 * 1024. * 1024.) as usize;
+
+    for raw in mbox_messages(mbox, max_message_size) {
+        each(Message::parse(&raw).document());
+    }
+
+    Some(())
+}
+
+/// Reads an mbox a message at a time, splitting it on its "From " lines.
+/// Messages are cut off after `max_message_size` bytes.
+pub fn mbox_messages<R: BufRead>(reader: R, max_message_size: usize) -> MboxMessages<R> {
+    MboxMessages {
+        reader,
+        max_message_size,
+        current: None,
+        line: vec![],
+    }
+}
+
+pub struct MboxMessages<R> {
+    reader: R,
+    max_message_size: usize,
+    current: Option<Vec<u8>>,
+    line: Vec<u8>,
+}
+
+impl<R: BufRead> Iterator for MboxMessages<R> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        loop {
+            self.line.clear();
+
+            if read_line(&mut self.reader, &mut self.line, self.max_message_size) == 0 {
+                return self.current.take();
+            }
+
+            if self.line.starts_with(b"From ") {
+                match self.current.replace(vec![]) {
+                    Some(message) => return Some(message),
+                    None => continue,
+                }
+            }
+
+            let message = match self.current.as_mut() {
+                Some(message) if message.len() < self.max_message_size => message,
+                _ => continue,
+            };
+
+            // Lines starting with "From " in bodies are escaped with ">".
+            let quotes = self.line.iter().take_while(|b| **b == b'>').count();
+            if quotes > 0 && self.line[quotes..].starts_with(b"From ") {
+                message.extend_from_slice(&self.line[1..]);
+            } else {
+                message.extend_from_slice(&self.line);
+            }
+        }
+    }
+}
+
+/// Reads up to and including the next newline, keeping at most `max` bytes
+/// of it, and gives how many bytes were read.
+fn read_line(reader: &mut impl BufRead, line: &mut Vec<u8>, max: usize) -> usize {
+    let mut read = 0;
+
+    loop {
+        let buffer = match reader.fill_buf() {
+            Ok(buffer) if !buffer.is_empty() => buffer,
+            _ => return read,
+        };
+
+        let (end, is_end_of_line) = match buffer.iter().position(|b| *b == b'\n') {
+            Some(newline) => (newline + 1, true),
+            None => (buffer.len(), false),
+        };
+
+        let kept = end.min(max.saturating_sub(line.len()));
+        line.extend_from_slice(&buffer[..kept]);
+        reader.consume(end);
+        read += end;
+
+        if is_end_of_line {
+            return read;
+        }
+    }
+}
+
+type Headers = HashMap<String, String>;
+
+fn split_headers(raw: &[u8]) -> (Headers, &[u8]) {
+    let (head, body) = match find(raw, b"\r\n\r\n") {
+        Some(i) => (&raw[..i], &raw[i + 4..]),
+        None => match find(raw, b"\n\n") {
+            Some(i) => (&raw[..i], &raw[i + 2..]),
+            None => (raw, &raw[raw.len()..]),
+        },
+    };
+
+    let head = String::from_utf8_lossy(head);
+
+    // Long headers are folded onto lines starting with whitespace.
+    let mut lines: Vec<String> = vec![];
+    for line in head.lines() {
+        match lines.last_mut() {
+            Some(last) if line.starts_with([' ', '\t']) => {
+                last.push(' ');
+                last.push_str(line.trim());
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut headers = HashMap::new();
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            headers
+                .entry(name.trim().to_ascii_lowercase())
+                .or_insert_with(|| value.trim().to_string());
+        }
+    }
+
+    (headers, body)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn header(headers: &Headers, name: &str) -> String {
+    headers
+        .get(name)
+        .map(|value| decode_encoded_words(value))
+        .unwrap_or_default()
+}
+
+/// The readable text of a message or MIME part. Attachments and anything
+/// that isn't text give `None`.
+fn part_text(headers: &Headers, body: &[u8], depth: usize) -> Option<String> {
+    if depth > MAX_NESTING {
+        return None;
+    }
+
+    let disposition = headers
+        .get("content-disposition")
+        .map(|d| d.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    if disposition.starts_with("attachment") {
+        return None;
+    }
+
+    let (mime_type, params) = content_type(headers);
+
+    if let Some(subtype) = mime_type.strip_prefix("multipart/") {
+        let boundary = params.get("boundary")?;
+        let parts = multipart(body, boundary)
+            .into_iter()
+            .map(split_headers)
+            .collect::<Vec<_>>();
+
+        if subtype == "alternative" {
+            // Prefer plain text and fall back to whatever else there is.
+            let plain = parts
+                .iter()
+                .find(|(headers, _)| content_type(headers).0 == "text/plain");
+
+            return plain
+                .into_iter()
+                .chain(parts.iter())
+                .find_map(|(headers, body)| part_text(headers, body, depth + 1));
+        }
+
+        let texts = parts
+            .iter()
+            .filter_map(|(headers, body)| part_text(headers, body, depth + 1))
+            .collect::<Vec<String>>();
+
+        return Some(texts.join("\n"));
+    }
+
+    if mime_type == "message/rfc822" {
+        return Some(Message::parse_nested(body, depth + 1).text());
+    }
+
+    if mime_type != "text/plain" && mime_type != "text/html" {
+        return None;
+    }
+
+    let encoding = headers
+        .get("content-transfer-encoding")
+        .map(|e| e.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    let decoded = match encoding.as_str() {
+        "base64" => decode_base64(body)?,
+        "quoted-printable" => decode_quoted_printable(body, false),
+        _ => body.to_vec(),
+    };
+
+    let text = decode_charset(&decoded, params.get("charset").map(String::as_str));
+
+    if mime_type == "text/html" {
+        Some(html::parse(&text).body)
+    } else {
+        Some(text)
+    }
+}
+
+/// "text/plain; charset=utf-8" gives the type, lowercased, and the params.
+/// Messages without a type are plain text.
+fn content_type(headers: &Headers) -> (String, HashMap<String, String>) {
+    let value = match headers.get("content-type") {
+        Some(value) => value,
+        None => return ("text/plain".to_string(), HashMap::new()),
+    };
+
+    let mut fields = value.split(';');
+    let mime_type = fields
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    let params = fields
+        .filter_map(|field| {
+            let (name, value) = field.split_once('=')?;
+            Some((
+                name.trim().to_ascii_lowercase(),
+                value.trim().trim_matches('"').to_string(),
+            ))
+        })
+        .collect();
+
+    (mime_type, params)
+}
+
+fn multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let delimiter = delimiter.as_bytes();
+
+    let mut parts = vec![];
+    let mut start = None;
+    let mut offset = 0;
+
+    for line in body.split_inclusive(|b| *b == b'\n') {
+        if line.starts_with(delimiter) {
+            if let Some(start) = start {
+                parts.push(&body[start..offset]);
+            }
+
+            let closing = line[delimiter.len()..].starts_with(b"--");
+            if closing {
+                return parts;
+            }
+            start = Some(offset + line.len());
+        }
+        offset += line.len();
+    }
+
+    if let Some(start) = start {
+        parts.push(&body[start..]);
+    }
+
+    parts
+}
+
+fn decode_charset(bytes: &[u8], charset: Option<&str>) -> String {
+    let encoding = charset
+        .and_then(|charset| Encoding::for_label(charset.as_bytes()))
+        .unwrap_or(UTF_8);
+
+    encoding.decode(bytes).0.into_owned()
+}
+
+fn decode_base64(encoded: &[u8]) -> Option<Vec<u8>> {
+    let cleaned = encoded
+        .iter()
+        .filter(|b| !b.is_ascii_whitespace())
+        .copied()
+        .collect::<Vec<u8>>();
+
+    let engine = GeneralPurpose::new(
+        &alphabet::STANDARD,
+        GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+    );
+
+    engine.decode(cleaned).ok()
+}
+
+/// In headers ("Q" encoding) underscores stand for spaces.
+fn decode_quoted_printable(encoded: &[u8], header: bool) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(encoded.len());
+
+    let mut i = 0;
+    while i < encoded.len() {
+        match encoded[i] {
+            b'=' => {
+                let rest = &encoded[i + 1..];
+                if rest.starts_with(b"\r\n") {
+                    i += 3;
+                } else if rest.starts_with(b"\n") {
+                    i += 2;
+                } else if let Some(byte) = rest
+                    .get(..2)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    decoded.push(byte);
+                    i += 3;
+                } else {
+                    decoded.push(b'=');
+                    i += 1;
+                }
+            }
+            b'_' if header => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    decoded
+}
+
+/// Decodes RFC 2047 encoded words like "=?UTF-8?B?w6l0w6k=?=". Whitespace
+/// between two encoded words is dropped.
+fn decode_encoded_words(value: &str) -> String {
+    let mut decoded = String::new();
+    let mut pending_space = String::new();
+    let mut previous_was_encoded = false;
+
+    let mut rest = value;
+    while !rest.is_empty() {
+        let word = rest.strip_prefix("=?").and_then(|word| {
+            let (charset, word) = word.split_once('?')?;
+            let (encoding, word) = word.split_once('?')?;
+            let (text, remaining) = word.split_once("?=")?;
+
+            let bytes = match encoding.to_ascii_uppercase().as_str() {
+                "B" => decode_base64(text.as_bytes())?,
+                "Q" => decode_quoted_printable(text.as_bytes(), true),
+                _ => return None,
+            };
+
+            // Charsets can have a language after a "*", as in "utf-8*en".
+            let charset = charset.split('*').next().unwrap_or_default();
+            Some((decode_charset(&bytes, Some(charset)), remaining))
+        });
+
+        if let Some((text, remaining)) = word {
+            if !previous_was_encoded {
+                decoded.push_str(&pending_space);
+            }
+            pending_space.clear();
+            decoded.push_str(&text);
+            previous_was_encoded = true;
+            rest = remaining;
+            continue;
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+        if c.is_whitespace() {
+            pending_space.push(c);
+        } else {
+            decoded.push_str(&pending_space);
+            pending_space.clear();
+            decoded.push(c);
+            previous_was_encoded = false;
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_words() {
+        assert_eq!(
+            decode_encoded_words("=?UTF-8?B?w6l0w6k=?= =?ISO-8859-1?Q?_caf=E9?= report"),
+            "été café report"
+        );
+        assert_eq!(decode_encoded_words("Plain subject"), "Plain subject");
+    }
+
+    #[test]
+    fn multipart_message() {
+        let raw = b"From: Jane <jane@example.com>\r
+To: team@example.com\r
+Subject: =?utf-8?q?Quarterly_numbers?=\r
+ and plans\r
+Date: Mon, 4 Mar 2024 10:00:00 +0000\r
+Content-Type: multipart/mixed; boundary=\"outer\"\r
+\r
+--outer\r
+Content-Type: multipart/alternative; boundary=inner\r
+\r
+--inner\r
+Content-Type: text/html\r
+\r
+<p>html version</p>\r
+--inner\r
+Content-Type: text/plain; charset=iso-8859-1\r
+Content-Transfer-Encoding: quoted-printable\r
+\r
+Caf=E9 budget is=\r
+ approved\r
+--inner--\r
+--outer\r
+Content-Type: text/plain\r
+Content-Disposition: attachment; filename=secret.txt\r
+\r
+attached secret\r
+--outer--\r
+";
+
+        let message = Message::parse(raw);
+
+        assert_eq!(message.subject, "Quarterly numbers and plans");
+        assert_eq!(message.from, "Jane <jane@example.com>");
+        assert!(message.body.contains("Café budget is approved"));
+        assert!(!message.body.contains("html version"));
+        assert!(!message.body.contains("secret"));
+    }
+
+    #[test]
+    fn deeply_nested() {
+        let mut raw = b"Subject: Fwd\r\n".to_vec();
+        for _ in 0..100_000 {
+            raw.extend_from_slice(b"Content-Type: message/rfc822\r\n\r\n");
+        }
+        raw.extend_from_slice(b"Subject: Inner\r\n\r\nHidden\r\n");
+
+        let message = Message::parse(&raw);
+        assert_eq!(message.subject, "Fwd");
+        assert!(!message.body.contains("Hidden"));
+    }
+
+    #[test]
+    fn mbox() {
+        let mbox = b"From a@b Mon Mar 4 2024\nSubject: One\n\nHi\n>From here\n\nFrom c@d Tue Mar 5 2024\nSubject: Two\n\nBye\n";
+        let messages = mbox_messages(&mbox[..], usize::MAX).collect::<Vec<Vec<u8>>>();
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(Message::parse(&messages[0]).body, "Hi\nFrom here\n\n");
+        assert_eq!(Message::parse(&messages[1]).subject, "Two");
+
+        let cut_off = mbox_messages(&mbox[..], 16).collect::<Vec<Vec<u8>>>();
+        assert_eq!(cut_off.len(), 2);
+        assert_eq!(cut_off[0], b"Subject: One\n\nHi\n");
+    }
+}
@@ -0,0 +1,395 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use exif::{Context, Exif, In, Tag, Value};
+use symphonia::core::{
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey},
+    probe::Hint,
+};
+
+use super::ContentExtractor;
+
+/// Photos, music and videos by what their metadata says about them rather
+/// than their usually meaningless file names.
+pub struct Media;
+
+pub const IMAGE_EXTENSIONS: [&str; 8] =
+    ["jpg", "jpeg", "tif", "tiff", "heic", "heif", "png", "webp"];
+pub const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "flac", "ogg", "oga", "opus", "m4a", "wav"];
+pub const VIDEO_EXTENSIONS: [&str; 5] = ["mp4", "m4v", "mov", "mkv", "webm"];
+
+/// Every extension read for metadata, used when media indexing is on.
+pub fn extensions() -> Vec<&'static str> {
+    IMAGE_EXTENSIONS
+        .iter()
+        .chain(AUDIO_EXTENSIONS.iter())
+        .chain(VIDEO_EXTENSIONS.iter())
+        .copied()
+        .collect()
+}
+
+/// Labelled fields in the order they're shown in the preview window.
+#[derive(Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub fields: Vec<(&'static str, String)>,
+}
+
+impl Metadata {
+    fn push(&mut self, label: &'static str, value: Option<String>) {
+        if let Some(value) = value {
+            let value = value.trim().trim_matches('\0').trim();
+            if !value.is_empty() && !self.fields.iter().any(|(l, _)| *l == label) {
+                self.fields.push((label, value.to_string()));
+            }
+        }
+    }
+}
+
+impl ContentExtractor for Media {
+    fn name(&self) -> &str {
+        "media"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        let metadata = read(path)?;
+
+        let text = metadata
+            .fields
+            .into_iter()
+            .filter(|(label, _)| *label != "Duration")
+            .map(|(_, value)| value)
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Some(text)
+    }
+}
+
+pub fn read(path: &Path) -> Option<Metadata> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+
+    let metadata = if IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+        image_metadata(path)
+    } else if AUDIO_EXTENSIONS.contains(&ext.as_str()) || VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+        tagged_metadata(path, &ext)
+    } else {
+        None
+    }?;
+
+    if metadata.fields.is_empty() {
+        None
+    } else {
+        Some(metadata)
+    }
+}
+
+fn image_metadata(path: &Path) -> Option<Metadata> {
+    let mut metadata = Metadata::default();
+
+    if let Ok(exif) =
+        exif::Reader::new().read_from_container(&mut BufReader::new(File::open(path).ok()?))
+    {
+        let make = ascii_field(&exif, Tag::Make).unwrap_or_default();
+        let model = ascii_field(&exif, Tag::Model).unwrap_or_default();
+        // Models often repeat the make, as in "Canon" and "Canon EOS 80D".
+        let camera = if model.to_lowercase().starts_with(&make.to_lowercase()) {
+            model
+        } else {
+            format!("{} {}", make, model)
+        };
+
+        metadata.push("Camera", Some(camera));
+        metadata.push("Lens", ascii_field(&exif, Tag::LensModel));
+        metadata.push(
+            "Taken",
+            ascii_field(&exif, Tag::DateTimeOriginal)
+                .or_else(|| ascii_field(&exif, Tag::DateTime))
+                .map(|date| readable_date(&date)),
+        );
+        metadata.push("Place", comment_field(&exif, Tag::GPSAreaInformation));
+        metadata.push("Title", utf16_field(&exif, XP_TITLE));
+        metadata.push(
+            "Description",
+            ascii_field(&exif, Tag::ImageDescription)
+                .or_else(|| comment_field(&exif, Tag::UserComment))
+                .or_else(|| utf16_field(&exif, XP_COMMENT))
+                .or_else(|| utf16_field(&exif, XP_SUBJECT)),
+        );
+        metadata.push("Keywords", utf16_field(&exif, XP_KEYWORDS));
+        metadata.push("Artist", ascii_field(&exif, Tag::Artist));
+    }
+
+    // Place names are usually only in the XMP written by photo managers.
+    if let Some(place) = xmp_place(path) {
+        metadata.fields.retain(|(label, _)| *label != "Place");
+        metadata.push("Place", Some(place));
+    }
+
+    Some(metadata)
+}
+
+/// Windows' title, comment, keywords and subject fields.
+const XP_TITLE: Tag = Tag(Context::Tiff, 0x9c9b);
+const XP_COMMENT: Tag = Tag(Context::Tiff, 0x9c9c);
+const XP_KEYWORDS: Tag = Tag(Context::Tiff, 0x9c9e);
+const XP_SUBJECT: Tag = Tag(Context::Tiff, 0x9c9f);
+
+fn ascii_field(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => Some(String::from_utf8_lossy(values.first()?).into_owned()),
+        _ => None,
+    }
+}
+
+/// Fields that start with 8 bytes naming their character set.
+fn comment_field(exif: &Exif, tag: Tag) -> Option<String> {
+    let bytes = match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Undefined(bytes, _) => bytes.clone(),
+        _ => return None,
+    };
+
+    if bytes.len() < 8 {
+        return None;
+    }
+
+    let (charset, text) = bytes.split_at(8);
+    if charset.starts_with(b"UNICODE") {
+        Some(utf16(text))
+    } else {
+        Some(String::from_utf8_lossy(text).into_owned())
+    }
+}
+
+fn utf16_field(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Byte(bytes) => Some(utf16(bytes)),
+        _ => None,
+    }
+}
+
+fn utf16(bytes: &[u8]) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect::<Vec<u16>>();
+    String::from_utf16_lossy(&units)
+}
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// "2023:07:14 18:02:11" as "14 July 2023 18:02", so the month can be
+/// searched for by name.
+fn readable_date(date: &str) -> String {
+    let parts = date.split([':', ' ', '-']).collect::<Vec<&str>>();
+
+    let month = parts
+        .get(1)
+        .and_then(|m| m.parse::<usize>().ok())
+        .and_then(|m| MONTHS.get(m.checked_sub(1)?));
+
+    match (parts.as_slice(), month) {
+        ([year, _, day, hour, minute, ..], Some(month)) => format!(
+            "{} {} {} {}:{}",
+            day.trim_start_matches('0'),
+            month,
+            year,
+            hour,
+            minute
+        ),
+        ([year, _, day], Some(month)) => {
+            format!("{} {} {}", day.trim_start_matches('0'), month, year)
+        }
+        _ => date.to_string(),
+    }
+}
+
+/// How much of an image is searched for an XMP packet.
+const XMP_SEARCH_LENGTH: u64 = 256 * 1024;
+
+fn xmp_place(path: &Path) -> Option<String> {
+    let mut bytes = vec![];
+    File::open(path)
+        .ok()?
+        .take(XMP_SEARCH_LENGTH)
+        .read_to_end(&mut bytes)
+        .ok()?;
+
+    let text = String::from_utf8_lossy(&bytes);
+    let start = text.find("<x:xmpmeta")?;
+    let end = text[start..]
+        .find("</x:xmpmeta>")
+        .map_or(text.len(), |e| start + e);
+    let xmp = &text[start..end];
+
+    let parts = [
+        "Iptc4xmpCore:Location",
+        "photoshop:City",
+        "photoshop:State",
+        "photoshop:Country",
+    ]
+    .into_iter()
+    .filter_map(|name| xmp_property(xmp, name))
+    .collect::<Vec<String>>();
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(", "))
+    }
+}
+
+/// A simple XMP property, written either as an attribute or an element.
+fn xmp_property(xmp: &str, name: &str) -> Option<String> {
+    let value = if let Some(i) = xmp.find(&format!("{}=\"", name)) {
+        let rest = &xmp[i + name.len() + 2..];
+        &rest[..rest.find('"')?]
+    } else {
+        let open = format!("<{}>", name);
+        let rest = &xmp[xmp.find(&open)? + open.len()..];
+        &rest[..rest.find('<')?]
+    };
+
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// ID3, Vorbis comments and MP4 and Matroska tags.
+fn tagged_metadata(path: &Path, ext: &str) -> Option<Metadata> {
+    let source = MediaSourceStream::new(Box::new(File::open(path).ok()?), Default::default());
+
+    let mut hint = Hint::new();
+    hint.with_extension(ext);
+
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+
+    let mut metadata = Metadata::default();
+
+    // ID3 tags come before the container so are found while probing.
+    if let Some(probed_metadata) = probed.metadata.get() {
+        if let Some(revision) = probed_metadata.current() {
+            push_tags(&mut metadata, revision);
+        }
+    }
+
+    if let Some(revision) = probed.format.metadata().current() {
+        push_tags(&mut metadata, revision);
+    }
+
+    let duration = probed.format.default_track().and_then(|track| {
+        let params = &track.codec_params;
+        let time = params.time_base?.calc_time(params.n_frames?);
+        Some(time.seconds)
+    });
+    metadata.push("Duration", duration.map(readable_duration));
+
+    Some(metadata)
+}
+
+fn push_tags(metadata: &mut Metadata, revision: &MetadataRevision) {
+    let fields = [
+        ("Title", StandardTagKey::TrackTitle),
+        ("Artist", StandardTagKey::Artist),
+        ("Album artist", StandardTagKey::AlbumArtist),
+        ("Album", StandardTagKey::Album),
+        ("Genre", StandardTagKey::Genre),
+        ("Composer", StandardTagKey::Composer),
+        ("Date", StandardTagKey::Date),
+        ("Description", StandardTagKey::Description),
+        ("Comment", StandardTagKey::Comment),
+    ];
+
+    for (label, key) in fields {
+        let value = revision
+            .tags()
+            .iter()
+            .find(|tag| tag.std_key == Some(key))
+            .map(|tag| tag.value.to_string());
+
+        metadata.push(label, value);
+    }
+}
+
+fn readable_duration(seconds: u64) -> String {
+    match seconds {
+        0..=3599 => format!("{}:{:02}", seconds / 60, seconds % 60),
+        _ => format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        assert_eq!(readable_date("2023:07:14 18:02:11"), "14 July 2023 18:02");
+        assert_eq!(readable_date("2023-12-01"), "1 December 2023");
+        assert_eq!(readable_date("unknown"), "unknown");
+        assert_eq!(readable_duration(222), "3:42");
+        assert_eq!(readable_duration(3725), "1:02:05");
+    }
+
+    #[test]
+    fn xmp() {
+        let xmp = r#"<x:xmpmeta><rdf:Description photoshop:City="Lisbon"
+            photoshop:Country="Portugal"><Iptc4xmpCore:Location>Alfama</Iptc4xmpCore:Location>
+            </rdf:Description></x:xmpmeta>"#;
+
+        assert_eq!(xmp_property(xmp, "photoshop:City"), Some("Lisbon".into()));
+        assert_eq!(
+            xmp_property(xmp, "Iptc4xmpCore:Location"),
+            Some("Alfama".into())
+        );
+        assert_eq!(xmp_property(xmp, "photoshop:State"), None);
+    }
+}
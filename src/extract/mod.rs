@@ -0,0 +1,359 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+
+use crate::config::{ExtractorLimits, CONF};
+
+pub mod cache;
+mod code;
+pub mod command;
+mod csv;
+mod docx;
+mod epub;
+pub mod html;
+pub mod mail;
+pub mod media;
+mod ocr;
+mod open_document;
+mod pdf;
+mod pptx;
+mod text;
+pub mod worker;
+mod xlsx;
+mod zipped_xml;
+
+pub static REGISTRY: Lazy<Registry> = Lazy::new(Registry::from_config);
+
+/// Extractions running on threads of their own, counting those that ran
+/// over time and are still going.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Without a worker process an extractor that hangs can't be stopped, so
+/// past this many still running nothing else is extracted in-process.
+const MAX_RUNNING: usize = 4;
+
+/// Pulls the searchable text out of a file.
+pub trait ContentExtractor: Send + Sync {
+    fn name(&self) -> &str;
+    fn extract(&self, path: &Path) -> Option<String>;
+
+    /// Like `extract`, for formats that can tell which words matter most.
+    fn extract_document(&self, path: &Path) -> Option<Document> {
+        self.extract(path).map(Document::from)
+    }
+}
+
+/// The text of a file and the words in it that say the most about it, such
+/// as a page's title or the names a source file defines. `emphasized` is
+/// also part of `text`, it is kept apart so it can be ranked higher.
+#[derive(Default, Debug, PartialEq)]
+pub struct Document {
+    pub text: String,
+    pub emphasized: String,
+}
+
+impl From<String> for Document {
+    fn from(text: String) -> Self {
+        Document {
+            text,
+            emphasized: String::new(),
+        }
+    }
+}
+
+struct Entry {
+    extractor: Box<dyn ContentExtractor>,
+    limits: ExtractorLimits,
+}
+
+pub struct Registry {
+    extractors: Vec<Entry>,
+    by_extension: HashMap<String, usize>,
+    by_mime_type: HashMap<String, usize>,
+}
+
+impl Registry {
+    fn from_config() -> Self {
+        let mut registry = Registry {
+            extractors: vec![],
+            by_extension: HashMap::new(),
+            by_mime_type: HashMap::new(),
+        };
+
+        registry.register(Box::new(pdf::Pdf), &["pdf"], &["application/pdf"]);
+        registry.register(
+            Box::new(docx::Docx),
+            &["docx"],
+            &[
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+                "application/word",
+            ],
+        );
+        registry.register(
+            Box::new(open_document::OpenDocument),
+            &["odt", "ods", "odp", "ott", "ots", "otp"],
+            &[
+                "application/vnd.oasis.opendocument.text",
+                "application/vnd.oasis.opendocument.spreadsheet",
+                "application/vnd.oasis.opendocument.presentation",
+            ],
+        );
+        registry.register(Box::new(epub::Epub), &["epub"], &["application/epub+zip"]);
+        registry.register(
+            Box::new(xlsx::Xlsx),
+            &["xlsx", "xlsm"],
+            &["application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"],
+        );
+        registry.register(
+            Box::new(pptx::Pptx),
+            &["pptx"],
+            &["application/vnd.openxmlformats-officedocument.presentationml.presentation"],
+        );
+        registry.register(Box::new(csv::Csv), &["csv", "tsv"], &["text/csv"]);
+        registry.register(
+            Box::new(html::Html),
+            &["html", "htm", "xhtml", "shtml", "md", "markdown"],
+            &["text/html"],
+        );
+        registry.register(Box::new(text::PlainText), &["txt"], &["text/plain"]);
+        registry.register(Box::new(mail::Mail), &["eml"], &["message/rfc822"]);
+
+        let code_extensions = match CONF.extractors.source_code {
+            true => code::extensions(),
+            false => vec![],
+        };
+        registry.register(Box::new(code::Code), &code_extensions, &[]);
+
+        let media_extensions = match CONF.extractors.media {
+            true => media::extensions(),
+            false => vec![],
+        };
+        registry.register(Box::new(media::Media), &media_extensions, &[]);
+
+        // Registered after the PDF and media extractors so it takes over their
+        // extensions, keeping their text alongside what it reads.
+        if CONF.ocr.use_tesseract {
+            let limits = limits_for("ocr");
+            let mut extensions = ocr::IMAGE_EXTENSIONS.to_vec();
+            extensions.push("pdf");
+
+            registry.register(
+                Box::new(ocr::Ocr::new(&limits)),
+                &extensions,
+                &["application/pdf", "image/png", "image/jpeg", "image/tiff"],
+            );
+        }
+
+        for command in &CONF.extractors.commands {
+            let extensions = command
+                .extensions
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<&str>>();
+            let mime_types = command
+                .mime_types
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<&str>>();
+
+            let limits = limits_for(&command.name);
+            registry.register(
+                Box::new(command::Command::new(command, &limits)),
+                &extensions,
+                &mime_types,
+            );
+        }
+
+        for (extension, name) in &CONF.extractors.extensions {
+            registry.map_extension(extension, name);
+        }
+
+        for (mime_type, name) in &CONF.extractors.mime_types {
+            registry.map_mime_type(mime_type, name);
+        }
+
+        registry
+    }
+
+    fn register(
+        &mut self,
+        extractor: Box<dyn ContentExtractor>,
+        extensions: &[&str],
+        mime_types: &[&str],
+    ) {
+        let limits = limits_for(extractor.name());
+
+        self.extractors.push(Entry { extractor, limits });
+        let index = self.extractors.len() - 1;
+
+        for extension in extensions {
+            self.by_extension
+                .insert(extension.to_ascii_lowercase(), index);
+        }
+
+        for mime_type in mime_types {
+            self.by_mime_type.insert(mime_type.to_string(), index);
+        }
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.extractors
+            .iter()
+            .position(|e| e.extractor.name() == name)
+    }
+
+    fn map_extension(&mut self, extension: &str, name: &str) {
+        match self.index_of(name) {
+            Some(index) => {
+                self.by_extension
+                    .insert(extension.to_ascii_lowercase(), index);
+            }
+            None => eprintln!("Unknown extractor \"{}\" for .{} files", name, extension),
+        }
+    }
+
+    fn map_mime_type(&mut self, mime_type: &str, name: &str) {
+        match self.index_of(name) {
+            Some(index) => {
+                self.by_mime_type.insert(mime_type.to_string(), index);
+            }
+            None => eprintln!("Unknown extractor \"{}\" for {}", name, mime_type),
+        }
+    }
+
+    /// Looks the file up by extension. Only files without an extension are
+    /// sniffed for their MIME type so that crawling stays cheap.
+    fn find(&self, path: &Path) -> Option<&Entry> {
+        // Maildir file names end in flags like ",U=12:2,S" rather than an
+        // extension.
+        if CONF.mail.index_mail && mail::is_maildir_message(path) {
+            return self
+                .extractors
+                .get(*self.by_mime_type.get("message/rfc822")?);
+        }
+
+        let index = match path.extension() {
+            Some(ext) => *self.by_extension.get(&ext.to_str()?.to_ascii_lowercase())?,
+            None => {
+                let mime_type = infer::get_from_path(path).ok()??.mime_type();
+                *self.by_mime_type.get(mime_type)?
+            }
+        };
+
+        self.extractors.get(index)
+    }
+
+    pub fn can_extract(&self, path: &Path) -> bool {
+        self.find(path).is_some()
+    }
+
+    pub fn extractor_name(&self, path: &Path) -> Option<&str> {
+        Some(self.find(path)?.extractor.name())
+    }
+
+    /// Extracts the text of `path` within the extractor's size and time
+    /// limits, in a worker process if the indexer has started one.
+    pub fn extract(&'static self, path: &Path) -> Option<Document> {
+        let entry = self.find(path)?;
+
+        let size = path.metadata().ok()?.len();
+        if size as f32 > entry.limits.max_size_MiB * 1024. * 1024. {
+            worker::skip(
+                path,
+                &format!("Larger than {} MiB", entry.limits.max_size_MiB),
+            );
+            return None;
+        }
+
+        let timeout = Duration::from_secs_f32(entry.limits.timeout_secs);
+        if worker::is_enabled() {
+            return worker::extract(path, timeout);
+        }
+
+        if RUNNING.fetch_add(1, Ordering::SeqCst) >= MAX_RUNNING {
+            RUNNING.fetch_sub(1, Ordering::SeqCst);
+            worker::skip(path, "Too many extractions running over time");
+            return None;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let owned_path = PathBuf::from(path);
+
+        // The extractor can't be interrupted, if it runs over time it is
+        // left to finish in the background and its result is dropped.
+        std::thread::spawn(move || {
+            let _running = Running;
+            let _ = sender.send(entry.extractor.extract_document(&owned_path));
+        });
+
+        match receiver.recv_timeout(timeout) {
+            Ok(document) => document,
+            Err(RecvTimeoutError::Timeout) => {
+                let reason = format!("Timed out after {}s", timeout.as_secs_f32());
+                worker::skip(path, &reason);
+                None
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                worker::skip(path, "Extractor panicked");
+                None
+            }
+        }
+    }
+
+    /// For worker processes, whose limits are enforced from outside.
+    fn extract_unlimited(&self, path: &Path) -> Option<Document> {
+        self.find(path)?.extractor.extract_document(path)
+    }
+}
+
+/// Counts an extraction thread out of `RUNNING` when it finishes, even by
+/// panicking.
+struct Running;
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Size and time limits for an extractor, or for listing archives with
+/// "archive".
+pub fn limits_for(name: &str) -> ExtractorLimits {
+    match CONF.extractors.limits.get(name) {
+        Some(limits) => limits.clone(),
+        // Only the headers of photos and videos are read.
+        None if name == "media" => ExtractorLimits {
+            max_size_MiB: f32::MAX,
+            ..Default::default()
+        },
+        None if name == "ocr" => ExtractorLimits {
+            timeout_secs: 120.,
+            ..Default::default()
+        },
+        None => ExtractorLimits::default(),
+    }
+}
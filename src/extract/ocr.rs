@@ -0,0 +1,245 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command as Process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{
+    config::{ExtractorLimits, CONF},
+    content_hash,
+};
+
+use super::{
+    command::{max_output, run},
+    media, pdf, ContentExtractor,
+};
+
+/// Scanned PDFs and images, read with `tesseract`. Whatever else the file
+/// has, the text layer of a PDF or the metadata of a photo, is kept too.
+pub struct Ocr {
+    timeout: Duration,
+    max_output: usize,
+}
+
+impl Ocr {
+    pub fn new(limits: &ExtractorLimits) -> Self {
+        Ocr {
+            timeout: Duration::from_secs_f32(limits.timeout_secs),
+            max_output: max_output(limits),
+        }
+    }
+}
+
+pub const IMAGE_EXTENSIONS: [&str; 9] = [
+    "png", "jpg", "jpeg", "tif", "tiff", "bmp", "webp", "gif", "pnm",
+];
+
+/// PDFs whose text layer has fewer words than this per page are taken to
+/// be scanned.
+const MIN_WORDS_PER_PAGE: usize = 10;
+
+/// Rendering resolution for scanned pages, tesseract does best at 300 DPI.
+const PAGE_DPI: &str = "300";
+
+/// Timed out OCR is left running in the background, so each scan renders
+/// its pages into a directory of its own.
+static SCANS: AtomicUsize = AtomicUsize::new(0);
+
+static TESSERACT_INSTALLED: Lazy<bool> = Lazy::new(|| {
+    let installed = run(
+        Process::new("tesseract").arg("--version"),
+        Duration::from_secs(5),
+        64 * 1024,
+    )
+    .is_some();
+
+    if !installed {
+        eprintln!("OCR is on but tesseract isn't installed.");
+    }
+    installed
+});
+
+impl ContentExtractor for Ocr {
+    fn name(&self) -> &str {
+        "ocr"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        let deadline = Instant::now() + self.timeout;
+
+        let text = if is_pdf(path) {
+            let (text, pages) = pdf::text_and_pages(path).unwrap_or_default();
+            if !looks_scanned(&text, pages) {
+                return Some(text);
+            }
+            text
+        } else if CONF.extractors.media {
+            media::Media.extract(path).unwrap_or_default()
+        } else {
+            String::new()
+        };
+
+        match cached(path, |scan| self.recognize(scan, deadline)) {
+            Some(recognized) => Some(format!("{}\n{}", text, recognized)),
+            None if text.is_empty() => None,
+            None => Some(text),
+        }
+    }
+}
+
+impl Ocr {
+    fn recognize(&self, path: &Path, deadline: Instant) -> Option<String> {
+        if !*TESSERACT_INSTALLED {
+            return None;
+        }
+
+        if !is_pdf(path) {
+            return tesseract(path, deadline, self.max_output);
+        }
+
+        let pages = std::env::temp_dir().join(format!(
+            "glimpse-ocr-{}-{}",
+            std::process::id(),
+            SCANS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let text = render_pages(path, &pages, deadline, self.max_output).map(|images| {
+            let mut text = String::new();
+
+            // Pages left when time runs out are skipped, the ones read are
+            // still worth keeping.
+            for image in images {
+                match tesseract(&image, deadline, self.max_output) {
+                    Some(page) => text.push_str(&page),
+                    None => break,
+                }
+            }
+
+            text
+        });
+
+        let _ = std::fs::remove_dir_all(&pages);
+        text
+    }
+}
+
+fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+fn looks_scanned(text: &str, pages: u32) -> bool {
+    let words = text
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphabetic))
+        .count();
+
+    words < MIN_WORDS_PER_PAGE * pages.max(1) as usize
+}
+
+/// Renders the first pages of a PDF into `dir` as images, in page order.
+fn render_pages(
+    path: &Path,
+    dir: &Path,
+    deadline: Instant,
+    max_output: usize,
+) -> Option<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir).ok()?;
+
+    run(
+        Process::new("pdftoppm")
+            .args(["-r", PAGE_DPI, "-gray", "-png", "-l"])
+            .arg(CONF.ocr.max_pages.to_string())
+            .arg(path)
+            .arg(dir.join("page")),
+        deadline.checked_duration_since(Instant::now())?,
+        max_output,
+    )?;
+
+    // Page numbers are zero padded so these sort in order.
+    let mut pages = std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect::<Vec<PathBuf>>();
+    pages.sort();
+
+    Some(pages)
+}
+
+fn tesseract(image: &Path, deadline: Instant, max_output: usize) -> Option<String> {
+    let output = run(
+        Process::new("tesseract")
+            .arg(image)
+            .arg("stdout")
+            .args(["-l", &CONF.ocr.tesseract_languages]),
+        deadline.checked_duration_since(Instant::now())?,
+        max_output,
+    )?;
+
+    Some(String::from_utf8_lossy(&output).into_owned())
+}
+
+/// Results are kept by content hash and languages, so a file is only read
+/// once however often it's reindexed, moved or copied.
+fn cached(path: &Path, recognize: impl FnOnce(&Path) -> Option<String>) -> Option<String> {
+    let hash = content_hash::of_file(path)?;
+    let cache_path = cache_dir().join(cache_name(&hash, &CONF.ocr.tesseract_languages));
+
+    if let Ok(text) = std::fs::read_to_string(&cache_path) {
+        return Some(text);
+    }
+
+    let text = recognize(path)?;
+
+    // Written then renamed so a half written result is never read back.
+    let temp_path = cache_path.with_extension("tmp");
+    if std::fs::create_dir_all(cache_dir()).is_ok() && std::fs::write(&temp_path, &text).is_ok() {
+        let _ = std::fs::rename(&temp_path, &cache_path);
+    }
+
+    Some(text)
+}
+
+fn cache_dir() -> PathBuf {
+    PathBuf::from(&CONF.indexing.location).join("ocr_cache")
+}
+
+fn cache_name(hash: &str, languages: &str) -> String {
+    format!("{}-{}", hash, languages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scanned() {
+        assert!(looks_scanned("", 1));
+        assert!(looks_scanned("Page 1 Page 2 Page 3", 3));
+        assert!(!looks_scanned(
+            &"The tenant agrees to pay the rent monthly. ".repeat(3),
+            2
+        ));
+        assert!(looks_scanned("1 2 3 4 5 6 7 8 9 10 11 12", 1));
+        assert_eq!(cache_name("abc", "eng+deu"), "abc-eng+deu");
+    }
+}
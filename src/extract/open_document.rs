@@ -0,0 +1,88 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use super::{
+    zipped_xml::{self, Markup},
+    ContentExtractor,
+};
+
+/// Text documents, spreadsheets and presentations from LibreOffice and other
+/// OpenDocument editors. All of them keep their contents in "content.xml".
+pub struct OpenDocument;
+
+const MARKUP: Markup = Markup {
+    breaks: &[
+        "p",
+        "h",
+        "s",
+        "tab",
+        "line-break",
+        "table-cell",
+        "list-item",
+    ],
+    // Deleted text kept around for change tracking.
+    skip: &["tracked-changes"],
+};
+
+impl ContentExtractor for OpenDocument {
+    fn name(&self) -> &str {
+        "opendocument"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        let mut archive = zipped_xml::open(path)?;
+        let content = zipped_xml::read_member(&mut archive, "content.xml")?;
+
+        Some(zipped_xml::xml_to_text(&content, &MARKUP))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_document() {
+        let path =
+            &std::env::temp_dir().join(format!("glimpse-document-{}.odt", std::process::id()));
+
+        zipped_xml::tests::write_archive(
+            path,
+            &[
+                ("mimetype", "application/vnd.oasis.opendocument.text"),
+                (
+                    "content.xml",
+                    r#"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0">
+<office:body><office:text>
+<text:tracked-changes><text:p>removed</text:p></text:tracked-changes>
+<text:h>Quarterly</text:h><text:p>Budget<text:s/>report</text:p>
+</office:text></office:body></office:document-content>"#,
+                ),
+            ],
+        );
+
+        let text = OpenDocument.extract(path);
+        std::fs::remove_file(path).unwrap();
+
+        let text = text.unwrap();
+        assert!(text.contains("Quarterly\n"));
+        assert!(text.contains("Budget\nreport"));
+        assert!(!text.contains("removed"));
+    }
+}
@@ -0,0 +1,55 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use lopdf::Document;
+
+use super::ContentExtractor;
+
+pub struct Pdf;
+
+impl ContentExtractor for Pdf {
+    fn name(&self) -> &str {
+        "pdf"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        text_and_pages(path).map(|(text, _)| text)
+    }
+}
+
+/// The text layer of the first pages and how many pages it came from.
+pub fn text_and_pages(path: &Path) -> Option<(String, u32)> {
+    let doc = Document::load(path).ok()?;
+    let pages = doc.get_pages();
+
+    let mut pages = pages.len() as u32;
+
+    if pages == 0 {
+        return None;
+    }
+
+    const MAX_PAGES: u32 = 70;
+    if pages > MAX_PAGES {
+        pages = MAX_PAGES;
+    }
+
+    let range = (1..=pages).collect::<Vec<u32>>();
+    let text = doc.extract_text(&range).ok()?;
+
+    Some((text, pages))
+}
@@ -0,0 +1,86 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use super::{
+    zipped_xml::{self, Markup},
+    ContentExtractor,
+};
+
+/// Slide text followed by the speaker notes.
+pub struct Pptx;
+
+const MARKUP: Markup = Markup {
+    breaks: &["p", "tc"],
+    skip: &[],
+};
+
+impl ContentExtractor for Pptx {
+    fn name(&self) -> &str {
+        "pptx"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        let mut archive = zipped_xml::open(path)?;
+
+        let mut members = zipped_xml::numbered_members(&archive, "ppt/slides/slide");
+        members.extend(zipped_xml::numbered_members(
+            &archive,
+            "ppt/notesSlides/notesSlide",
+        ));
+
+        let mut text = String::new();
+        for member in members {
+            if let Some(xml) = zipped_xml::read_member(&mut archive, &member) {
+                text.push_str(&zipped_xml::xml_to_text(&xml, &MARKUP));
+            }
+        }
+
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slide(text: &str) -> String {
+        format!(
+            r#"<p:sld xmlns:p="p" xmlns:a="a"><p:cSld><p:spTree><p:sp><p:txBody><a:p><a:r><a:t>{}</a:t></a:r></a:p></p:txBody></p:sp></p:spTree></p:cSld></p:sld>"#,
+            text
+        )
+    }
+
+    #[test]
+    fn slides_and_notes() {
+        let path = &std::env::temp_dir().join(format!("glimpse-deck-{}.pptx", std::process::id()));
+
+        zipped_xml::tests::write_archive(
+            path,
+            &[
+                ("ppt/slides/slide10.xml", &slide("Roadmap")),
+                ("ppt/slides/slide2.xml", &slide("Agenda")),
+                ("ppt/notesSlides/notesSlide2.xml", &slide("Mention hiring")),
+            ],
+        );
+
+        let text = Pptx.extract(path);
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(text.unwrap(), "Agenda\nRoadmap\nMention hiring\n");
+    }
+}
@@ -0,0 +1,31 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use super::ContentExtractor;
+
+pub struct PlainText;
+
+impl ContentExtractor for PlainText {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        std::fs::read_to_string(path).ok()
+    }
+}
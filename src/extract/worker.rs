@@ -0,0 +1,405 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, Read, Write},
+    os::{
+        fd::FromRawFd,
+        unix::{
+            ffi::{OsStrExt, OsStringExt},
+            process::CommandExt,
+        },
+    },
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use once_cell::sync::OnceCell;
+
+use crate::config::CONF;
+
+use super::{limits_for, mail, Document, REGISTRY};
+
+/// Set by the indexer so extraction runs in a worker process. A document
+/// that panics, hangs or runs out of memory then takes down the worker,
+/// which is restarted, rather than the whole reindex.
+static WORKER_COMMAND: OnceCell<PathBuf> = OnceCell::new();
+
+static WORKER: Mutex<Option<Worker>> = Mutex::new(None);
+
+/// Set by the indexer, which keeps the list of skipped files whether or not
+/// it uses a worker.
+static RECORD_SKIPPED: AtomicBool = AtomicBool::new(false);
+
+/// Workers answer on this descriptor. Their stdout goes to stderr so
+/// nothing an extractor prints can be mistaken for an answer.
+const RESPONSE_FD: i32 = 3;
+
+/// Extra time given to workers so extractors that keep to a time budget of
+/// their own, like OCR, can still return what they have.
+const GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+const EXTRACT: u8 = 0;
+const READ_MBOX: u8 = 1;
+
+const NO_TEXT: u8 = 0;
+const TEXT: u8 = 1;
+const PANICKED: u8 = 2;
+
+/// An mbox is answered with a `Text` for each message, then `Text(None)`.
+enum Response {
+    Text(Option<Document>),
+    Panicked,
+}
+
+struct Worker {
+    child: Child,
+    requests: ChildStdin,
+    responses: mpsc::Receiver<Response>,
+}
+
+impl Worker {
+    fn spawn(command: &Path) -> Option<Worker> {
+        // ulimit takes KiB.
+        let memory_limit = CONF.extractors.max_memory_MiB as u64 * 1024;
+
+        // In a process group of its own so commands the extractors run can
+        // be killed along with it.
+        let mut child = Command::new("sh")
+            .process_group(0)
+            .arg("-c")
+            .arg(format!(
+                "ulimit -v {} && exec \"$0\" --extract-worker {}>&1 1>&2",
+                memory_limit, RESPONSE_FD
+            ))
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+
+        let requests = child.stdin.take()?;
+        let mut stdout = BufReader::new(child.stdout.take()?);
+
+        let (sender, responses) = mpsc::channel();
+        std::thread::spawn(move || {
+            while let Some(response) = read_response(&mut stdout) {
+                if sender.send(response).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Some(Worker {
+            child,
+            requests,
+            responses,
+        })
+    }
+
+    fn kill(mut self) {
+        // The shell execs the worker, so its pid is the group's id.
+        let killed_group = Command::new("kill")
+            .arg("-KILL")
+            .arg("--")
+            .arg(format!("-{}", self.child.id()))
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+
+        if !killed_group {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
+
+pub fn enable(command: PathBuf) {
+    let _ = WORKER_COMMAND.set(command);
+}
+
+pub fn is_enabled() -> bool {
+    WORKER_COMMAND.get().is_some()
+}
+
+/// The process group of the running worker, which the commands its
+/// extractors run are in too.
+pub fn process_group() -> Option<u32> {
+    Some(WORKER.try_lock().ok()?.as_ref()?.child.id())
+}
+
+pub fn record_skipped() {
+    RECORD_SKIPPED.store(true, Ordering::Relaxed);
+}
+
+/// Extracts `path` in the worker, starting one if there isn't one running.
+/// Files that make it fail or run over `timeout` are added to the skipped
+/// list.
+pub fn extract(path: &Path, timeout: Duration) -> Option<Document> {
+    let mut worker = WORKER.lock().unwrap();
+    let running = start(&mut worker)?;
+
+    let response = match write_request(&mut running.requests, EXTRACT, path) {
+        Ok(()) => running.responses.recv_timeout(timeout + GRACE_PERIOD),
+        Err(_) => Err(RecvTimeoutError::Disconnected),
+    };
+
+    let reason = match response {
+        Ok(Response::Text(document)) => return document,
+        failed => failure(failed, timeout),
+    };
+
+    fail(&mut worker, path, &reason);
+    None
+}
+
+/// Parses the messages of an mbox in the worker, passing each to `each` as
+/// it comes back. Each message gets the mail extractor's time limit. Without
+/// a worker the mbox is parsed here.
+pub fn read_mbox(path: &Path, each: &mut dyn FnMut(Document)) {
+    if !is_enabled() {
+        mail::read_mbox(path, each);
+        return;
+    }
+
+    let timeout = Duration::from_secs_f32(limits_for("mail").timeout_secs);
+
+    let mut worker = WORKER.lock().unwrap();
+    let running = match start(&mut worker) {
+        Some(running) => running,
+        None => return,
+    };
+
+    let sent = write_request(&mut running.requests, READ_MBOX, path).is_ok();
+
+    let reason = loop {
+        let response = match sent {
+            true => running.responses.recv_timeout(timeout + GRACE_PERIOD),
+            false => Err(RecvTimeoutError::Disconnected),
+        };
+
+        match response {
+            Ok(Response::Text(Some(document))) => each(document),
+            Ok(Response::Text(None)) => return,
+            failed => break failure(failed, timeout),
+        }
+    };
+
+    fail(&mut worker, path, &reason);
+}
+
+/// The running worker, starting one if there isn't one.
+fn start(worker: &mut Option<Worker>) -> Option<&mut Worker> {
+    if worker.is_none() {
+        *worker = Worker::spawn(WORKER_COMMAND.get()?);
+        if worker.is_none() {
+            eprintln!("Failed to start extraction worker.");
+        }
+    }
+
+    worker.as_mut()
+}
+
+fn failure(response: Result<Response, RecvTimeoutError>, timeout: Duration) -> String {
+    match response {
+        Ok(Response::Panicked) => String::from("Extractor panicked"),
+        Ok(Response::Text(_)) => String::from("Unexpected answer from extractor"),
+        Err(RecvTimeoutError::Timeout) => {
+            format!("Timed out after {}s", timeout.as_secs_f32())
+        }
+        Err(RecvTimeoutError::Disconnected) => {
+            String::from("Extractor crashed, possibly from running out of memory")
+        }
+    }
+}
+
+/// Stops a worker that failed on `path` so the next request gets a new one.
+fn fail(worker: &mut Option<Worker>, path: &Path, reason: &str) {
+    if let Some(worker) = worker.take() {
+        worker.kill();
+    }
+
+    skip(path, reason);
+}
+
+/// Runs as the worker, answering each request sent on stdin until it closes.
+pub fn serve() {
+    // The descriptor is set up by the shell that starts the worker.
+    let mut responses = unsafe { File::from_raw_fd(RESPONSE_FD) };
+    let mut requests = BufReader::new(std::io::stdin().lock());
+
+    while let Some((kind, path)) = read_request(&mut requests) {
+        let served = std::panic::catch_unwind(AssertUnwindSafe(|| match kind {
+            READ_MBOX => {
+                let mut written = Ok(());
+                mail::read_mbox(&path, &mut |document| {
+                    if written.is_ok() {
+                        let message = Response::Text(Some(document));
+                        written = write_response(&mut responses, &message);
+                    }
+                });
+                written.map(|()| Response::Text(None))
+            }
+            _ => Ok(Response::Text(REGISTRY.extract_unlimited(&path))),
+        }));
+
+        let response = match served {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => return,
+            Err(_) => Response::Panicked,
+        };
+
+        if write_response(&mut responses, &response).is_err() {
+            return;
+        }
+    }
+}
+
+fn write_request(out: &mut impl Write, kind: u8, path: &Path) -> std::io::Result<()> {
+    out.write_all(&[kind])?;
+    write_bytes(out, path.as_os_str().as_bytes())?;
+    out.flush()
+}
+
+fn read_request(input: &mut impl Read) -> Option<(u8, PathBuf)> {
+    let mut kind = [0];
+    input.read_exact(&mut kind).ok()?;
+
+    let bytes = read_bytes(input)?;
+    Some((kind[0], PathBuf::from(std::ffi::OsString::from_vec(bytes))))
+}
+
+fn write_response(out: &mut impl Write, response: &Response) -> std::io::Result<()> {
+    match response {
+        Response::Text(Some(document)) => {
+            out.write_all(&[TEXT])?;
+            write_bytes(out, document.text.as_bytes())?;
+            write_bytes(out, document.emphasized.as_bytes())?;
+        }
+        Response::Text(None) => out.write_all(&[NO_TEXT])?,
+        Response::Panicked => out.write_all(&[PANICKED])?,
+    }
+    out.flush()
+}
+
+fn read_response(input: &mut impl Read) -> Option<Response> {
+    let mut tag = [0];
+    input.read_exact(&mut tag).ok()?;
+
+    match tag[0] {
+        TEXT => {
+            let text = String::from_utf8(read_bytes(input)?).ok()?;
+            let emphasized = String::from_utf8(read_bytes(input)?).ok()?;
+            Some(Response::Text(Some(Document { text, emphasized })))
+        }
+        NO_TEXT => Some(Response::Text(None)),
+        PANICKED => Some(Response::Panicked),
+        _ => None,
+    }
+}
+
+fn write_bytes(out: &mut impl Write, bytes: &[u8]) -> std::io::Result<()> {
+    out.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    out.write_all(bytes)
+}
+
+fn read_bytes(input: &mut impl Read) -> Option<Vec<u8>> {
+    let mut len = [0; 8];
+    input.read_exact(&mut len).ok()?;
+
+    let mut bytes = vec![0; u64::from_le_bytes(len) as usize];
+    input.read_exact(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+fn skipped_path() -> PathBuf {
+    PathBuf::from(&CONF.indexing.location).join("skipped")
+}
+
+/// Files the indexer couldn't read and why, as (path, reason).
+pub fn skipped() -> Vec<(String, String)> {
+    std::fs::read_to_string(skipped_path())
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (reason, path) = line.split_once('\t')?;
+            Some((path.to_string(), reason.to_string()))
+        })
+        .collect()
+}
+
+pub fn clear_skipped() {
+    let _ = std::fs::remove_file(skipped_path());
+}
+
+/// Adds `path` to the skipped list. Only the indexer keeps one, elsewhere
+/// this does nothing.
+pub fn skip(path: &Path, reason: &str) {
+    if !RECORD_SKIPPED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(skipped_path());
+
+    if let (Ok(mut file), Some(path)) = (file, path.to_str()) {
+        let _ = writeln!(file, "{}\t{}", reason, path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protocol() {
+        let mut buffer = vec![];
+        write_request(&mut buffer, READ_MBOX, Path::new("/tmp/odd\nname")).unwrap();
+        let document = Document {
+            text: "Invoice\nTotal".to_string(),
+            emphasized: "Invoice".to_string(),
+        };
+        write_response(&mut buffer, &Response::Text(Some(document))).unwrap();
+        write_response(&mut buffer, &Response::Panicked).unwrap();
+
+        let mut input = buffer.as_slice();
+        assert_eq!(
+            read_request(&mut input),
+            Some((READ_MBOX, PathBuf::from("/tmp/odd\nname")))
+        );
+        assert!(matches!(
+            read_response(&mut input),
+            Some(Response::Text(Some(Document { text, emphasized })))
+                if text == "Invoice\nTotal" && emphasized == "Invoice"
+        ));
+        assert!(matches!(
+            read_response(&mut input),
+            Some(Response::Panicked)
+        ));
+        assert!(read_response(&mut input).is_none());
+    }
+}
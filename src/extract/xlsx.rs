@@ -0,0 +1,184 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::Path;
+
+use xml::{reader::XmlEvent, EventReader};
+
+use crate::tfidf::is_numeric;
+
+use super::{
+    zipped_xml::{self, Archive},
+    ContentExtractor,
+};
+
+/// Sheet names and the text in cells. Cells holding numbers, dates or
+/// booleans are left out.
+pub struct Xlsx;
+
+impl ContentExtractor for Xlsx {
+    fn name(&self) -> &str {
+        "xlsx"
+    }
+
+    fn extract(&self, path: &Path) -> Option<String> {
+        let mut archive = zipped_xml::open(path)?;
+
+        let mut text = String::new();
+
+        if let Some(workbook) = zipped_xml::read_member(&mut archive, "xl/workbook.xml") {
+            for sheet in zipped_xml::start_elements(&workbook, "sheet") {
+                if let Some(name) = zipped_xml::attribute(&sheet, "name") {
+                    text.push_str(name);
+                    text.push('\n');
+                }
+            }
+        }
+
+        let shared_strings = shared_strings(&mut archive);
+
+        for sheet in zipped_xml::numbered_members(&archive, "xl/worksheets/sheet") {
+            if let Some(xml) = zipped_xml::read_member(&mut archive, &sheet) {
+                for cell in cells(&xml, &shared_strings) {
+                    if !is_numeric(&cell) {
+                        text.push_str(&cell);
+                        text.push('\n');
+                    }
+                }
+            }
+        }
+
+        Some(text)
+    }
+}
+
+/// Text cells refer to an index in this table rather than holding the text.
+fn shared_strings(archive: &mut Archive) -> Vec<String> {
+    let xml = match zipped_xml::read_member(archive, "xl/sharedStrings.xml") {
+        Some(xml) => xml,
+        None => return vec![],
+    };
+
+    let mut strings = vec![];
+    let mut current = String::new();
+    let mut in_text = false;
+
+    for event in EventReader::new(xml.as_bytes())
+        .into_iter()
+        .map_while(Result::ok)
+    {
+        match event {
+            XmlEvent::StartElement { name, .. } if name.local_name == "t" => in_text = true,
+            XmlEvent::EndElement { name } => match name.local_name.as_str() {
+                "t" => in_text = false,
+                "si" => strings.push(std::mem::take(&mut current)),
+                _ => {}
+            },
+            XmlEvent::Characters(chars) | XmlEvent::Whitespace(chars) if in_text => {
+                current.push_str(&chars)
+            }
+            _ => {}
+        }
+    }
+
+    strings
+}
+
+fn cells(xml: &str, shared_strings: &[String]) -> Vec<String> {
+    let mut cells = vec![];
+
+    let mut cell_type = String::new();
+    let mut value = String::new();
+    let mut in_value = false;
+
+    for event in EventReader::new(xml.as_bytes())
+        .into_iter()
+        .map_while(Result::ok)
+    {
+        match &event {
+            XmlEvent::StartElement { name, .. } => match name.local_name.as_str() {
+                "c" => {
+                    cell_type = zipped_xml::attribute(&event, "t")
+                        .unwrap_or_default()
+                        .to_string();
+                    value.clear();
+                }
+                "v" | "t" => in_value = true,
+                _ => {}
+            },
+            XmlEvent::EndElement { name } => match name.local_name.as_str() {
+                "v" | "t" => in_value = false,
+                "c" => {
+                    let text = match cell_type.as_str() {
+                        "s" => value
+                            .trim()
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|i| shared_strings.get(i).cloned()),
+                        "inlineStr" | "str" => Some(value.clone()),
+                        _ => None,
+                    };
+
+                    if let Some(text) = text {
+                        cells.push(text);
+                    }
+                }
+                _ => {}
+            },
+            XmlEvent::Characters(chars) if in_value => value.push_str(chars),
+            _ => {}
+        }
+    }
+
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cell_text() {
+        let path =
+            &std::env::temp_dir().join(format!("glimpse-workbook-{}.xlsx", std::process::id()));
+
+        zipped_xml::tests::write_archive(
+            path,
+            &[
+                (
+                    "xl/workbook.xml",
+                    r#"<workbook><sheets><sheet name="Expenses" sheetId="1"/></sheets></workbook>"#,
+                ),
+                (
+                    "xl/sharedStrings.xml",
+                    r#"<sst><si><t>Travel</t></si><si><r><t>Hotel </t></r><r><t>Berlin</t></r></si><si><t>2024</t></si></sst>"#,
+                ),
+                (
+                    "xl/worksheets/sheet1.xml",
+                    r#"<worksheet><sheetData><row>
+<c t="s"><v>0</v></c><c t="s"><v>1</v></c><c t="s"><v>2</v></c>
+<c><v>1250.5</v></c><c t="inlineStr"><is><t>Flights</t></is></c>
+</row></sheetData></worksheet>"#,
+                ),
+            ],
+        );
+
+        let text = Xlsx.extract(path);
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(text.unwrap(), "Expenses\nTravel\nHotel Berlin\nFlights\n");
+    }
+}
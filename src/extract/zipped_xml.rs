@@ -0,0 +1,179 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs::File, io::Read, path::Path};
+
+use xml::{
+    reader::{ParserConfig, XmlEvent},
+    EventReader,
+};
+use zip::ZipArchive;
+
+/// Members are read no further than this so a small archive can't expand
+/// into something huge.
+const MAX_MEMBER_SIZE: u64 = 64 * 1024 * 1024;
+
+pub type Archive = ZipArchive<File>;
+
+pub fn open(path: &Path) -> Option<Archive> {
+    ZipArchive::new(File::open(path).ok()?).ok()
+}
+
+/// Members like "ppt/slides/slide12.xml" in numeric rather than name order.
+pub fn numbered_members(archive: &Archive, prefix: &str) -> Vec<String> {
+    let mut members = archive
+        .file_names()
+        .filter_map(|name| {
+            let number = name.strip_prefix(prefix)?.strip_suffix(".xml")?;
+            Some((number.parse::<u32>().ok()?, name.to_string()))
+        })
+        .collect::<Vec<(u32, String)>>();
+
+    members.sort();
+    members.into_iter().map(|(_, name)| name).collect()
+}
+
+pub fn read_member(archive: &mut Archive, name: &str) -> Option<String> {
+    let member = archive.by_name(name).ok()?;
+
+    let mut contents = String::new();
+    member
+        .take(MAX_MEMBER_SIZE)
+        .read_to_string(&mut contents)
+        .ok()?;
+
+    Some(contents)
+}
+
+/// Which elements separate words and which should be left out entirely.
+/// Elements are matched on their local name, i.e. "p" matches "text:p".
+pub struct Markup<'a> {
+    pub breaks: &'a [&'a str],
+    pub skip: &'a [&'a str],
+}
+
+/// Drops the markup and keeps the text. Anything readable before an error
+/// in the document is still returned.
+pub fn xml_to_text(xml: &str, markup: &Markup) -> String {
+    let reader = html_entities(ParserConfig::new())
+        .cdata_to_characters(true)
+        .create_reader(xml.as_bytes());
+
+    let mut text = String::new();
+    let mut skipping = 0;
+
+    for event in reader {
+        match event {
+            Ok(XmlEvent::StartElement { name, .. }) => {
+                if skipping > 0 || markup.skip.contains(&name.local_name.as_str()) {
+                    skipping += 1;
+                }
+            }
+            Ok(XmlEvent::EndElement { name }) => {
+                if skipping > 0 {
+                    skipping -= 1;
+                } else if markup.breaks.contains(&name.local_name.as_str()) {
+                    text.push('\n');
+                }
+            }
+            Ok(XmlEvent::Characters(chars)) if skipping == 0 => text.push_str(&chars),
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+
+    text
+}
+
+pub fn attribute<'a>(event: &'a XmlEvent, name: &str) -> Option<&'a str> {
+    match event {
+        XmlEvent::StartElement { attributes, .. } => attributes
+            .iter()
+            .find(|a| a.name.local_name == name)
+            .map(|a| a.value.as_str()),
+        _ => None,
+    }
+}
+
+pub fn start_elements<'a>(
+    xml: &'a str,
+    local_name: &'a str,
+) -> impl Iterator<Item = XmlEvent> + 'a {
+    EventReader::new(xml.as_bytes())
+        .into_iter()
+        .map_while(Result::ok)
+        .filter(move |event| match event {
+            XmlEvent::StartElement { name, .. } => name.local_name == local_name,
+            _ => false,
+        })
+}
+
+/// XHTML often uses entities that XML doesn't define.
+fn html_entities(config: ParserConfig) -> ParserConfig {
+    [
+        ("nbsp", " "),
+        ("shy", ""),
+        ("ndash", "–"),
+        ("mdash", "—"),
+        ("hellip", "…"),
+        ("lsquo", "‘"),
+        ("rsquo", "’"),
+        ("ldquo", "“"),
+        ("rdquo", "”"),
+        ("laquo", "«"),
+        ("raquo", "»"),
+        ("copy", "©"),
+    ]
+    .into_iter()
+    .fold(config, |config, (entity, value)| {
+        config.add_entity(entity, value)
+    })
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::Write;
+
+    use zip::{write::FileOptions, ZipWriter};
+
+    use super::*;
+
+    /// Writes a zip of `members` to `path` for testing extractors.
+    pub fn write_archive(path: &Path, members: &[(&str, &str)]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+
+        for (name, contents) in members {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(contents.as_bytes()).unwrap();
+        }
+
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn markup() {
+        let markup = Markup {
+            breaks: &["p"],
+            skip: &["script"],
+        };
+
+        let text = xml_to_text(
+            "<body><p>one&nbsp;two</p><script>var x;</script><p>three</p></body>",
+            &markup,
+        );
+        assert_eq!(text, "one two\nthree\n");
+    }
+}
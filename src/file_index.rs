@@ -16,20 +16,38 @@
 
 
 use std::{path::Path, sync::Mutex};
-use std::{fs, io::Write, path::PathBuf};
+use std::{
+    fs,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+};
 
 use chrono;
 
 use once_cell::sync::Lazy;
 
+use crate::archive;
 use crate::config::CONF;
+use crate::content_hash;
 use crate::db::hashmap_db::HashMapDB;
+use crate::db::list::DBList;
+use crate::db::string::DBString;
 use crate::db::string_search_db::StringSearchDb;
-use crate::tfidf::{add_document_to_corpus, TfIdfMap};
+use crate::extract::{cache, mail, worker};
+use crate::filters::Metadata;
+use crate::tfidf::{add_document_to_corpus, add_text_to_corpus, TfIdfMap};
+use crate::tokenizer::{file_name_keywords, split_words};
 
 pub const FILE_DB_READ: i32 = 0b1;
 pub const FILE_DB_WRITE: i32 = 0b10;
 
+/// Bump whenever the way things are stored in the index changes. Indexes
+/// with a different version are rebuilt.
+pub const INDEX_VERSION: u32 = 9;
+
+/// Key in `copies` for the list of fingerprints with more than one file.
+const DUPLICATED: &str = "duplicated";
+
 static HELD_LOCKS: Lazy<Mutex<Vec<PathBuf>>> = Lazy::new(|| Mutex::new(vec![]));
 
 pub struct FileIndex {
@@ -39,8 +57,18 @@ pub struct FileIndex {
     // pub tf_idf: HashMap<String, Vec<(PathBuf, f32)>>,
     pub tf_idf: TfIdfMap,
     pub terms: StringSearchDb,
+    /// What to show instead of the file name, e.g. the subject of a mail.
+    pub titles: HashMapDB<DBString, DBString>,
+    /// Content fingerprints by path, when content hashes are on.
+    pub hashes: HashMapDB<DBString, DBString>,
+    /// Paths by content fingerprint.
+    pub copies: HashMapDB<DBString, DBList<DBString>>,
+    /// Size and modification time by path, and whether it's a folder.
+    pub metadata: MetadataMap,
 }
 
+pub type MetadataMap = HashMapDB<DBString, (Metadata, bool)>;
+
 // pub fn lock() -> Result<(), Box<dyn std::error::Error>> {
 //     let mut lock_file = fs::File::create(LOCK_PATH.clone())?;
 //     let time = format!("{}", chrono::Utc::now().timestamp());
@@ -91,6 +119,26 @@ impl FileIndex {
         path.join("terms")
     }
 
+    fn titles_path(path: &Path) -> PathBuf {
+        path.join("titles")
+    }
+
+    fn hashes_path(path: &Path) -> PathBuf {
+        path.join("hashes")
+    }
+
+    fn copies_path(path: &Path) -> PathBuf {
+        path.join("copies")
+    }
+
+    fn metadata_path(path: &Path) -> PathBuf {
+        path.join("metadata")
+    }
+
+    fn recent_path(path: &Path) -> PathBuf {
+        path.join("recent")
+    }
+
     fn lock_path(path: &Path) -> PathBuf {
         path.join("lock")
     }
@@ -99,6 +147,14 @@ impl FileIndex {
         path.join("last_indexed")
     }
 
+    fn index_version_path(path: &Path) -> PathBuf {
+        path.join("index_version")
+    }
+
+    fn analyzer_settings_path(path: &Path) -> PathBuf {
+        path.join("analyzer_settings")
+    }
+
     pub fn open(
         path: &PathBuf,
         _access_flags: i32,
@@ -117,6 +173,10 @@ impl FileIndex {
         let dirs = StringSearchDb::open(Self::dirs_path(path));
         let tf_idf = HashMapDB::open(Self::tf_idf_path(path), 5000);
         let terms = StringSearchDb::open(Self::terms_path(path));
+        let titles = HashMapDB::open(Self::titles_path(path), 1000);
+        let hashes = HashMapDB::open(Self::hashes_path(path), 5000);
+        let copies = HashMapDB::open(Self::copies_path(path), 5000);
+        let metadata = HashMapDB::open(Self::metadata_path(path), 5000);
 
         Ok(FileIndex {
             path: path.clone(),
@@ -124,6 +184,10 @@ impl FileIndex {
             dirs,
             tf_idf,
             terms,
+            titles,
+            hashes,
+            copies,
+            metadata,
         })
     }
 
@@ -138,13 +202,37 @@ impl FileIndex {
         StringSearchDb::reset(Self::dirs_path(path));
         StringSearchDb::reset(Self::tf_idf_path(path));
         StringSearchDb::reset(Self::terms_path(path));
+        StringSearchDb::reset(Self::titles_path(path));
+        StringSearchDb::reset(Self::hashes_path(path));
+        StringSearchDb::reset(Self::copies_path(path));
+        StringSearchDb::reset(Self::metadata_path(path));
+        let _ = fs::remove_file(Self::recent_path(path));
 
         Self::unlock(path);
     }
 
+    /// Flushes all databases to disk.
+    pub fn flush(&mut self) {
+        self.files.flush();
+        self.dirs.flush();
+        self.tf_idf.flush();
+        self.terms.flush();
+        self.titles.flush();
+        self.hashes.flush();
+        self.copies.flush();
+        self.metadata.flush();
+    }
+
     /// Full size of all databases in bytes
     fn db_size(&self) -> usize {
-        self.files.size() + self.dirs.size() + self.tf_idf.size() + self.terms.size()
+        self.files.size()
+            + self.dirs.size()
+            + self.tf_idf.size()
+            + self.terms.size()
+            + self.titles.size()
+            + self.hashes.size()
+            + self.copies.size()
+            + self.metadata.size()
     }
 
     pub fn exceeded_capcaity(&self) -> bool {
@@ -153,24 +241,219 @@ impl FileIndex {
         self.db_size() > (CONF.indexing.size_upper_bound_GiB * GiB as f32) as usize
     }
 
-    pub fn add_file(&mut self, path: &PathBuf) {
+    pub fn add_file(&mut self, path: &Path) {
         if self.exceeded_capcaity() {
             return;
         }
 
+        if CONF.mail.index_mail && mail::is_maildir_message(path) {
+            self.add_mail(path);
+            return;
+        }
+
+        // An mbox is found by its name and its messages by their subjects.
+        let is_mbox = CONF.mail.index_mail && mail::is_mbox(path);
+
         let file_name = path.file_name().unwrap().to_str().unwrap().to_string();
         let file_path = path.to_str().unwrap().to_string();
 
         self.files
             .insert(file_name.clone(), Some(file_path.clone()));
+        self.add_metadata(path, false);
 
-        let keywords = file_name_inner_keywords(&file_name);
+        let keywords = file_name_keywords(&file_name);
         for keyword in keywords {
             self.files.insert(keyword.clone(), Some(file_path.clone()));
         }
 
+        let hash = match CONF.indexing.content_hashes {
+            true => content_hash::fingerprint(path),
+            false => None,
+        };
+
+        if let Some(hash) = &hash {
+            self.add_hash(&file_path, hash);
+        }
+
+        if CONF.search_file_contents && !is_mbox {
+            add_document_to_corpus(self, path, hash.as_deref());
+        }
+
+        if CONF.indexing.archive_members && archive::is_archive(path) {
+            self.add_archive_members(path);
+        }
+
+        if is_mbox {
+            self.add_mbox(path);
+        }
+    }
+
+    /// Mail is found by subject rather than its file name, which is just an
+    /// id.
+    fn add_mail(&mut self, path: &Path) {
+        let message = match fs::read(path) {
+            Ok(raw) => mail::Message::parse(&raw),
+            Err(_) => return,
+        };
+
+        let file_path = path.to_str().unwrap().to_string();
+        self.add_titled(&file_path, &message.subject);
+        self.add_metadata(path, false);
+
         if CONF.search_file_contents {
-            add_document_to_corpus(self, path);
+            add_document_to_corpus(self, path, None);
+        }
+    }
+
+    /// Each message in an mbox is indexed as "path#n", with its subject as
+    /// the emphasized part of its text. They're parsed in the extraction
+    /// worker one at a time.
+    fn add_mbox(&mut self, path: &Path) {
+        let mut index = 0;
+
+        worker::read_mbox(path, &mut |message| {
+            let message_path = match mail::mbox_message_path(path, index) {
+                Some(message_path) => message_path,
+                None => return,
+            };
+            index += 1;
+
+            self.add_titled(&message_path, &message.emphasized);
+
+            if CONF.search_file_contents {
+                cache::store(&message_path, &message.text);
+                add_text_to_corpus(self, &message_path, &message.text, &message.emphasized);
+            }
+        });
+    }
+
+    /// Untitled ones are found by their file name instead.
+    fn add_titled(&mut self, path: &str, title: &str) {
+        let title = match title.trim() {
+            "" => match Path::new(path).file_name().and_then(|name| name.to_str()) {
+                Some(file_name) => file_name,
+                None => return,
+            },
+            title => title,
+        };
+
+        self.files.insert(title.to_string(), Some(path.to_string()));
+        for keyword in split_words(title) {
+            if keyword.chars().count() > 1 {
+                self.files.insert(keyword, Some(path.to_string()));
+            }
+        }
+
+        let key = self.titles.alloc_string(path.to_string());
+        let title = self.titles.alloc_string(title.to_string());
+        self.titles.insert(key, title);
+    }
+
+    pub fn title(&mut self, path: &str) -> Option<String> {
+        let title = self.titles.get(path.to_string())?;
+        Some(self.titles.get_string(&title))
+    }
+
+    fn add_hash(&mut self, path: &str, hash: &str) {
+        if self.hash(path).as_deref() == Some(hash) {
+            return;
+        }
+
+        let key = self.hashes.alloc_string(path.to_string());
+        let value = self.hashes.alloc_string(hash.to_string());
+        self.hashes.insert(key, value);
+
+        let mut copies = match self.copies.get(hash.to_string()) {
+            Some(copies) => copies,
+            None => {
+                let key = self.copies.alloc_string(hash.to_string());
+                let copies = self.copies.new_list();
+                self.copies.insert(key, copies.clone());
+                copies
+            }
+        };
+
+        let path = self.copies.alloc_string(path.to_string());
+        self.copies.push_to_list(&mut copies, path);
+
+        // Kept when a second copy turns up so `duplicates` doesn't have to
+        // go through every fingerprint.
+        if self.copies.get_list(&copies).len() == 2 {
+            let mut duplicated = match self.copies.get(DUPLICATED.to_string()) {
+                Some(duplicated) => duplicated,
+                None => {
+                    let key = self.copies.alloc_string(DUPLICATED.to_string());
+                    let duplicated = self.copies.new_list();
+                    self.copies.insert(key, duplicated.clone());
+                    duplicated
+                }
+            };
+
+            let hash = self.copies.alloc_string(hash.to_string());
+            self.copies.push_to_list(&mut duplicated, hash);
+        }
+    }
+
+    pub fn hash(&mut self, path: &str) -> Option<String> {
+        let hash = self.hashes.get(path.to_string())?;
+        Some(self.hashes.get_string(&hash))
+    }
+
+    /// Every indexed file with the same contents as `path`, itself included.
+    pub fn copies_of(&mut self, path: &str) -> Vec<String> {
+        match self.hash(path) {
+            Some(hash) => self.copies_with(&hash),
+            None => vec![],
+        }
+    }
+
+    fn copies_with(&mut self, hash: &str) -> Vec<String> {
+        let copies = match self.copies.get(hash.to_string()) {
+            Some(copies) => self.copies.get_list(&copies),
+            None => return vec![],
+        };
+
+        copies
+            .iter()
+            .map(|path| self.copies.get_string(path))
+            .collect()
+    }
+
+    /// Every set of files with the same contents.
+    pub fn duplicates(&mut self) -> Vec<Vec<String>> {
+        let hashes = match self.copies.get(DUPLICATED.to_string()) {
+            Some(hashes) => self.copies.get_list(&hashes),
+            None => return vec![],
+        };
+
+        hashes
+            .iter()
+            .map(|hash| {
+                let hash = self.copies.get_string(hash);
+                self.copies_with(&hash)
+            })
+            .collect()
+    }
+
+    /// Indexes the files in an archive by name under their virtual paths.
+    fn add_archive_members(&mut self, path: &Path) {
+        for member in archive::list_members(path) {
+            let virtual_path = match archive::virtual_path(path, &member) {
+                Some(virtual_path) => virtual_path,
+                None => continue,
+            };
+
+            let file_name = match Path::new(&member).file_name().and_then(|n| n.to_str()) {
+                Some(file_name) => file_name.to_string(),
+                None => continue,
+            };
+
+            self.files
+                .insert(file_name.clone(), Some(virtual_path.clone()));
+
+            for keyword in file_name_keywords(&file_name) {
+                self.files.insert(keyword, Some(virtual_path.clone()));
+            }
         }
     }
 
@@ -183,13 +466,75 @@ impl FileIndex {
         let dir_path = path.to_str().unwrap().to_string();
 
         self.dirs.insert(dir_name.clone(), Some(dir_path.clone()));
+        self.add_metadata(path, true);
 
-        let keywords = file_name_inner_keywords(&dir_name.clone());
+        let keywords = file_name_keywords(&dir_name);
         for keyword in keywords {
             self.dirs.insert(keyword.clone(), Some(dir_path.clone()));
         }
     }
 
+    fn add_metadata(&mut self, path: &Path, is_dir: bool) {
+        let (Some(file_path), Ok(metadata)) = (path.to_str(), fs::metadata(path)) else {
+            return;
+        };
+
+        let key = self.metadata.alloc_string(file_path.to_string());
+        self.metadata.insert(key, (Metadata::of(&metadata), is_dir));
+    }
+
+    pub fn metadata(&mut self, path: &str) -> Option<Metadata> {
+        metadata_of(&mut self.metadata, path)
+    }
+
+    /// Lists every indexed file and folder newest first, for going through
+    /// what passes filters when there's nothing else to search for.
+    pub fn write_recent(&mut self) {
+        let mut recent = self
+            .metadata
+            .entries()
+            .into_iter()
+            .map(|(path, (metadata, is_dir))| (self.metadata.get_string(&path), metadata, is_dir))
+            .collect::<Vec<(String, Metadata, bool)>>();
+        recent.sort_by_key(|(_, metadata, _)| std::cmp::Reverse(metadata.modified));
+
+        let Ok(file) = fs::File::create(Self::recent_path(&self.path)) else {
+            return;
+        };
+        let mut file = BufWriter::new(file);
+
+        for (path, metadata, is_dir) in recent {
+            if path.contains('\n') {
+                continue;
+            }
+
+            let _ = writeln!(
+                file,
+                "{}\t{}\t{}\t{}",
+                metadata.modified, metadata.size, is_dir as u8, path
+            );
+        }
+    }
+
+    /// What `write_recent` listed, read as it's gone through.
+    pub fn recent(&self) -> impl Iterator<Item = (String, Metadata, bool)> {
+        let lines = fs::File::open(Self::recent_path(&self.path))
+            .map(|file| BufReader::new(file).lines())
+            .into_iter()
+            .flatten()
+            .map_while(Result::ok);
+
+        lines.filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            let modified = fields.next()?.parse().ok()?;
+            let size = fields.next()?.parse().ok()?;
+            let is_dir = fields.next()? == "1";
+            let path = fields.next()?.to_string();
+
+            Some((path, Metadata { size, modified }, is_dir))
+        })
+    }
+
     fn lock(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
         let mut lock_file = fs::File::create(Self::lock_path(path))?;
         let time = format!("{}", chrono::Utc::now().timestamp());
@@ -235,7 +580,7 @@ impl FileIndex {
     }
 
     /// * `timeout` - in seconds
-    pub fn wait_for_unlock(path: &PathBuf, timeout: u64) {
+    pub fn wait_for_unlock(path: &PathBuf, timeout: u64) -> Result<(), IsLocked> {
         let mut time = 0;
         while Self::is_locked(path) {
             std::thread::sleep(std::time::Duration::from_secs(1));
@@ -243,10 +588,11 @@ impl FileIndex {
             println!("Waiting for database lock for {} seconds...", time);
 
             if time > timeout {
-                eprintln!("Timeout waiting for database lock.");
-                std::process::exit(1);
+                return Err(IsLocked);
             }
         }
+
+        Ok(())
     }
 
     pub fn last_indexed(path: &Path) -> Option<i64> {
@@ -260,6 +606,35 @@ impl FileIndex {
         let _ = fs::write(Self::last_indexed_path(path), time);
     }
 
+    pub fn index_version(path: &Path) -> Option<u32> {
+        fs::read_to_string(Self::index_version_path(path))
+            .ok()
+            .and_then(|s| s.parse::<u32>().ok())
+    }
+
+    pub fn set_index_version(path: &Path) {
+        let _ = fs::write(Self::index_version_path(path), INDEX_VERSION.to_string());
+        let _ = fs::write(
+            Self::analyzer_settings_path(path),
+            Self::analyzer_settings(),
+        );
+    }
+
+    /// Also true when the index was built with other analyzer settings,
+    /// since queries would then be turned into terms it doesn't have.
+    pub fn is_outdated(path: &Path) -> bool {
+        Self::index_version(path) != Some(INDEX_VERSION)
+            || fs::read_to_string(Self::analyzer_settings_path(path)).ok()
+                != Some(Self::analyzer_settings())
+    }
+
+    /// The settings that decide which terms text is indexed under.
+    fn analyzer_settings() -> String {
+        let tokenizer = toml::to_string(&CONF.tokenizer).unwrap_or_default();
+        let analyzer = toml::to_string(&CONF.analyzer).unwrap_or_default();
+        format!("[tokenizer]\n{}\n[analyzer]\n{}", tokenizer, analyzer)
+    }
+
     fn unlock_if_old(path: &PathBuf) -> Option<bool> {
         if let Ok(timestamp) = fs::read_to_string(Self::lock_path(path)) {
             if let Ok(timestamp) = timestamp.parse::<i64>() {
@@ -276,6 +651,12 @@ impl FileIndex {
     }
 }
 
+/// For looking metadata up while the rest of the index is borrowed, with a
+/// clone of `FileIndex::metadata`.
+pub fn metadata_of(map: &mut MetadataMap, path: &str) -> Option<Metadata> {
+    map.get(path.to_string()).map(|(metadata, _)| metadata)
+}
+
 impl Drop for FileIndex {
     fn drop(&mut self) {
         Self::unlock(&self.path);
@@ -302,100 +683,6 @@ impl Drop for FileIndex {
 //     }
 // }
 
-const WORD_BUF_SIZE: usize = 100;
-
 pub fn tokenize_string(str: &str) -> Vec<String> {
-    let mut tokens = vec![];
-
-    let mut word_buf: [char; WORD_BUF_SIZE] = ['\0'; WORD_BUF_SIZE];
-    let mut word_buf_index = 0;
-
-    let mut pre_is_alpha = false;
-
-    for c in str.chars() {
-        handle_char(
-            c,
-            &mut pre_is_alpha,
-            &mut word_buf_index,
-            &mut word_buf,
-            &mut tokens,
-        );
-    }
-
-    if pre_is_alpha {
-        append_word(&word_buf, word_buf_index, &mut tokens);
-    }
-
-    tokens
-}
-
-fn handle_char(
-    c: char,
-    pre_is_alpha: &mut bool,
-    word_buf_index: &mut usize,
-    word_buf: &mut [char; 100],
-    tokens: &mut Vec<String>,
-) {
-    let is_alphanum = c.is_alphanumeric();
-
-    if is_alphanum != *pre_is_alpha || *word_buf_index == WORD_BUF_SIZE {
-        if *pre_is_alpha {
-            append_word(word_buf, *word_buf_index, tokens);
-        }
-
-        *word_buf_index = 0;
-    }
-
-    word_buf[*word_buf_index] = c;
-    *word_buf_index += 1;
-
-    *pre_is_alpha = is_alphanum;
-}
-
-#[inline]
-fn append_word(word_buf: &[char; 100], word_buf_index: usize, tokens: &mut Vec<String>) {
-    let token = word_buf
-        .iter()
-        .take(word_buf_index)
-        .collect::<String>()
-        .to_lowercase();
-
-    tokens.push(token);
-}
-
-fn file_name_inner_keywords(file_name: &str) -> Vec<String> {
-    let mut tokens = vec![];
-
-    let mut file_name = file_name.to_string();
-    // Remove file extension
-    if let Some(index) = file_name.chars().rev().position(|c| c == '.') {
-        file_name = file_name
-            .chars()
-            .take(file_name.len() - index - 1)
-            .collect::<String>();
-    }
-
-    let separator = if file_name.contains('_') {
-        Some('_')
-    } else if file_name.contains(' ') {
-        Some(' ')
-    } else if file_name.contains('-') {
-        Some('-')
-    } else if file_name.contains('.') {
-        Some('.')
-    } else {
-        None
-    };
-
-    if let Some(separator) = separator {
-        for token in file_name.split(separator).skip(1) {
-            tokens.push(token.to_string());
-        }
-    }
-
-    tokens
-        .into_iter()
-        .filter(|s| s.len() > 1 && s.chars().all(|c| c.is_alphanumeric()))
-        .map(|s| s.to_lowercase())
-        .collect()
+    split_words(str)
 }
@@ -0,0 +1,473 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Filters written into a query, like "report ext:pdf modified:<7d".
+#[derive(Debug, Default, PartialEq)]
+pub struct Filters {
+    /// Lowercase, without the dot.
+    pub extensions: Vec<String>,
+    pub kinds: Vec<Kind>,
+    pub within: Vec<PathBuf>,
+    pub modified: Option<Bound<Duration>>,
+    pub size: Option<Bound<u64>>,
+}
+
+/// What modified and size filters go by. Stored in the index so nothing
+/// has to be read from disk while searching.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Metadata {
+    pub size: u64,
+    /// Seconds since the Unix epoch.
+    pub modified: u64,
+}
+
+impl Metadata {
+    pub fn of(metadata: &std::fs::Metadata) -> Metadata {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+
+        Metadata {
+            size: metadata.len(),
+            modified: modified.as_secs(),
+        }
+    }
+
+    fn age(&self) -> Duration {
+        let modified = UNIX_EPOCH + Duration::from_secs(self.modified);
+        SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Dir,
+    File,
+    Image,
+    Audio,
+    Video,
+    Document,
+}
+
+/// "<7d" is `Below(7 days)`, for modified that's within the last week.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound<T> {
+    Below(T),
+    Above(T),
+}
+
+impl<T: PartialOrd> Bound<T> {
+    fn contains(&self, value: T) -> bool {
+        match self {
+            Bound::Below(bound) => value < *bound,
+            Bound::Above(bound) => value > *bound,
+        }
+    }
+}
+
+const IMAGE_EXTENSIONS: [&str; 15] = [
+    "png", "jpg", "jpeg", "gif", "bmp", "webp", "svg", "tif", "tiff", "heic", "heif", "ico", "raw",
+    "cr2", "nef",
+];
+const AUDIO_EXTENSIONS: [&str; 10] = [
+    "mp3", "flac", "ogg", "oga", "opus", "m4a", "wav", "aac", "wma", "aiff",
+];
+const VIDEO_EXTENSIONS: [&str; 10] = [
+    "mp4", "m4v", "mov", "mkv", "webm", "avi", "wmv", "flv", "mpg", "mpeg",
+];
+const DOCUMENT_EXTENSIONS: [&str; 17] = [
+    "pdf", "doc", "docx", "odt", "ods", "odp", "xls", "xlsx", "ppt", "pptx", "rtf", "txt", "md",
+    "epub", "tex", "csv", "html",
+];
+
+impl Kind {
+    fn parse(name: &str) -> Option<Kind> {
+        Some(match name {
+            "dir" | "folder" => Kind::Dir,
+            "file" => Kind::File,
+            "image" | "photo" => Kind::Image,
+            "audio" | "music" => Kind::Audio,
+            "video" => Kind::Video,
+            "document" | "doc" => Kind::Document,
+            _ => return None,
+        })
+    }
+
+    fn matches(&self, extension: &str, is_dir: bool) -> bool {
+        match self {
+            Kind::Dir => is_dir,
+            Kind::File => !is_dir,
+            Kind::Image => !is_dir && IMAGE_EXTENSIONS.contains(&extension),
+            Kind::Audio => !is_dir && AUDIO_EXTENSIONS.contains(&extension),
+            Kind::Video => !is_dir && VIDEO_EXTENSIONS.contains(&extension),
+            Kind::Document => !is_dir && DOCUMENT_EXTENSIONS.contains(&extension),
+        }
+    }
+}
+
+/// Takes the filters out of `query`, giving them and what's left. Anything
+/// that isn't a valid filter, or is in quotes, is left in the query, as is
+/// `-word`, which `query::parse` handles.
+pub fn parse(query: &str) -> (Filters, String) {
+    let mut filters = Filters::default();
+    let mut rest = vec![];
+
+    for token in tokens(query) {
+        if !parse_filter(&token, &mut filters) {
+            rest.push(token);
+        }
+    }
+
+    (filters, rest.join(" "))
+}
+
+/// Splits on whitespace, except inside quotes.
+fn tokens(query: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        if c == '"' {
+            quoted = !quoted;
+        }
+
+        if c.is_whitespace() && !quoted {
+            if !token.is_empty() {
+                tokens.push(std::mem::take(&mut token));
+            }
+        } else {
+            token.push(c);
+        }
+    }
+
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+
+    tokens
+}
+
+fn parse_filter(token: &str, filters: &mut Filters) -> bool {
+    if token.starts_with('"') {
+        return false;
+    }
+
+    let (key, value) = match token.split_once(':') {
+        Some((key, value)) if !value.is_empty() => (key.to_lowercase(), value),
+        _ => return false,
+    };
+
+    // Several values can be given as "ext:jpg|png" or "ext:jpg,png".
+    let values = || value.split(['|', ',']).filter(|v| !v.is_empty());
+
+    match key.as_str() {
+        "ext" => {
+            let extensions = values().map(|ext| ext.trim_start_matches('.').to_lowercase());
+            filters.extensions.extend(extensions);
+        }
+        "type" => {
+            let kinds = values().map(|kind| Kind::parse(&kind.to_lowercase()));
+            match kinds.collect::<Option<Vec<Kind>>>() {
+                Some(kinds) => filters.kinds.extend(kinds),
+                None => return false,
+            }
+        }
+        "in" => filters.within.push(expand_home(value)),
+        "modified" => match parse_bound(value, parse_age) {
+            Some(bound) => filters.modified = Some(bound),
+            None => return false,
+        },
+        "size" => match parse_bound(value, parse_size) {
+            Some(bound) => filters.size = Some(bound),
+            None => return false,
+        },
+        _ => return false,
+    }
+
+    true
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), home::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn parse_bound<T>(value: &str, parse: impl Fn(&str) -> Option<T>) -> Option<Bound<T>> {
+    if let Some(value) = value.strip_prefix('<') {
+        Some(Bound::Below(parse(value.trim_start_matches('='))?))
+    } else if let Some(value) = value.strip_prefix('>') {
+        Some(Bound::Above(parse(value.trim_start_matches('='))?))
+    } else {
+        None
+    }
+}
+
+/// Splits "100M" into 100 and "m".
+fn number_and_unit(value: &str) -> Option<(f64, String)> {
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let number = value[..split].parse::<f64>().ok()?;
+    Some((number, value[split..].to_lowercase()))
+}
+
+/// "30m", "12h", "7d", "2w", "6mo" or "1y".
+fn parse_age(value: &str) -> Option<Duration> {
+    const MINUTE: f64 = 60.;
+    const DAY: f64 = 24. * 60. * MINUTE;
+
+    let (number, unit) = number_and_unit(value)?;
+    let seconds = match unit.as_str() {
+        "m" | "min" => MINUTE,
+        "h" => 60. * MINUTE,
+        "d" | "" => DAY,
+        "w" => 7. * DAY,
+        "mo" => 30. * DAY,
+        "y" => 365. * DAY,
+        _ => return None,
+    };
+
+    // Too large for a `Duration` is left in the query like any other typo.
+    Duration::try_from_secs_f64(number * seconds).ok()
+}
+
+/// "512", "100K", "100M" or "1.5G", in powers of 1024.
+fn parse_size(value: &str) -> Option<u64> {
+    let (number, unit) = number_and_unit(value)?;
+    let power = match unit.trim_end_matches("ib").trim_end_matches('b') {
+        "" => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        _ => return None,
+    };
+
+    Some((number * 1024f64.powi(power)) as u64)
+}
+
+impl Filters {
+    pub fn is_empty(&self) -> bool {
+        *self == Filters::default()
+    }
+
+    /// Whether the file or folder at `path` passes every filter. `metadata`
+    /// is only looked up for modified and size filters, which nothing
+    /// without it passes.
+    pub fn matches(
+        &self,
+        path: &str,
+        is_dir: bool,
+        metadata: impl FnOnce() -> Option<Metadata>,
+    ) -> bool {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+
+        if !self.extensions.is_empty() && (is_dir || !self.extensions.contains(&extension)) {
+            return false;
+        }
+
+        if !self.kinds.is_empty() && !self.kinds.iter().any(|k| k.matches(&extension, is_dir)) {
+            return false;
+        }
+
+        if !self.within.is_empty()
+            && !self
+                .within
+                .iter()
+                .any(|dir| Path::new(path).starts_with(dir))
+        {
+            return false;
+        }
+
+        if self.modified.is_none() && self.size.is_none() {
+            return true;
+        }
+
+        let metadata = match metadata() {
+            Some(metadata) => metadata,
+            None => return false,
+        };
+
+        if let Some(modified) = &self.modified {
+            if !modified.contains(metadata.age()) {
+                return false;
+            }
+        }
+
+        if let Some(size) = &self.size {
+            if is_dir || !size.contains(metadata.size) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Whether everything modified longer ago than `metadata` fails, so a
+    /// newest first listing can stop there.
+    pub fn too_old(&self, metadata: &Metadata) -> bool {
+        matches!(self.modified, Some(Bound::Below(age)) if metadata.age() >= age)
+    }
+}
+
+/// Filters offered as the last word of the query is typed, with what they
+/// do.
+pub fn completions() -> Vec<(String, String)> {
+    let mut completions = vec![];
+
+    for ext in [
+        "pdf", "docx", "odt", "txt", "md", "jpg", "png", "mp3", "mp4", "zip",
+    ] {
+        completions.push((format!("ext:{}", ext), format!(".{} files", ext)));
+    }
+
+    for (kind, description) in [
+        ("dir", "Folders"),
+        ("file", "Files"),
+        ("image", "Images"),
+        ("audio", "Music and other audio"),
+        ("video", "Videos"),
+        ("document", "Documents"),
+    ] {
+        completions.push((format!("type:{}", kind), description.to_string()));
+    }
+
+    for dir in [
+        "Documents",
+        "Downloads",
+        "Pictures",
+        "Music",
+        "Videos",
+        "Desktop",
+    ] {
+        completions.push((format!("in:~/{}", dir), format!("Only in ~/{}", dir)));
+    }
+
+    for (age, description) in [
+        ("<1d", "Modified today"),
+        ("<7d", "Modified this week"),
+        ("<30d", "Modified this month"),
+        (">1y", "Not modified for a year"),
+    ] {
+        completions.push((format!("modified:{}", age), description.to_string()));
+    }
+
+    for (size, description) in [
+        ("<100K", "Smaller than 100 KiB"),
+        (">10M", "Bigger than 10 MiB"),
+        (">100M", "Bigger than 100 MiB"),
+        (">1G", "Bigger than 1 GiB"),
+    ] {
+        completions.push((format!("size:{}", size), description.to_string()));
+    }
+
+    completions
+}
+
+/// Whether `completion` should be offered for the word being typed. Only
+/// once the filter's name and colon have been typed, so they don't get in
+/// the way of ordinary searches.
+pub fn completes(word: &str, completion: &str) -> bool {
+    word.contains(':')
+        && completion.len() > word.len()
+        && completion.to_lowercase().starts_with(&word.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters() {
+        let (filters, rest) =
+            parse("tax ext:pdf|docx type:file modified:<7d size:>1.5M -draft \"in:tray\"");
+
+        assert_eq!(rest, "tax -draft \"in:tray\"");
+        assert_eq!(filters.extensions, vec!["pdf", "docx"]);
+        assert_eq!(filters.kinds, vec![Kind::File]);
+        assert_eq!(
+            filters.modified,
+            Some(Bound::Below(Duration::from_secs(7 * 24 * 60 * 60)))
+        );
+        assert_eq!(filters.size, Some(Bound::Above(1572864)));
+    }
+
+    #[test]
+    fn not_filters() {
+        let (filters, rest) = parse("c++ - size:big type:car 10:30 http://example.com");
+
+        assert!(filters.is_empty());
+        assert_eq!(rest, "c++ - size:big type:car 10:30 http://example.com");
+
+        let (filters, rest) = parse("modified:<999999999999y modified:>1.5.5d");
+        assert!(filters.is_empty());
+        assert_eq!(rest, "modified:<999999999999y modified:>1.5.5d");
+    }
+
+    #[test]
+    fn matching() {
+        let unknown = || None;
+
+        let (filters, _) = parse("ext:toml");
+        assert!(filters.matches("Cargo.toml", false, unknown));
+        assert!(!filters.matches("Cargo.lock", false, unknown));
+        assert!(!filters.matches("src", true, unknown));
+
+        let (filters, _) = parse("type:dir in:/tmp");
+        assert!(filters.matches("/tmp/photos", true, unknown));
+        assert!(!filters.matches("/tmpfiles/photos", true, unknown));
+
+        let (filters, _) = parse("size:<1M modified:<1d");
+        assert!(!filters.matches("does not exist", false, unknown));
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let recent = Metadata {
+            size: 1024,
+            modified: now - 60,
+        };
+        let old = Metadata {
+            size: 1024,
+            modified: now - 2 * 24 * 60 * 60,
+        };
+        assert!(filters.matches("notes.txt", false, || Some(recent)));
+        assert!(!filters.matches("notes.txt", false, || Some(old)));
+        assert!(!filters.too_old(&recent));
+        assert!(filters.too_old(&old));
+
+        assert!(completes("ext:p", "ext:pdf"));
+        assert!(!completes("ex", "ext:pdf"));
+        assert!(!completes("ext:pdf", "ext:pdf"));
+    }
+}
@@ -0,0 +1,128 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use glimpse::file_index::FileIndex;
+use savefile_derive::Savefile;
+
+const STATE_VERSION: u32 = 0;
+
+/// Where the crawl got up to. Directories in `pending` belong to the first
+/// search path that isn't in `completed_roots`.
+#[derive(Savefile, Default)]
+pub struct CrawlState {
+    pub pending: Vec<String>,
+    pub completed_roots: Vec<String>,
+}
+
+fn checkpoint_path(db_path: &Path) -> PathBuf {
+    db_path.join("full_index_checkpoint")
+}
+
+fn state_path(checkpoint_path: &Path) -> PathBuf {
+    checkpoint_path.join("crawl_state")
+}
+
+/// Snapshots the index being built along with the crawl state. The databases
+/// are modified in place so a copy of them is taken rather than just
+/// remembering where the crawl was.
+pub fn save(db_path: &Path, temp_db_path: &Path, idx: &mut FileIndex, state: &CrawlState) {
+    idx.flush();
+
+    let checkpoint = checkpoint_path(db_path);
+    let in_progress = checkpoint.with_extension("partial");
+
+    fs::remove_dir_all(&in_progress).unwrap_or_default();
+
+    if let Err(err) = write_checkpoint(&in_progress, temp_db_path, state) {
+        eprintln!("Failed to write checkpoint: {:?}", err);
+        fs::remove_dir_all(&in_progress).unwrap_or_default();
+        return;
+    }
+
+    fs::remove_dir_all(&checkpoint).unwrap_or_default();
+    if fs::rename(&in_progress, &checkpoint).is_ok() {
+        println!("Saved checkpoint.");
+    }
+}
+
+fn write_checkpoint(
+    dest: &Path,
+    temp_db_path: &Path,
+    state: &CrawlState,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(dest)?;
+
+    for file in fs::read_dir(temp_db_path)? {
+        let file = file?;
+
+        // The lock belongs to the running indexer, not to the snapshot.
+        if file.file_name() == "lock" {
+            continue;
+        }
+
+        fs::copy(file.path(), dest.join(file.file_name()))?;
+    }
+
+    let mut file = fs::File::create(state_path(dest))?;
+    savefile::save(&mut file, STATE_VERSION, state)?;
+
+    Ok(())
+}
+
+/// Replaces `temp_db_path` with the last checkpoint if there is one and
+/// returns the crawl state to continue from.
+pub fn restore(db_path: &Path, temp_db_path: &Path) -> Option<CrawlState> {
+    let checkpoint = checkpoint_path(db_path);
+
+    let mut file = fs::File::open(state_path(&checkpoint)).ok()?;
+    let state: CrawlState = match savefile::load(&mut file, STATE_VERSION) {
+        Ok(state) => state,
+        Err(_) => {
+            clear(db_path);
+            return None;
+        }
+    };
+
+    fs::remove_dir_all(temp_db_path).unwrap_or_default();
+    fs::create_dir_all(temp_db_path).ok()?;
+
+    for file in fs::read_dir(&checkpoint).ok()? {
+        let file = file.ok()?;
+        if file.path() == state_path(&checkpoint) {
+            continue;
+        }
+
+        fs::copy(file.path(), temp_db_path.join(file.file_name())).ok()?;
+    }
+
+    if FileIndex::is_outdated(temp_db_path) {
+        clear(db_path);
+        return None;
+    }
+
+    Some(state)
+}
+
+pub fn clear(db_path: &Path) {
+    let checkpoint = checkpoint_path(db_path);
+    fs::remove_dir_all(checkpoint.with_extension("partial")).unwrap_or_default();
+    fs::remove_dir_all(checkpoint).unwrap_or_default();
+}
@@ -14,19 +14,43 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod checkpoint;
+
 use std::{
     fs::{self, DirEntry},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant, UNIX_EPOCH},
 };
 
+use checkpoint::CrawlState;
 use glimpse::{
     config::CONF,
+    extract::{cache, worker},
     file_index::{FileIndex, FILE_DB_READ, FILE_DB_WRITE},
+    scheduler,
 };
 
+static DAEMON: AtomicBool = AtomicBool::new(false);
+
+/// Checkpoints copy the whole index being built, so on large indexes they
+/// are spaced out further to keep them to a tenth of the crawl's time.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const MAX_CHECKPOINT_SHARE: u32 = 10;
+
 fn main() {
     let args: Vec<String> = std::env::args().collect();
 
+    if args.contains(&String::from("--extract-worker")) {
+        worker::serve();
+        return;
+    }
+
+    if args.contains(&String::from("--skipped")) {
+        print_skipped();
+        return;
+    }
+
     if args.contains(&String::from("--init")) {
         if CONF.error.is_some() {
             eprintln!("Failed to initialize config");
@@ -36,18 +60,85 @@ fn main() {
         return;
     }
 
+    worker::record_skipped();
+    if CONF.extractors.sandbox {
+        match std::env::current_exe() {
+            Ok(exe) => worker::enable(exe),
+            Err(err) => eprintln!("Extracting without a sandbox: {}", err),
+        }
+    }
+
+    if args.contains(&String::from("--daemon")) {
+        run_daemon();
+        return;
+    }
+
     if CONF.modules.files {
         reindex();
     }
 }
 
-fn reindex() {
+fn run_daemon() {
+    if !CONF.modules.files {
+        eprintln!("Files module is disabled in config");
+        std::process::exit(1);
+    }
+
     let db_path = PathBuf::from(&CONF.indexing.location);
-    FileIndex::set_last_indexed(&db_path);
+    if daemon_already_running(&db_path) {
+        eprintln!("Indexer daemon is already running.");
+        std::process::exit(1);
+    }
+    let _ = fs::write(pid_path(&db_path), std::process::id().to_string());
+
+    DAEMON.store(true, Ordering::Relaxed);
+    scheduler::lower_priority();
+
+    loop {
+        if scheduler::needs_reindex() && scheduler::in_active_hours() && scheduler::system_is_idle()
+        {
+            println!("Starting scheduled reindex.");
+            reindex();
+        }
+
+        std::thread::sleep(check_interval());
+    }
+}
+
+fn check_interval() -> Duration {
+    Duration::from_secs_f32(CONF.scheduler.check_interval_minutes.max(0.1) * 60.)
+}
 
+fn pid_path(db_path: &Path) -> PathBuf {
+    db_path.join("daemon.pid")
+}
+
+fn daemon_already_running(db_path: &Path) -> bool {
+    match fs::read_to_string(pid_path(db_path)) {
+        Ok(pid) => {
+            let pid = pid.trim();
+            pid != std::process::id().to_string() && PathBuf::from("/proc").join(pid).exists()
+        }
+        Err(_) => false,
+    }
+}
+
+fn reindex() {
+    let db_path = PathBuf::from(&CONF.indexing.location);
     let temp_db_path = db_path.join("full_index_temp");
 
-    fs::remove_dir_all(&temp_db_path).unwrap_or_default();
+    let mut state = match checkpoint::restore(&db_path, &temp_db_path) {
+        Some(state) => {
+            println!("Resuming from checkpoint...");
+            state
+        }
+        None => {
+            FileIndex::set_last_indexed(&db_path);
+            worker::clear_skipped();
+            fs::remove_dir_all(&temp_db_path).unwrap_or_default();
+            CrawlState::default()
+        }
+    };
 
     let idx = FileIndex::open(&temp_db_path, FILE_DB_READ | FILE_DB_WRITE);
 
@@ -57,27 +148,35 @@ fn reindex() {
     }
 
     let mut idx = idx.unwrap();
+    FileIndex::set_index_version(&temp_db_path);
 
-    for path in &CONF.search_paths {
-        let _ = index_dir(
-            path,
-            &CONF.search_hidden_folders,
-            &mut idx,
-            &CONF.ignore_directories,
-        );
-    }
+    crawl(&mut state, &mut idx, &db_path, &temp_db_path);
 
     idx.dirs.save_meta();
     idx.files.save_meta();
     idx.tf_idf.save_meta();
     idx.terms.save_meta();
+    idx.hashes.save_meta();
+    idx.copies.save_meta();
+    idx.metadata.save_meta();
+    idx.write_recent();
 
     // close db connection
     drop(idx);
 
     // Copy the temp db to the main db
 
-    FileIndex::wait_for_unlock(&db_path, 60);
+    // The applet holds the lock while it loads the index. The daemon keeps
+    // the finished index and tries again rather than giving up on it.
+    while let Err(err) = FileIndex::wait_for_unlock(&db_path, 60) {
+        if !DAEMON.load(Ordering::Relaxed) {
+            eprintln!("Timeout waiting for database lock: {}", err);
+            std::process::exit(1);
+        }
+
+        println!("Index is still locked, trying again later.");
+        std::thread::sleep(check_interval());
+    }
 
     println!("Copying files...");
     FileIndex::manual_lock(&db_path).unwrap();
@@ -98,6 +197,76 @@ fn reindex() {
     FileIndex::manual_unlock(&db_path);
 
     fs::remove_dir_all(temp_db_path).unwrap();
+    checkpoint::clear(&db_path);
+
+    if let Some(started) = FileIndex::last_indexed(&db_path) {
+        cache::prune(UNIX_EPOCH + Duration::from_secs(started.max(0) as u64));
+    }
+
+    let skipped = worker::skipped().len();
+    if skipped > 0 {
+        println!(
+            "Skipped {} files that couldn't be read, see glimpse-indexer --skipped.",
+            skipped
+        );
+    }
+}
+
+fn print_skipped() {
+    let skipped = worker::skipped();
+    if skipped.is_empty() {
+        println!("No files were skipped.");
+    }
+
+    for (path, reason) in skipped {
+        println!("{}: {}", path, reason);
+    }
+}
+
+fn crawl(state: &mut CrawlState, idx: &mut FileIndex, db_path: &Path, temp_db_path: &Path) {
+    let mut last_checkpoint = Instant::now();
+    let mut checkpoint_interval = CHECKPOINT_INTERVAL;
+
+    for root in &CONF.search_paths {
+        let root = root.to_str().unwrap().to_string();
+
+        if state.completed_roots.contains(&root) {
+            continue;
+        }
+
+        if state.pending.is_empty() {
+            state.pending.push(root.clone());
+        }
+
+        while let Some(dir) = state.pending.pop() {
+            if idx.exceeded_capcaity() {
+                println!("Exceeded capacity");
+                return;
+            }
+
+            let _ = index_dir(
+                &PathBuf::from(dir),
+                &CONF.search_hidden_folders,
+                idx,
+                &CONF.ignore_directories,
+                &mut state.pending,
+            );
+
+            // Marked before a checkpoint can be saved, as once pending is
+            // empty a resumed crawl would otherwise start the root again.
+            if state.pending.is_empty() {
+                state.completed_roots.push(root.clone());
+            }
+
+            if last_checkpoint.elapsed() > checkpoint_interval {
+                let started = Instant::now();
+                checkpoint::save(db_path, temp_db_path, idx, state);
+                checkpoint_interval =
+                    CHECKPOINT_INTERVAL.max(started.elapsed() * MAX_CHECKPOINT_SHARE);
+                last_checkpoint = Instant::now();
+            }
+        }
+    }
 }
 
 #[inline]
@@ -105,17 +274,15 @@ fn is_hidden_file(file: &DirEntry) -> bool {
     file.file_name().to_str().unwrap().starts_with('.')
 }
 
+/// Indexes the directory and its files. Subdirectories are pushed to
+/// `pending` rather than recursed into so the crawl can be checkpointed.
 fn index_dir(
     path: &PathBuf,
     index_hidden: &bool,
     idx: &mut FileIndex,
-    ignore_dirs: &Vec<String>,
+    ignore_dirs: &[String],
+    pending: &mut Vec<String>,
 ) -> Result<(), std::io::Error> {
-    if idx.exceeded_capcaity() {
-        println!("Exceeded capacity");
-        return Ok(());
-    }
-
     if ignore_dirs.contains(&path.file_name().unwrap().to_str().unwrap().to_string()) {
         return Ok(());
     }
@@ -125,7 +292,7 @@ fn index_dir(
     idx.add_dir(path);
 
     for entry in dir {
-        let _ = handle_dir_entry(entry, index_hidden, idx, ignore_dirs);
+        let _ = handle_dir_entry(entry, index_hidden, idx, pending);
     }
 
     Ok(())
@@ -135,20 +302,24 @@ fn handle_dir_entry(
     entry: Result<DirEntry, std::io::Error>,
     index_hidden: &bool,
     idx: &mut FileIndex,
-    ignore_dirs: &Vec<String>,
+    pending: &mut Vec<String>,
 ) -> Result<(), std::io::Error> {
     if idx.exceeded_capcaity() {
         println!("Exceeded capacity");
         return Ok(());
     }
 
+    if DAEMON.load(Ordering::Relaxed) {
+        scheduler::pause_while_busy();
+    }
+
     let entry = entry?;
 
     if entry.file_type()?.is_dir() {
         if !index_hidden && is_hidden_file(&entry) {
             return Ok(());
         }
-        let _ = index_dir(&entry.path(), index_hidden, idx, ignore_dirs);
+        pending.push(entry.path().to_str().unwrap().to_string());
     } else {
         if !index_hidden && is_hidden_file(&entry) {
             return Ok(());
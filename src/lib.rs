@@ -14,10 +14,22 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+pub mod analyzer;
+pub mod archive;
 pub mod biases;
+pub mod calibration;
 pub mod config;
+pub mod content_hash;
 pub mod db;
+pub mod extract;
 pub mod file_index;
+pub mod filters;
+pub mod normalize;
+pub mod path_match;
 pub mod prelude;
+pub mod query;
+pub mod scheduler;
+pub mod snippet;
 pub mod string_similarity;
 pub mod tfidf;
+pub mod tokenizer;
@@ -0,0 +1,90 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::config::CONF;
+
+/// Puts `str` in the form everything is stored and compared in. Should be
+/// applied to anything going into the index and to queries alike.
+pub fn normalize(str: &str) -> String {
+    fold(&compose(str))
+}
+
+/// NFKC, which merges things like full-width characters and decomposed
+/// accents (common in file names from macOS) into their usual forms. Case
+/// is kept so word boundaries can still be found.
+pub fn compose(str: &str) -> String {
+    str.nfkc().collect()
+}
+
+/// Case folds and, if enabled, strips diacritics from already composed text.
+pub fn fold(str: &str) -> String {
+    fold_with(str, CONF.tokenizer.strip_diacritics)
+}
+
+/// Like `fold`, stripping diacritics or not regardless of the config.
+pub fn fold_with(str: &str, strip_diacritics: bool) -> String {
+    let mut folded = String::with_capacity(str.len());
+
+    for c in str.chars() {
+        match c {
+            'ß' | 'ẞ' => folded.push_str("ss"),
+            'ς' => folded.push('σ'),
+            _ => folded.extend(c.to_lowercase()),
+        }
+    }
+
+    if strip_diacritics {
+        folded = folded
+            .nfd()
+            .filter(|c| !is_combining_mark(*c))
+            .nfc()
+            .collect();
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(str: &str) -> String {
+        fold_with(&compose(str), true)
+    }
+
+    #[test]
+    fn diacritics() {
+        assert_eq!(normalize("Café"), "cafe");
+        assert_eq!(normalize("naïve résumé"), "naive resume");
+        assert_eq!(fold_with("café", false), "café");
+    }
+
+    #[test]
+    fn case_folding() {
+        assert_eq!(normalize("Straße"), "strasse");
+        assert_eq!(normalize("ΣΟΦΟΣ"), normalize("σοφος"));
+    }
+
+    #[test]
+    fn compatibility_forms() {
+        assert_eq!(normalize("ＦＵＬＬ１２"), "full12");
+        // Decomposed "é" as found in file names written on macOS.
+        assert_eq!(compose("cafe\u{301}"), "café");
+        assert_eq!(fold_with(&compose("cafe\u{301}"), false), "café");
+    }
+}
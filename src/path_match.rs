@@ -0,0 +1,140 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::path::{Component, Path};
+
+use crate::normalize::normalize;
+
+/// Splits a query like "proj/src/main" into its normalized segments. Returns
+/// `None` for queries that aren't paths.
+pub fn query_segments(query: &str) -> Option<Vec<String>> {
+    if !query.contains('/') {
+        return None;
+    }
+
+    let mut query = query.to_string();
+    if let Some(rest) = query.strip_prefix("~/") {
+        if let Some(home) = home::home_dir() {
+            query = format!("{}/{}", home.to_str()?, rest);
+        }
+    }
+
+    let segments = query
+        .split('/')
+        .map(normalize)
+        .filter(|s| !s.trim().is_empty())
+        .collect::<Vec<String>>();
+
+    if segments.is_empty() {
+        None
+    } else {
+        Some(segments)
+    }
+}
+
+/// Whether `segments` are found, in order, among the directories `path` is
+/// in. Each segment only has to be part of a directory's name.
+pub fn ancestors_match(path: &Path, segments: &[String]) -> bool {
+    match path.parent() {
+        Some(parent) => components_match(parent, segments),
+        None => segments.is_empty(),
+    }
+}
+
+/// Like `ancestors_match` but `path` itself is included.
+pub fn components_match(path: &Path, segments: &[String]) -> bool {
+    let mut segments = segments.iter().peekable();
+
+    for component in normalized_components(path) {
+        if let Some(segment) = segments.peek() {
+            if component.contains(segment.as_str()) {
+                segments.next();
+            }
+        } else {
+            break;
+        }
+    }
+
+    segments.peek().is_none()
+}
+
+/// How many of `tokens` appear in the names of the directories `path` is in.
+pub fn ancestor_token_matches(path: &Path, tokens: &[String]) -> usize {
+    let ancestors = match path.parent() {
+        Some(parent) => normalized_components(parent),
+        None => return 0,
+    };
+
+    tokens
+        .iter()
+        .filter(|token| ancestors.iter().any(|a| a.contains(token.as_str())))
+        .count()
+}
+
+fn normalized_components(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => name.to_str().map(normalize),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn segments(strs: &[&str]) -> Vec<String> {
+        strs.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn splitting_queries() {
+        assert_eq!(query_segments("report"), None);
+        assert_eq!(
+            query_segments("Proj/src//main"),
+            Some(segments(&["proj", "src", "main"]))
+        );
+        assert_eq!(query_segments("/"), None);
+    }
+
+    #[test]
+    fn ordered_ancestors() {
+        let path = PathBuf::from("/home/user/projects/glimpse/src/main.rs");
+
+        assert!(ancestors_match(&path, &segments(&["proj", "src"])));
+        assert!(ancestors_match(&path, &segments(&["glimpse"])));
+        assert!(!ancestors_match(&path, &segments(&["src", "proj"])));
+        assert!(!ancestors_match(&path, &segments(&["main"])));
+        assert!(components_match(&path, &segments(&["src", "main"])));
+    }
+
+    #[test]
+    fn ancestor_tokens() {
+        let path = PathBuf::from("/home/user/Work/2024/report.pdf");
+
+        assert_eq!(
+            ancestor_token_matches(&path, &segments(&["work", "report"])),
+            1
+        );
+        assert_eq!(
+            ancestor_token_matches(&path, &segments(&["2024", "work"])),
+            2
+        );
+    }
+}
@@ -0,0 +1,345 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::normalize::normalize;
+
+/// A query like `report (pdf OR docx) -draft "tax return"` as a tree.
+/// Words next to each other all have to match, `OR` (in capitals) gives
+/// alternatives and `NOT` or `-` leaves out what would otherwise match.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    Word(String),
+    Phrase(String),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Or,
+    Not,
+    Word(String),
+    Phrase(String),
+}
+
+/// `None` if there is nothing to search for. Mismatched parentheses, a
+/// dangling `OR` and the like are ignored rather than being errors.
+pub fn parse(query: &str) -> Option<Query> {
+    let tokens = tokenize(query);
+    let mut parser = Parser { tokens, next: 0 };
+
+    let mut queries = vec![];
+    while parser.next < parser.tokens.len() {
+        match parser.or() {
+            Some(query) => queries.push(query),
+            // A ")" with nothing open.
+            None => parser.next += 1,
+        }
+    }
+
+    and(queries)
+}
+
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = query.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '"' => {
+                let mut phrase = String::new();
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    phrase.push(c);
+                }
+
+                let phrase = phrase.split_whitespace().collect::<Vec<&str>>().join(" ");
+                if !phrase.is_empty() {
+                    tokens.push(Token::Phrase(phrase));
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+
+                match word.as_str() {
+                    "OR" | "|" => tokens.push(Token::Or),
+                    "NOT" | "-" => tokens.push(Token::Not),
+                    "AND" | "&" => {}
+                    _ => match word.strip_prefix('-') {
+                        Some(rest) if !rest.starts_with('-') => {
+                            tokens.push(Token::Not);
+                            tokens.push(Token::Word(rest.to_string()));
+                        }
+                        _ => tokens.push(Token::Word(word)),
+                    },
+                }
+            }
+        }
+    }
+
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn or(&mut self) -> Option<Query> {
+        let mut alternatives = vec![];
+
+        loop {
+            alternatives.extend(self.and());
+            if self.peek() != Some(&Token::Or) {
+                break;
+            }
+            self.next += 1;
+        }
+
+        or(alternatives)
+    }
+
+    fn and(&mut self) -> Option<Query> {
+        let mut queries = vec![];
+
+        while !matches!(self.peek(), None | Some(Token::Or) | Some(Token::Close)) {
+            queries.extend(self.unary());
+        }
+
+        and(queries)
+    }
+
+    fn unary(&mut self) -> Option<Query> {
+        let token = self.peek()?.clone();
+        self.next += 1;
+
+        match token {
+            Token::Not => match self.peek() {
+                None | Some(Token::Or) | Some(Token::Close) => None,
+                _ => Some(Query::Not(Box::new(self.unary()?))),
+            },
+            Token::Open => {
+                let group = self.or();
+                // An unclosed group runs to the end.
+                if self.peek() == Some(&Token::Close) {
+                    self.next += 1;
+                }
+                group
+            }
+            Token::Word(word) => Some(Query::Word(word)),
+            Token::Phrase(phrase) => Some(Query::Phrase(phrase)),
+            Token::Close | Token::Or => None,
+        }
+    }
+}
+
+fn and(mut queries: Vec<Query>) -> Option<Query> {
+    match queries.len() {
+        0 | 1 => queries.pop(),
+        _ => Some(Query::And(queries)),
+    }
+}
+
+fn or(mut queries: Vec<Query>) -> Option<Query> {
+    match queries.len() {
+        0 | 1 => queries.pop(),
+        _ => Some(Query::Or(queries)),
+    }
+}
+
+impl Query {
+    /// Whether this is a single word or phrase.
+    pub fn is_term(&self) -> bool {
+        matches!(self, Query::Word(_) | Query::Phrase(_))
+    }
+
+    /// Scores something against the query, with `leaf` scoring it against
+    /// each word and phrase and giving `None` where they don't match. Words
+    /// joined by AND score their average, alternatives the best of them.
+    /// NOT only leaves things out, so a query of nothing else matches
+    /// nothing.
+    pub fn evaluate<F: FnMut(&Query) -> Option<f32>>(&self, leaf: &mut F) -> Option<f32> {
+        match self {
+            Query::Word(_) | Query::Phrase(_) => leaf(self),
+            Query::And(queries) => {
+                let mut total = 0.;
+                let mut count = 0;
+
+                for query in queries {
+                    match query {
+                        Query::Not(negated) => {
+                            if negated.evaluate(leaf).is_some() {
+                                return None;
+                            }
+                        }
+                        query => {
+                            total += query.evaluate(leaf)?;
+                            count += 1;
+                        }
+                    }
+                }
+
+                (count > 0).then(|| total / count as f32)
+            }
+            Query::Or(queries) => queries
+                .iter()
+                .filter_map(|query| query.evaluate(leaf))
+                .reduce(f32::max),
+            Query::Not(_) => None,
+        }
+    }
+
+    /// Like `evaluate` for things that either match a word or don't.
+    pub fn matches<F: FnMut(&Query) -> bool>(&self, leaf: &mut F) -> bool {
+        self.evaluate(&mut |term| leaf(term).then_some(1.))
+            .is_some()
+    }
+
+    /// The query without the words and phrases `keep` is false for, `None`
+    /// if that's all of it.
+    pub fn retain<F: Fn(&Query) -> bool>(self, keep: &F) -> Option<Query> {
+        match self {
+            Query::Word(_) | Query::Phrase(_) => keep(&self).then_some(self),
+            Query::And(queries) => {
+                and(queries.into_iter().filter_map(|q| q.retain(keep)).collect())
+            }
+            Query::Or(queries) => or(queries.into_iter().filter_map(|q| q.retain(keep)).collect()),
+            Query::Not(query) => query.retain(keep).map(|query| Query::Not(Box::new(query))),
+        }
+    }
+
+    /// Every word and phrase, including those after NOT.
+    pub fn terms(&self) -> Vec<&Query> {
+        match self {
+            Query::Word(_) | Query::Phrase(_) => vec![self],
+            Query::And(queries) | Query::Or(queries) => {
+                queries.iter().flat_map(|query| query.terms()).collect()
+            }
+            Query::Not(query) => query.terms(),
+        }
+    }
+
+    /// The words and phrases that results are looked up by, leaving out
+    /// those after NOT.
+    pub fn searched_terms(&self) -> Vec<&Query> {
+        match self {
+            Query::Word(_) | Query::Phrase(_) => vec![self],
+            Query::And(queries) | Query::Or(queries) => queries
+                .iter()
+                .flat_map(|query| query.searched_terms())
+                .collect(),
+            Query::Not(_) => vec![],
+        }
+    }
+
+    /// `searched_terms` as a query, with phrases in quotes.
+    pub fn searched_text(&self) -> String {
+        self.searched_terms()
+            .into_iter()
+            .map(|term| match term {
+                Query::Phrase(phrase) => format!("\"{}\"", phrase),
+                Query::Word(word) => word.clone(),
+                _ => String::new(),
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// The normalized text of a word or phrase.
+    pub fn text(&self) -> Option<String> {
+        match self {
+            Query::Word(text) | Query::Phrase(text) => Some(normalize(text)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(word: &str) -> Query {
+        Query::Word(word.to_string())
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse("  "), None);
+        assert_eq!(parse("report"), Some(word("report")));
+        assert_eq!(
+            parse("report (pdf OR docx) -draft NOT \"tax  return\""),
+            Some(Query::And(vec![
+                word("report"),
+                Query::Or(vec![word("pdf"), word("docx")]),
+                Query::Not(Box::new(word("draft"))),
+                Query::Not(Box::new(Query::Phrase("tax return".to_string()))),
+            ]))
+        );
+        assert_eq!(
+            parse("a b OR c"),
+            Some(Query::Or(vec![
+                Query::And(vec![word("a"), word("b")]),
+                word("c")
+            ]))
+        );
+        assert_eq!(parse("or c++ x-ray"), parse("or AND c++ x-ray"));
+        assert_eq!(parse(") a OR (b"), parse("a OR b"));
+        assert_eq!(parse("a OR - ()"), Some(word("a")));
+    }
+
+    #[test]
+    fn evaluation() {
+        let query = parse("report (pdf OR docx) -draft").unwrap();
+        let matches = |text: &str| {
+            query.matches(&mut |term| match term {
+                Query::Word(word) => text.split(' ').any(|w| w == word),
+                _ => false,
+            })
+        };
+
+        assert!(matches("report docx"));
+        assert!(matches("pdf report"));
+        assert!(!matches("report"));
+        assert!(!matches("report pdf draft"));
+        assert!(!parse("-draft").unwrap().matches(&mut |_| false));
+
+        assert_eq!(query.searched_text(), "report pdf docx");
+        assert_eq!(query.terms().len(), 4);
+
+        let short = |term: &Query| term.text().is_some_and(|text| text.len() > 3);
+        assert_eq!(query.retain(&short), parse("report docx -draft"));
+    }
+}
@@ -0,0 +1,261 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use chrono::Timelike;
+use once_cell::sync::Lazy;
+
+use crate::{config::CONF, extract::worker, file_index::FileIndex};
+
+const PROC_PATH: &str = "/proc";
+const LOADAVG_PATH: &str = "/proc/loadavg";
+const POWER_SUPPLY_PATH: &str = "/sys/class/power_supply";
+
+/// How often a running crawl re-checks whether the system is still idle.
+const BUSY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+static LAST_BUSY_CHECK: Lazy<Mutex<Option<Instant>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn needs_reindex() -> bool {
+    let days = CONF.indexing.full_reindex_after_days;
+    let now = chrono::Utc::now().timestamp();
+    const HOUR: f32 = 60. * 60.;
+    const DAY: f32 = HOUR * 24.;
+
+    let db_path = PathBuf::from(&CONF.indexing.location);
+    FileIndex::is_outdated(&db_path)
+        || now - FileIndex::last_indexed(&db_path).unwrap_or(0) > (DAY * days) as i64
+}
+
+/// Whether the system is quiet enough for the indexer to do work. The
+/// indexer's own work doesn't count, or a crawl would pause itself.
+pub fn system_is_idle() -> bool {
+    if let Some(load) = load_average() {
+        if load - own_load() > CONF.scheduler.max_load_average {
+            return false;
+        }
+    }
+
+    if CONF.scheduler.require_ac_power && !on_ac_power(Path::new(POWER_SUPPLY_PATH)) {
+        return false;
+    }
+
+    true
+}
+
+pub fn in_active_hours() -> bool {
+    let hour = chrono::Local::now().hour();
+    hour_in_range(hour, CONF.scheduler.start_hour, CONF.scheduler.end_hour)
+}
+
+/// Blocks while the system is busy or it's outside the active hours. Called
+/// from inside the crawl so a long reindex can be paused and picked back up
+/// where it was.
+pub fn pause_while_busy() {
+    {
+        let mut last_check = LAST_BUSY_CHECK.lock().unwrap();
+        if let Some(last_check) = *last_check {
+            if last_check.elapsed() < BUSY_CHECK_INTERVAL {
+                return;
+            }
+        }
+        *last_check = Some(Instant::now());
+    }
+
+    let may_run = || system_is_idle() && in_active_hours();
+
+    if may_run() {
+        return;
+    }
+
+    println!("System is busy or outside active hours, pausing indexing...");
+    while !may_run() {
+        std::thread::sleep(BUSY_CHECK_INTERVAL);
+    }
+    println!("Resuming indexing.");
+}
+
+/// Lowers the CPU and IO priority of the current process. Child processes
+/// inherit this.
+pub fn lower_priority() {
+    let pid = std::process::id().to_string();
+
+    let _ = Command::new("renice")
+        .args(["-n", &CONF.scheduler.niceness.to_string(), "-p", &pid])
+        .output();
+
+    if CONF.scheduler.idle_io_priority {
+        let _ = Command::new("ionice")
+            .args(["-c", "3", "-p", &pid])
+            .output();
+    }
+}
+
+fn load_average() -> Option<f32> {
+    parse_load_average(&fs::read_to_string(LOADAVG_PATH).ok()?)
+}
+
+fn parse_load_average(loadavg: &str) -> Option<f32> {
+    loadavg.split_whitespace().next()?.parse().ok()
+}
+
+/// Threads of this process and of the extraction worker that are running or
+/// waiting on disk, which the load average counts along with everything
+/// else. They're told apart from other processes by their process groups.
+fn own_load() -> f32 {
+    let groups = [own_process_group(), worker::process_group()];
+
+    let processes = match fs::read_dir(PROC_PATH) {
+        Ok(processes) => processes,
+        Err(_) => return 0.,
+    };
+
+    let stat = |path: &Path| parse_stat(&fs::read_to_string(path.join("stat")).ok()?);
+
+    let mut running = 0;
+    for process in processes.flatten() {
+        let in_groups =
+            stat(&process.path()).is_some_and(|(_, group)| groups.contains(&Some(group)));
+        if !in_groups {
+            continue;
+        }
+
+        let tasks = match fs::read_dir(process.path().join("task")) {
+            Ok(tasks) => tasks,
+            Err(_) => continue,
+        };
+
+        running += tasks
+            .flatten()
+            .filter(|task| stat(&task.path()).is_some_and(|(state, _)| matches!(state, 'R' | 'D')))
+            .count();
+    }
+
+    running as f32
+}
+
+fn own_process_group() -> Option<u32> {
+    let (_, group) = parse_stat(&fs::read_to_string(Path::new(PROC_PATH).join("self/stat")).ok()?)?;
+    Some(group)
+}
+
+/// The state and process group from a /proc stat file. They come after the
+/// command name, which is in parentheses and can contain anything.
+fn parse_stat(stat: &str) -> Option<(char, u32)> {
+    let mut fields = stat[stat.rfind(')')? + 1..].split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let _parent = fields.next()?;
+    let group = fields.next()?.parse().ok()?;
+    Some((state, group))
+}
+
+/// Machines without any mains power supply listed (most desktops) are
+/// treated as being on AC power.
+fn on_ac_power(power_supply_dir: &Path) -> bool {
+    let supplies = match fs::read_dir(power_supply_dir) {
+        Ok(supplies) => supplies,
+        Err(_) => return true,
+    };
+
+    let mut has_mains = false;
+
+    for supply in supplies.flatten() {
+        let path = supply.path();
+        let kind = fs::read_to_string(path.join("type")).unwrap_or_default();
+
+        if kind.trim() != "Mains" {
+            continue;
+        }
+
+        has_mains = true;
+
+        if fs::read_to_string(path.join("online"))
+            .unwrap_or_default()
+            .trim()
+            == "1"
+        {
+            return true;
+        }
+    }
+
+    !has_mains
+}
+
+/// Ranges that wrap past midnight (e.g. 22 to 6) are supported.
+fn hour_in_range(hour: u32, start: u32, end: u32) -> bool {
+    if start <= end {
+        hour >= start && hour < end
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_average_parsing() {
+        assert_eq!(
+            parse_load_average("0.52 0.58 0.59 1/467 12345\n"),
+            Some(0.52)
+        );
+        assert_eq!(parse_load_average(""), None);
+    }
+
+    #[test]
+    fn stat_parsing() {
+        assert_eq!(
+            parse_stat("4242 (glimpse (idx) R) R 1 4200 4200 0 -1 4194560 120 0 0\n"),
+            Some(('R', 4200))
+        );
+        assert_eq!(parse_stat("4242 (truncated"), None);
+    }
+
+    #[test]
+    fn active_hours() {
+        assert!(hour_in_range(3, 0, 24));
+        assert!(hour_in_range(23, 22, 6));
+        assert!(hour_in_range(2, 22, 6));
+        assert!(!hour_in_range(12, 22, 6));
+        assert!(!hour_in_range(6, 0, 6));
+    }
+
+    #[test]
+    fn power_supplies() {
+        let dir = std::env::temp_dir().join(format!("glimpse-power-supply-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(on_ac_power(&dir));
+
+        fs::create_dir_all(dir.join("AC")).unwrap();
+        fs::write(dir.join("AC").join("type"), "Mains\n").unwrap();
+        fs::write(dir.join("AC").join("online"), "0\n").unwrap();
+        assert!(!on_ac_power(&dir));
+
+        fs::write(dir.join("AC").join("online"), "1\n").unwrap();
+        assert!(on_ac_power(&dir));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,274 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use crate::{analyzer::query_terms, tokenizer::split_terms};
+
+/// A passage of a document around words that matched a query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snippet {
+    /// Whitespace is collapsed and "…" marks where the passage was cut off.
+    pub text: String,
+    /// Byte ranges in `text` of the words that matched.
+    pub highlights: Vec<Range<usize>>,
+}
+
+/// Only the start of very long documents is looked through.
+const MAX_SCANNED: usize = 512 * 1024;
+
+/// Up to `count` snippets of about `width` characters from `text` with the
+/// words of `query` highlighted, the best first. Words match the same way
+/// as in the content index, so "running" highlights "runs".
+pub fn find(text: &str, query: &str, count: usize, width: usize) -> Vec<Snippet> {
+    let terms = query_term_set(query);
+    if terms.is_empty() {
+        return vec![];
+    }
+
+    find_with(text, |word| word_matches(word, &terms), count, width)
+}
+
+/// The first word in `text` that matches `query`.
+pub fn first_match<'a>(text: &'a str, query: &str) -> Option<&'a str> {
+    let terms = query_term_set(query);
+    let range = words(text).find(|word| word_matches(&text[word.clone()], &terms))?;
+    Some(&text[range])
+}
+
+fn query_term_set(query: &str) -> HashSet<String> {
+    split_terms(query)
+        .iter()
+        .flat_map(|token| query_terms(token))
+        .collect()
+}
+
+fn word_matches(word: &str, terms: &HashSet<String>) -> bool {
+    split_terms(word)
+        .iter()
+        .flat_map(|token| query_terms(token))
+        .any(|term| terms.contains(&term))
+}
+
+/// Byte ranges of the runs of letters and digits in `text`.
+fn words(text: &str) -> impl Iterator<Item = Range<usize>> + '_ {
+    let scanned = &text[..floor_char_boundary(text, MAX_SCANNED)];
+    let mut rest = scanned.char_indices().peekable();
+
+    std::iter::from_fn(move || {
+        let start = loop {
+            let (i, c) = rest.next()?;
+            if c.is_alphanumeric() {
+                break i;
+            }
+        };
+
+        let mut end = scanned.len();
+        while let Some((i, c)) = rest.peek() {
+            if !c.is_alphanumeric() {
+                end = *i;
+                break;
+            }
+            rest.next();
+        }
+
+        Some(start..end)
+    })
+}
+
+fn find_with(
+    text: &str,
+    is_match: impl Fn(&str) -> bool,
+    count: usize,
+    width: usize,
+) -> Vec<Snippet> {
+    // The same words come up over and over, so each is only checked once.
+    let mut checked: HashMap<&str, bool> = HashMap::new();
+    let matches = words(text)
+        .filter(|range| {
+            let word = &text[range.clone()];
+            *checked.entry(word).or_insert_with(|| is_match(word))
+        })
+        .collect::<Vec<Range<usize>>>();
+
+    let mut windows = matches
+        .iter()
+        .map(|m| window(text, m.start, width))
+        .map(|window| {
+            let inside = matches
+                .iter()
+                .filter(|m| window.start <= m.start && m.end <= window.end)
+                .collect::<Vec<&Range<usize>>>();
+
+            let distinct = inside
+                .iter()
+                .map(|m| text[(*m).clone()].to_lowercase())
+                .collect::<HashSet<String>>()
+                .len();
+
+            // Different words of the query close together say more than
+            // one word repeated.
+            let score = distinct as f32 + inside.len() as f32 * 0.1;
+            (score, window)
+        })
+        .collect::<Vec<(f32, Range<usize>)>>();
+
+    // Stable, so ties go to whichever comes first in the document.
+    windows.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+    let mut chosen: Vec<Range<usize>> = vec![];
+    for (_, window) in windows {
+        if chosen.len() == count {
+            break;
+        }
+
+        if chosen
+            .iter()
+            .all(|c| window.end <= c.start || c.end <= window.start)
+        {
+            chosen.push(window);
+        }
+    }
+
+    chosen
+        .into_iter()
+        .map(|window| snippet(text, window, &matches))
+        .collect()
+}
+
+/// About `width` characters of `text` with the match at `at` a third of the
+/// way in, cut at spaces where possible.
+fn window(text: &str, at: usize, width: usize) -> Range<usize> {
+    let before = &text[..at];
+    let mut start = before
+        .char_indices()
+        .rev()
+        .nth(width / 3)
+        .map_or(0, |(i, _)| i);
+
+    if start > 0 {
+        if let Some(space) = text[start..at].find(char::is_whitespace) {
+            start += space + 1;
+        }
+    }
+
+    let mut end = text[start..]
+        .char_indices()
+        .nth(width)
+        .map_or(text.len(), |(i, _)| start + i);
+
+    if end < text.len() {
+        if let Some(space) = text[at..end].rfind(char::is_whitespace) {
+            end = at + space;
+        }
+    }
+
+    start..end
+}
+
+fn snippet(text: &str, window: Range<usize>, matches: &[Range<usize>]) -> Snippet {
+    let mut snippet = Snippet {
+        text: String::new(),
+        highlights: vec![],
+    };
+
+    if window.start > 0 {
+        snippet.text.push('…');
+    }
+
+    let mut position = window.start;
+    for m in matches
+        .iter()
+        .filter(|m| window.start <= m.start && m.end <= window.end)
+    {
+        push_collapsed(&mut snippet.text, &text[position..m.start]);
+
+        let start = snippet.text.len();
+        snippet.text.push_str(&text[m.clone()]);
+        snippet.highlights.push(start..snippet.text.len());
+
+        position = m.end;
+    }
+    push_collapsed(&mut snippet.text, &text[position..window.end]);
+
+    let trimmed = snippet.text.trim_end().len();
+    snippet.text.truncate(trimmed);
+
+    if window.end < text.len() {
+        snippet.text.push('…');
+    }
+
+    snippet
+}
+
+/// Pushes `str` with runs of whitespace, including newlines, as one space.
+fn push_collapsed(out: &mut String, str: &str) {
+    for c in str.chars() {
+        if !c.is_whitespace() {
+            out.push(c);
+        } else if !(out.is_empty() || out.ends_with([' ', '…'])) {
+            out.push(' ');
+        }
+    }
+}
+
+fn floor_char_boundary(str: &str, index: usize) -> usize {
+    if index >= str.len() {
+        return str.len();
+    }
+
+    (0..=index)
+        .rev()
+        .find(|i| str.is_char_boundary(*i))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlighted(snippet: &Snippet) -> Vec<&str> {
+        snippet
+            .highlights
+            .iter()
+            .map(|h| &snippet.text[h.clone()])
+            .collect()
+    }
+
+    #[test]
+    fn best_passage() {
+        let text = "The budget was mentioned once here.\n\nLater on, the quarterly   budget \
+            review covered the budget in detail. Nothing else.";
+        let is_match = |word: &str| ["budget", "quarterly"].contains(&word.to_lowercase().as_str());
+
+        let snippets = find_with(text, is_match, 2, 40);
+
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(snippets[0].text, "…on, the quarterly budget review…");
+        assert_eq!(highlighted(&snippets[0]), vec!["quarterly", "budget"]);
+        assert_eq!(highlighted(&snippets[1]), vec!["budget"]);
+        assert!(snippets[1].text.starts_with("The budget"));
+    }
+
+    #[test]
+    fn no_matches() {
+        assert!(find_with("nothing to see", |_| false, 3, 40).is_empty());
+        assert_eq!(words("héllo, wörld").collect::<Vec<_>>(), vec![0..6, 8..14]);
+    }
+}
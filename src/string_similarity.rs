@@ -14,33 +14,64 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::biases::BIASES;
+use crate::{biases::BIASES, normalize::normalize, query};
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+/// For matching the first letter of a word, which is what makes acronyms
+/// like "vsc" for "Visual Studio Code" work.
+const BONUS_BOUNDARY: i32 = SCORE_MATCH / 2;
+/// For matching the "C" of "fooCase" or the "2" of "foo2".
+const BONUS_CAMEL: i32 = BONUS_BOUNDARY - 1;
+/// At least as much as a gap would have cost, so matching characters next
+/// to each other is always better.
+const BONUS_CONSECUTIVE: i32 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+const NO_SCORE: i32 = i32::MIN / 2;
+
+/// What a fuzzy match's score is scaled by, so an exact match outweighs the
+/// frecency bias of an item that was picked a few times.
+const MATCH_WEIGHT: f32 = 8.;
+
+/// The similarity below which an item isn't worth listing, about that of
+/// the whole needle matching with 70% of the best possible score.
+pub const MIN_SIMILARITY: f32 = 0.7 * MATCH_WEIGHT;
+
+/// Where `needle` matched an item and how well.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    /// From 0 to 1, where 1 is the needle matching the start of the item
+    /// as it is.
+    pub score: f32,
+    /// The indices of the matched characters of the item.
+    pub positions: Vec<usize>,
+}
 
 pub fn word_similarity(needle: &str, item: String, id_hash: &dyn Fn(&str) -> u64) -> f32 {
-    let needle = needle.to_lowercase();
-    let item = item.to_lowercase();
+    let needle = normalize(needle);
+    let cased_item = item;
+    let item = normalize(&cased_item);
 
     let matched = matched_chars_loose(&needle, &item);
 
-    let mut similarity = matched as f32 + (matched as f32 / item.len() as f32);
-    similarity /= needle.len() as f32;
+    // Only the part of the needle that could be matched is scored, so a
+    // typo near the end still counts for something.
+    let matched_needle = needle.chars().take(matched as usize).collect::<String>();
+    let quality = fuzzy_match(&matched_needle, &cased_item).map_or(0., |m| m.score);
 
-    if item == *needle {
-        similarity += 8.0;
-    } else if item.starts_with(&needle) {
-        similarity += 4.0;
-    } else if item.contains(&needle) {
-        similarity += 3.5;
-    }
+    // The share of the item that was matched only breaks ties between
+    // equally good matches, putting "code" before "code - oss" for "code".
+    let coverage = matched as f32 / needle.chars().count() as f32;
+    let mut similarity =
+        coverage * (quality * MATCH_WEIGHT + matched as f32 / item.chars().count() as f32);
 
-    if let Some(bias) = BIASES.map.get(&id_hash(&item)) {
-        similarity += bias;
-    }
+    similarity += BIASES.read().unwrap().bias(id_hash(&cased_item), &needle);
 
     if matched == 0 {
         similarity = 0.0;
     }
-    if needle.len() > 1 && matched < 2 {
+    if needle.chars().count() > 1 && matched < 2 {
         similarity = 0.0;
     }
 
@@ -50,8 +81,9 @@ pub fn word_similarity(needle: &str, item: String, id_hash: &dyn Fn(&str) -> u64
 fn matched_chars_loose(checking: &str, against: &str) -> u32 {
     let mut ret: u32 = 0;
     let mut against_char = against.chars();
+    let checking_len = checking.chars().count();
     loop {
-        if ret as usize >= checking.len() {
+        if ret as usize >= checking_len {
             break;
         }
 
@@ -66,3 +98,225 @@ fn matched_chars_loose(checking: &str, against: &str) -> u32 {
     }
     ret
 }
+
+#[derive(Clone, Copy, PartialEq)]
+enum CharClass {
+    Lower,
+    Upper,
+    Number,
+    NonWord,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_uppercase() {
+        CharClass::Upper
+    } else if c.is_numeric() {
+        CharClass::Number
+    } else if c.is_alphabetic() {
+        CharClass::Lower
+    } else {
+        CharClass::NonWord
+    }
+}
+
+/// The bonus for matching a character depending on the one before it.
+fn bonus(previous: Option<char>, c: char) -> i32 {
+    let previous = match previous {
+        Some(previous) => char_class(previous),
+        None => return BONUS_BOUNDARY,
+    };
+
+    match (previous, char_class(c)) {
+        (_, CharClass::NonWord) => 0,
+        (CharClass::NonWord, _) => BONUS_BOUNDARY,
+        (CharClass::Lower, CharClass::Upper) => BONUS_CAMEL,
+        (CharClass::Lower | CharClass::Upper, CharClass::Number) => BONUS_CAMEL,
+        _ => 0,
+    }
+}
+
+/// Folds `item` a character at a time the way `normalize` folds a needle,
+/// along with the index of the character each folded one came from, as "ß"
+/// becomes "ss".
+fn fold_chars(item: &[char]) -> (Vec<char>, Vec<usize>) {
+    let mut folded = vec![];
+    let mut origins = vec![];
+
+    for (i, c) in item.iter().enumerate() {
+        if c.is_ascii() {
+            folded.push(c.to_ascii_lowercase());
+            origins.push(i);
+            continue;
+        }
+
+        for c in normalize(&c.to_string()).chars() {
+            folded.push(c);
+            origins.push(i);
+        }
+    }
+
+    (folded, origins)
+}
+
+/// Finds the best way of matching every character of `needle`, in order,
+/// in `item`, the way fzf does. Matches at the start of words and runs of
+/// consecutive characters score higher and gaps between matched characters
+/// cost more the longer they are.
+pub fn fuzzy_match(needle: &str, item: &str) -> Option<FuzzyMatch> {
+    let needle = normalize(needle).chars().collect::<Vec<char>>();
+    let cased = item.chars().collect::<Vec<char>>();
+    let (item, origins) = fold_chars(&cased);
+
+    let (n, m) = (needle.len(), item.len());
+    if n == 0 || n > m {
+        return None;
+    }
+
+    // The characters a character folds into after the first are in the
+    // middle of a word.
+    let bonuses = (0..m)
+        .map(|j| match j > 0 && origins[j - 1] == origins[j] {
+            true => 0,
+            false => {
+                let origin = origins[j];
+                bonus(origin.checked_sub(1).map(|k| cased[k]), cased[origin])
+            }
+        })
+        .collect::<Vec<i32>>();
+
+    // The best score with needle[i] matched at item[j], the bonus of the
+    // run of consecutive matches it ends and where needle[i - 1] was.
+    let mut scores = vec![NO_SCORE; n * m];
+    let mut run_bonuses = vec![0; n * m];
+    let mut previous = vec![0; n * m];
+
+    for i in 0..n {
+        let mut gap_score = NO_SCORE;
+        let mut gap_from = 0;
+
+        for j in 0..m {
+            if i > 0 && j >= 2 {
+                gap_score += SCORE_GAP_EXTENSION;
+                let start = scores[(i - 1) * m + j - 2] + SCORE_GAP_START;
+                if start > gap_score {
+                    gap_score = start;
+                    gap_from = j - 2;
+                }
+            }
+
+            if needle[i] != item[j] {
+                continue;
+            }
+
+            let at = i * m + j;
+            let bonus = bonuses[j];
+
+            if i == 0 {
+                scores[at] = SCORE_MATCH + bonus * BONUS_FIRST_CHAR_MULTIPLIER;
+                run_bonuses[at] = bonus;
+                continue;
+            }
+
+            if j >= 1 && scores[at - m - 1] > NO_SCORE {
+                let run_bonus = run_bonuses[at - m - 1].max(bonus);
+                scores[at] = scores[at - m - 1] + SCORE_MATCH + run_bonus.max(BONUS_CONSECUTIVE);
+                run_bonuses[at] = run_bonus;
+                previous[at] = j - 1;
+            }
+
+            if gap_score > NO_SCORE && gap_score + SCORE_MATCH + bonus > scores[at] {
+                scores[at] = gap_score + SCORE_MATCH + bonus;
+                run_bonuses[at] = bonus;
+                previous[at] = gap_from;
+            }
+        }
+    }
+
+    let last_row = &scores[(n - 1) * m..];
+    let (mut j, score) = last_row
+        .iter()
+        .enumerate()
+        .filter(|(_, score)| **score > NO_SCORE)
+        .max_by_key(|(j, score)| (**score, std::cmp::Reverse(*j)))?;
+    let score = *score;
+
+    let mut positions = vec![origins[j]];
+    for i in (1..n).rev() {
+        j = previous[i * m + j];
+        positions.push(origins[j]);
+    }
+    positions.reverse();
+    positions.dedup();
+
+    let perfect = SCORE_MATCH * n as i32
+        + BONUS_BOUNDARY * BONUS_FIRST_CHAR_MULTIPLIER
+        + BONUS_BOUNDARY * (n as i32 - 1);
+
+    Some(FuzzyMatch {
+        score: (score as f32 / perfect as f32).clamp(0., 1.),
+        positions,
+    })
+}
+
+/// The characters of `item` matched by the words and phrases of `query`,
+/// for highlighting.
+pub fn match_positions(query: &str, item: &str) -> Vec<usize> {
+    let query = match query::parse(query) {
+        Some(query) => query,
+        None => return vec![],
+    };
+
+    let mut positions = query
+        .searched_terms()
+        .into_iter()
+        .filter_map(|term| fuzzy_match(&term.text()?, item))
+        .flat_map(|m| m.positions)
+        .collect::<Vec<usize>>();
+    positions.sort();
+    positions.dedup();
+
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(needle: &str, item: &str) -> Vec<usize> {
+        fuzzy_match(needle, item).unwrap().positions
+    }
+
+    #[test]
+    fn fuzzy_matching() {
+        assert_eq!(fuzzy_match("xyz", "Firefox"), None);
+        assert_eq!(fuzzy_match("", "Firefox"), None);
+        assert_eq!(fuzzy_match("fire", "Firefox").unwrap().score, 1.);
+
+        assert_eq!(positions("vsc", "Visual Studio Code"), vec![0, 7, 14]);
+        assert_eq!(positions("fb", "fooBar"), vec![0, 3]);
+        assert_eq!(positions("ox", "box ox"), vec![4, 5]);
+        assert_eq!(positions("ÉT", "été"), vec![0, 1]);
+        assert_eq!(positions("strasse", "Straße"), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(positions("stras", "Straße"), vec![0, 1, 2, 3, 4]);
+
+        let prefix = fuzzy_match("code", "Code - OSS").unwrap().score;
+        let word = fuzzy_match("code", "Visual Studio Code").unwrap().score;
+        let inside = fuzzy_match("code", "unicode").unwrap().score;
+        let scattered = fuzzy_match("code", "cardboard sleeve").unwrap().score;
+        assert_eq!(prefix, word);
+        assert!(word > inside && inside > scattered);
+    }
+
+    #[test]
+    fn highlighting() {
+        assert_eq!(
+            match_positions("vsc -draft", "Visual Studio Code"),
+            vec![0, 7, 14]
+        );
+        assert_eq!(
+            match_positions("rep 2024", "2024 report"),
+            vec![0, 1, 2, 3, 5, 6, 7]
+        );
+        assert!(match_positions("", "report").is_empty());
+    }
+}
@@ -14,227 +14,704 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::{collections::HashMap, path::PathBuf};
-
-use docx_rs::*;
-use lopdf::Document;
+use std::{collections::HashMap, path::Path};
 
 use crate::{
-    db::{hashmap_db::HashMapDB, list::DBList, string::DBString, string_search_db::StringSearchDb},
-    file_index::{tokenize_string, FileIndex},
-    prelude::Relevance,
+    analyzer::{query_terms, Analyzer},
+    db::{
+        bytes::DBBytes, hashmap_db::HashMapDB, list::DBList, string::DBString,
+        string_search_db::StringSearchDb, value::DBValue,
+    },
+    extract::{cache, REGISTRY},
+    file_index::FileIndex,
+    tokenizer::{split_terms, split_terms_positioned},
 };
 
-pub type TfIdfMap = HashMapDB<DBString, DBList<(Relevance, DBString)>>;
-type TokenFrequency = HashMap<String, f32>;
+/// How often a term appears in a document, how long that document is and
+/// where in it the term is.
+#[derive(Clone)]
+pub struct Posting {
+    pub frequency: f32,
+    pub length: u32,
+    /// Token positions, see `encode_positions`.
+    pub positions: DBBytes,
+}
+
+/// The documents a term is kept for, see `MAX_POSTINGS`.
+#[derive(Clone)]
+pub struct Term {
+    pub postings: DBList<(Posting, DBString)>,
+    pub stats: DBValue<TermStats>,
+}
+
+#[derive(Clone)]
+pub struct TermStats {
+    /// Documents the term appears in, including ones whose postings were
+    /// dropped.
+    pub document_frequency: usize,
+    pub postings: usize,
+    /// The kept posting that is dropped next, and its score when added.
+    pub lowest: DBString,
+    pub lowest_score: f32,
+}
+
+pub type TfIdfMap = HashMapDB<DBString, Term>;
+
+/// Controls how quickly repeats of a term stop counting for more.
+const K1: f32 = 1.2;
+/// How much longer documents are penalised.
+const B: f32 = 0.75;
+
+/// Postings kept per term. Past this the lowest scoring one is dropped.
+const MAX_POSTINGS: usize = 200;
+/// Positions kept per term in a document. Phrases past this many of
+/// occurrences of a term in one document aren't found.
+const MAX_POSITIONS: usize = 256;
+/// How many times a term counts when it is in the emphasized part of a
+/// document, like a page's title.
+const EMPHASIS_WEIGHT: f32 = 3.;
+
+/// BM25 scores for the documents containing `token`, scaled to between 0
+/// and 1 so they can be weighed against name matches. A score of 1 would be
+/// a term that appears in a single document and makes up all of it.
+pub fn bm25(mut map: TfIdfMap, token: &String) -> Vec<(f32, DBString)> {
+    scored_postings(&mut map, token)
+        .into_iter()
+        .map(|(score, _, doc)| (score, doc))
+        .collect()
+}
 
-pub fn _tf_idf(corpus_size: usize, mut map: TfIdfMap, token: &String) -> Vec<(f32, DBString)> {
-    let appearances = match map.get(token) {
-        Some(list) => map.get_list(&list),
+fn scored_postings(map: &mut TfIdfMap, token: &String) -> Vec<(f32, Posting, DBString)> {
+    let term = match map.get(token) {
+        Some(term) => term,
         None => return vec![],
     };
 
-    let appears_in = appearances.len() as f32;
-
-    if appears_in == 0.0 {
+    let postings = map.get_list(&term.postings);
+    if postings.is_empty() {
         return vec![];
     }
 
-    let idf = f32::ln(corpus_size as f32 / appears_in);
+    let document_frequency = map.get_value(&term.stats).document_frequency;
+    let corpus_size = map.corpus_size().max(document_frequency);
+    let average_length = average_length(map);
+
+    let max_score = idf(corpus_size, 1) * (K1 + 1.);
+    let idf = idf(corpus_size, document_frequency);
 
-    appearances
+    postings
         .into_iter()
-        .map(|(tf, doc)| (tf * idf, doc))
+        .map(|(posting, doc)| {
+            let score = idf * term_score(&posting, average_length);
+            ((score / max_score).clamp(0., 1.), posting, doc)
+        })
         .collect()
 }
 
-pub fn add_document_to_corpus(idx: &mut FileIndex, document: &PathBuf) -> Option<()> {
-    let ext = document
-        .extension()
-        .unwrap_or_default()
-        .to_ascii_lowercase();
+fn average_length(map: &mut TfIdfMap) -> f32 {
+    match map.corpus_size() {
+        0 => 1.,
+        corpus_size => (map.total_length() as f32 / corpus_size as f32).max(1.),
+    }
+}
 
-    // TODO: Make configurable.
-    if ext != "pdf" && ext != "docx" && ext != "txt" && ext != "md" && ext != "html" && ext != "htm"
-    {
-        return None;
+/// Never negative, unlike the classic BM25 idf, so very common terms still
+/// count for a little.
+fn idf(corpus_size: usize, appears_in: usize) -> f32 {
+    let n = corpus_size as f32;
+    let df = appears_in as f32;
+    (1. + (n - df + 0.5) / (df + 0.5)).ln()
+}
+
+/// The part of a BM25 score that differs between documents for one term.
+fn term_score(posting: &Posting, average_length: f32) -> f32 {
+    saturated_frequency(posting.frequency, posting.length, average_length)
+}
+
+fn saturated_frequency(tf: f32, length: u32, average_length: f32) -> f32 {
+    let length_norm = 1. - B + B * length as f32 / average_length;
+    tf * (K1 + 1.) / (tf + K1 * length_norm)
+}
+
+/// A term's BM25 score in a document and where in it the term is.
+struct Hit {
+    score: f32,
+    positions: Vec<u32>,
+}
+
+/// Hits for any of `terms`, which are the forms one query word could have
+/// been indexed as.
+fn hits(map: &mut TfIdfMap, terms: &[String]) -> HashMap<String, Hit> {
+    let mut hits: HashMap<String, Hit> = HashMap::new();
+
+    for term in terms {
+        for (score, posting, doc) in scored_postings(map, term) {
+            let positions = decode_positions(&map.get_bytes(&posting.positions));
+            let doc = map.get_string(&doc);
+
+            match hits.get_mut(&doc) {
+                Some(hit) => {
+                    hit.score = hit.score.max(score);
+                    hit.positions.extend(positions);
+                    hit.positions.sort();
+                }
+                None => {
+                    hits.insert(doc, Hit { score, positions });
+                }
+            }
+        }
     }
 
-    let tokens = tokenize_file(document)?;
+    hits
+}
 
-    let document_path = idx
-        .tf_idf
-        .alloc_string(document.to_str().unwrap().to_string());
+/// The terms each word of a query could have been indexed as, along with
+/// the word's position among all of the query's tokens, so gaps left by
+/// stop words are kept.
+fn positioned_terms(text: &str) -> Vec<(u32, Vec<String>)> {
+    split_terms_positioned(text)
+        .into_iter()
+        .map(|(position, token)| (position as u32, query_terms(&token)))
+        .filter(|(_, terms)| !terms.is_empty())
+        .collect()
+}
 
-    for (term, frequency) in term_frequency(&tokens) {
-        let term_allocated = idx.tf_idf.alloc_string(term.clone());
+/// Splits the "quoted phrases" out of a query, giving them and the rest of
+/// the query. An unclosed quote runs to the end.
+pub fn split_phrases(query: &str) -> (Vec<String>, String) {
+    let mut phrases = vec![];
+    let mut rest = String::new();
 
-        let mut list = idx.tf_idf.get(&term).unwrap_or_else(|| {
-            let list = idx.tf_idf.new_list();
-            idx.tf_idf.insert(term_allocated, list.clone());
-            list
-        });
+    for (i, part) in query.split('"').enumerate() {
+        if i % 2 == 1 {
+            if !part.trim().is_empty() {
+                phrases.push(part.trim().to_string());
+            }
+        } else {
+            rest.push_str(part);
+            rest.push(' ');
+        }
+    }
+
+    (phrases, rest.trim().to_string())
+}
+
+/// Documents containing the words of `phrase` next to each other and in
+/// order, scored by the average BM25 score of the words.
+pub fn phrase_search(mut map: TfIdfMap, phrase: &str) -> Vec<(f32, String)> {
+    let terms = positioned_terms(phrase);
+    let first_offset = match terms.first() {
+        Some((offset, _)) => *offset,
+        None => return vec![],
+    };
 
-        idx.tf_idf
-            .push_to_list(&mut list, (frequency, document_path.clone()));
+    let term_hits = terms
+        .iter()
+        .map(|(_, term)| hits(&mut map, term))
+        .collect::<Vec<HashMap<String, Hit>>>();
 
-        remove_lowest_tf_idf_for_token(20, idx.tf_idf.clone(), &term);
+    let offsets = terms
+        .iter()
+        .map(|(offset, _)| offset - first_offset)
+        .collect::<Vec<u32>>();
 
-        if frequency > 3. {
-            add_term(idx.terms.clone(), &term);
+    let mut matches = vec![];
+
+    for doc in term_hits[0].keys() {
+        let positions = term_hits
+            .iter()
+            .map(|hits| Some(hits.get(doc)?.positions.as_slice()))
+            .collect::<Option<Vec<&[u32]>>>();
+
+        if positions.is_some_and(|positions| contains_phrase(&offsets, &positions)) {
+            let score = term_hits
+                .iter()
+                .filter_map(|hits| hits.get(doc))
+                .map(|hit| hit.score)
+                .sum::<f32>();
+
+            matches.push((score / terms.len() as f32, doc.clone()));
         }
     }
 
-    idx.tf_idf.increment_corpus_size();
+    matches
+}
 
-    Some(())
+/// Whether there is a place where each term is `offsets` after the first,
+/// given every position of each term.
+fn contains_phrase(offsets: &[u32], positions: &[&[u32]]) -> bool {
+    positions[0].iter().any(|start| {
+        offsets
+            .iter()
+            .zip(positions)
+            .all(|(offset, positions)| positions.contains(&(start + offset)))
+    })
 }
 
-fn remove_lowest_tf_idf_for_token(corpus_size: usize, mut map: TfIdfMap, token: &String) {
-    let mut tf_idf = _tf_idf(corpus_size, map.clone(), token);
+/// How close together the words of `query` are in each document containing
+/// more than one of them, from 0 to 1 for all of them right next to each
+/// other.
+pub fn proximity(mut map: TfIdfMap, query: &str) -> Vec<(f32, String)> {
+    let mut terms = positioned_terms(query)
+        .into_iter()
+        .map(|(_, terms)| terms)
+        .collect::<Vec<Vec<String>>>();
+    terms.sort();
+    terms.dedup();
 
-    if tf_idf.len() < 15 {
-        return;
+    if terms.len() < 2 {
+        return vec![];
     }
 
-    if tf_idf.is_empty() {
-        return;
+    let mut positions: HashMap<String, Vec<Vec<u32>>> = HashMap::new();
+    for term in &terms {
+        for (doc, hit) in hits(&mut map, term) {
+            positions.entry(doc).or_default().push(hit.positions);
+        }
     }
 
-    tf_idf.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
+    positions
+        .into_iter()
+        .filter(|(_, lists)| lists.len() > 1)
+        .map(|(doc, lists)| {
+            let present = lists.len();
+            let closeness = (present - 1) as f32 / smallest_window(&lists).max(1) as f32;
+            let coverage = present as f32 / terms.len() as f32;
+            (closeness.min(1.) * coverage, doc)
+        })
+        .collect()
+}
 
-    let mut list = map.get(token).unwrap();
+/// The shortest distance between the first and last position of a run that
+/// has a position from every one of `lists`.
+fn smallest_window(lists: &[Vec<u32>]) -> u32 {
+    let mut all = lists
+        .iter()
+        .enumerate()
+        .flat_map(|(list, positions)| positions.iter().map(move |p| (*p, list)))
+        .collect::<Vec<(u32, usize)>>();
+    all.sort();
+
+    let mut counts = vec![0; lists.len()];
+    let mut covered = 0;
+    let mut start = 0;
+    let mut smallest = u32::MAX;
+
+    for &(position, list) in &all {
+        if counts[list] == 0 {
+            covered += 1;
+        }
+        counts[list] += 1;
 
-    let lowest = &tf_idf[0];
+        while covered == lists.len() {
+            let (start_position, start_list) = all[start];
+            smallest = smallest.min(position - start_position);
 
-    map.remove_from_list(&mut list, &lowest.1);
+            counts[start_list] -= 1;
+            if counts[start_list] == 0 {
+                covered -= 1;
+            }
+            start += 1;
+        }
+    }
+
+    smallest
 }
 
-fn term_frequency(tokens: &Vec<String>) -> TokenFrequency {
-    let mut t = HashMap::new();
+/// Stored as the gaps between positions in LEB128, which is a byte for
+/// most gaps.
+fn encode_positions(positions: &[u32]) -> Vec<u8> {
+    let mut bytes = vec![];
+    let mut previous = 0;
 
-    for token in tokens {
-        if !is_suitable_token(token) {
-            continue;
-        }
+    for position in positions {
+        let mut gap = position - previous;
+        previous = *position;
 
-        let count = t.entry(token.clone()).or_insert(0.0);
-        *count += 1.0;
+        while gap >= 0x80 {
+            bytes.push((gap & 0x7f) as u8 | 0x80);
+            gap >>= 7;
+        }
+        bytes.push(gap as u8);
     }
 
-    if t.len() > 15 {
-        let mut t = t.into_iter().collect::<Vec<(String, f32)>>();
-        t.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap());
-
-        t.remove(t.len() - 1);
-        t.remove(t.len() - 1);
-        t.remove(t.len() - 1);
-        t.remove(t.len() - 1);
-        t.remove(t.len() - 1);
+    bytes
+}
 
-        t.into_iter().collect()
-    } else {
-        t
+fn decode_positions(bytes: &[u8]) -> Vec<u32> {
+    let mut positions = vec![];
+    let mut previous = 0u32;
+    let mut gap = 0u32;
+    let mut shift = 0;
+
+    for byte in bytes {
+        gap |= ((byte & 0x7f) as u32) << shift;
+
+        if byte & 0x80 == 0 {
+            previous = previous.wrapping_add(gap);
+            positions.push(previous);
+            gap = 0;
+            shift = 0;
+        } else {
+            shift = (shift + 7).min(28);
+        }
     }
+
+    positions
 }
 
-fn is_suitable_token(token: &str) -> bool {
-    token.len() > 3 && token.len() < 32 && !token.chars().all(|c| c.is_numeric())
+/// The text is kept for snippets, by content fingerprint when there is one.
+pub fn add_document_to_corpus(
+    idx: &mut FileIndex,
+    document: &Path,
+    hash: Option<&str>,
+) -> Option<()> {
+    if !REGISTRY.can_extract(document) {
+        return None;
+    }
+
+    let extracted = cache::extract(document, hash)?;
+    add_text_to_corpus(
+        idx,
+        document.to_str()?,
+        &extracted.text,
+        &extracted.emphasized,
+    )
 }
 
-fn add_term(mut map: StringSearchDb, term: &str) {
-    map.insert_if_new(term, Some(term.to_owned()));
+/// Adds already extracted text as a document, for things like messages in
+/// an mbox that aren't files of their own. `emphasized` is a part of `text`
+/// whose terms count for more.
+pub fn add_text_to_corpus(
+    idx: &mut FileIndex,
+    document: &str,
+    text: &str,
+    emphasized: &str,
+) -> Option<()> {
+    let tokens = split_terms_positioned(text);
+    let (occurrences, length) = term_occurrences(
+        &tokens,
+        &split_terms(emphasized),
+        &Analyzer::for_document(text),
+    );
+
+    if occurrences.is_empty() {
+        return None;
+    }
+
+    let document_path = idx.tf_idf.alloc_string(document.to_string());
+
+    idx.tf_idf.increment_corpus_size();
+    idx.tf_idf.add_to_total_length(length);
+
+    let posting_length = length.min(u32::MAX as usize) as u32;
+    let average_length = average_length(&mut idx.tf_idf);
+
+    for (term, occurrences) in occurrences {
+        add_posting(
+            &mut idx.tf_idf,
+            &term,
+            &document_path,
+            &occurrences,
+            posting_length,
+            average_length,
+        );
+
+        if occurrences.frequency > 3. {
+            add_term(idx.terms.clone(), &term);
+        }
+    }
+
+    Some(())
 }
 
-fn load_as_pdf(path: &PathBuf) -> Option<String> {
-    let doc = Document::load(path).ok()?;
-    let pages = doc.get_pages();
+/// Counts `document` towards the term's document frequency and keeps its
+/// posting if it is among the `MAX_POSTINGS` the term matters most in. idf
+/// is the same for every posting of a term so it's left out. The posting
+/// list is only read when a posting displaces the lowest one.
+fn add_posting(
+    map: &mut TfIdfMap,
+    term: &String,
+    document: &DBString,
+    occurrences: &Occurrences,
+    length: u32,
+    average_length: f32,
+) {
+    let mut entry = match map.get(term) {
+        Some(entry) => entry,
+        None => {
+            let key = map.alloc_string(term.clone());
+            let entry = Term {
+                postings: map.new_list(),
+                stats: map.alloc_value(TermStats {
+                    document_frequency: 0,
+                    postings: 0,
+                    lowest: document.clone(),
+                    lowest_score: f32::MAX,
+                }),
+            };
+            map.insert(key, entry.clone());
+            entry
+        }
+    };
+
+    let mut stats = map.get_value(&entry.stats);
+    stats.document_frequency += 1;
 
-    let mut pages = pages.len() as u32;
+    let score = saturated_frequency(occurrences.frequency, length, average_length);
+    let full = stats.postings >= MAX_POSTINGS;
 
-    if pages == 0 {
-        return None;
+    if full && score <= stats.lowest_score {
+        map.set_value(&entry.stats, stats);
+        return;
     }
 
-    const MAX_PAGES: u32 = 70;
-    if pages > MAX_PAGES {
-        pages = MAX_PAGES;
+    if full {
+        if let Some((posting, _)) = map.remove_from_list(&mut entry.postings, &stats.lowest) {
+            map.dealloc_bytes(&posting.positions);
+            stats.postings -= 1;
+        }
     }
 
-    let range = (1..=pages).collect::<Vec<u32>>();
-    let text = doc.extract_text(&range).ok()?;
+    let posting = Posting {
+        frequency: occurrences.frequency,
+        length,
+        positions: map.alloc_bytes(encode_positions(&occurrences.positions)),
+    };
+    map.push_to_list(&mut entry.postings, (posting, document.clone()));
+    stats.postings += 1;
 
-    Some(text)
-}
+    if full {
+        if let Some((lowest_score, lowest)) = lowest_posting(map, &entry, average_length) {
+            stats.lowest_score = lowest_score;
+            stats.lowest = lowest;
+        }
+    } else if score < stats.lowest_score {
+        stats.lowest_score = score;
+        stats.lowest = document.clone();
+    }
 
-fn load_docx(path: &PathBuf) -> Option<String> {
-    let bytes = std::fs::read(path).unwrap();
+    map.set_value(&entry.stats, stats);
+}
 
-    let doc = docx_rs::read_docx(&bytes).ok()?.document;
-    let contents = get_doc_text(doc.children);
+fn lowest_posting(map: &mut TfIdfMap, term: &Term, average_length: f32) -> Option<(f32, DBString)> {
+    map.get_list(&term.postings)
+        .into_iter()
+        .map(|(posting, doc)| (term_score(&posting, average_length), doc))
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+}
 
-    Some(contents)
+#[derive(Default)]
+struct Occurrences {
+    frequency: f32,
+    positions: Vec<u32>,
 }
 
-fn get_doc_text(doc: Vec<DocumentChild>) -> String {
-    // This just has to be like this...
-    // This crate isn't really meant to be used like this I think.
-    let mut ret = String::new();
-    for child in doc {
-        if let DocumentChild::Paragraph(paragraph) = child {
-            for child in paragraph.children {
-                handle_paragraph_child(child, &mut ret);
+/// Counts each term and where it appears and gives the length of the
+/// document in terms. Words kept whole after being split share a position
+/// with their last part and aren't counted towards the length again, nor
+/// are the other terms a word is indexed as.
+/// Emphasized terms add to the frequency of the same terms in `tokens` but
+/// not to the length or positions, since they aren't extra text.
+fn term_occurrences(
+    tokens: &[(usize, String)],
+    emphasized: &[String],
+    analyzer: &Analyzer,
+) -> (HashMap<String, Occurrences>, usize) {
+    let mut t: HashMap<String, Occurrences> = HashMap::new();
+    let mut length = 0;
+    let mut counted = None;
+
+    for (position, token) in tokens {
+        let terms = analyzer.terms(token);
+        if terms.is_empty() {
+            continue;
+        }
+
+        if counted != Some(*position) {
+            length += 1;
+            counted = Some(*position);
+        }
+
+        for term in terms {
+            let occurrences = t.entry(term).or_default();
+            occurrences.frequency += 1.0;
+            if occurrences.positions.len() < MAX_POSITIONS {
+                occurrences
+                    .positions
+                    .push((*position).min(u32::MAX as usize) as u32);
             }
         }
     }
-    ret
-}
 
-#[inline]
-fn handle_paragraph_child(child: ParagraphChild, ret: &mut String) {
-    if let ParagraphChild::Run(run) = child {
-        handle_run(run, ret);
+    for token in emphasized {
+        for term in analyzer.terms(token) {
+            if let Some(occurrences) = t.get_mut(&term) {
+                occurrences.frequency += EMPHASIS_WEIGHT - 1.;
+            }
+        }
     }
+
+    (t, length)
 }
 
-#[inline]
-fn handle_run(run: Box<Run>, ret: &mut String) {
-    for child in run.children {
-        if let RunChild::Text(text) = child {
-            *ret += text.text.to_string().as_str();
-        }
-    }
+/// Numbers on their own don't make useful search terms.
+pub fn is_numeric(str: &str) -> bool {
+    str.chars()
+        .all(|c| c.is_numeric() || c.is_ascii_punctuation() || c.is_whitespace())
 }
 
-enum FileType {
-    Unknown,
-    Pdf,
-    Docx,
+fn add_term(mut map: StringSearchDb, term: &str) {
+    map.insert_if_new(term, Some(term.to_owned()));
 }
 
-pub fn tokenize_file(path: &PathBuf) -> Option<Vec<String>> {
-    let mut file_type = match infer::get_from_path(path).ok()? {
-        Some(ty) => match ty.mime_type() {
-            "application/pdf" => FileType::Pdf,
-            "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
-            | "application/word" => FileType::Docx,
+#[cfg(test)]
+mod tests {
+    use crate::{analyzer::language, config::Tokenizer, tokenizer::split_words_positioned_with};
 
-            _ => FileType::Unknown,
-        },
-        None => FileType::Unknown,
-    };
+    use super::*;
 
-    if let Some(ext) = path.extension() {
-        if ext == "docx" {
-            file_type = FileType::Docx;
+    #[test]
+    fn bm25_scores() {
+        // More occurrences count for more, but less and less.
+        let one = saturated_frequency(1., 100, 100.);
+        let two = saturated_frequency(2., 100, 100.);
+        let nine = saturated_frequency(9., 100, 100.);
+        let ten = saturated_frequency(10., 100, 100.);
+        assert!(one < two && two < ten);
+        assert!(two - one > ten - nine);
+        assert!(ten < K1 + 1.);
+
+        // The same count in a longer document is worth less.
+        assert!(saturated_frequency(2., 1000, 100.) < two);
+
+        assert!(idf(100, 1) > idf(100, 50));
+        assert!(idf(100, 100) > 0.);
+    }
+
+    #[test]
+    fn capped_postings() {
+        let path = std::env::temp_dir().join(format!("glimpse-postings-{}.db", std::process::id()));
+        TfIdfMap::reset(path.clone());
+        let mut map = TfIdfMap::open(path.clone(), 10);
+
+        let common = "common".to_string();
+        let documents = MAX_POSTINGS + 50;
+        for i in 0..documents {
+            let document = map.alloc_string(i.to_string());
+            map.increment_corpus_size();
+            map.add_to_total_length(100);
+
+            let occurrences = Occurrences {
+                frequency: (i % 7 + 1) as f32,
+                positions: vec![0],
+            };
+            add_posting(&mut map, &common, &document, &occurrences, 100, 100.);
         }
+
+        let term = map.get(&common).unwrap();
+        let stats = map.get_value(&term.stats);
+        assert_eq!(stats.document_frequency, documents);
+        assert_eq!(stats.postings, MAX_POSTINGS);
+
+        // The documents it appears in least are the ones dropped.
+        let postings = map.get_list(&term.postings);
+        assert_eq!(postings.len(), MAX_POSTINGS);
+        assert!(postings.iter().all(|(posting, _)| posting.frequency > 1.));
+
+        // idf comes from every document the term is in, not just the kept ones.
+        let scored = scored_postings(&mut map, &common);
+        let max_score = idf(documents, 1) * (K1 + 1.);
+        let (score, posting, _) = &scored[0];
+        let expected = idf(documents, documents) * term_score(posting, 100.) / max_score;
+        assert_eq!(*score, expected);
+
+        drop(map);
+        TfIdfMap::reset(path);
     }
 
-    let file = match file_type {
-        FileType::Pdf => load_as_pdf(path)?,
-        FileType::Docx => load_docx(path)?,
-        _ => std::fs::read_to_string(path).ok()?,
-    };
+    #[test]
+    fn occurrences() {
+        let tokens = ["reports", "the", "report", "1234", "budget"]
+            .map(String::from)
+            .into_iter()
+            .enumerate()
+            .collect::<Vec<(usize, String)>>();
+        let analyzer = Analyzer::with(language("english"), true, true, true);
+        let emphasized = ["budget"].map(String::from);
+        let (occurrences, length) = term_occurrences(&tokens, &emphasized, &analyzer);
+
+        assert_eq!(length, 3);
+        assert_eq!(occurrences["report"].frequency, 2.);
+        assert_eq!(occurrences["report"].positions, vec![0, 2]);
+        assert_eq!(occurrences["budget"].frequency, EMPHASIS_WEIGHT);
+        assert_eq!(occurrences["budget"].positions, vec![4]);
+        assert!(!occurrences.contains_key("1234"));
+    }
 
-    Some(tokenize_string(&file))
+    #[test]
+    fn split_word_phrases() {
+        let rules = Tokenizer::default();
+        let analyzer = Analyzer::with(language("english"), false, true, true);
+
+        let phrase_in = |phrase: &str, text: &str| {
+            let tokens = split_words_positioned_with(text, &rules);
+            let (occurrences, _) = term_occurrences(&tokens, &[], &analyzer);
+
+            let terms = split_words_positioned_with(phrase, &rules)
+                .into_iter()
+                .filter_map(|(position, token)| {
+                    Some((position as u32, analyzer.terms(&token).pop()?))
+                })
+                .collect::<Vec<(u32, String)>>();
+            let offsets = terms
+                .iter()
+                .map(|(position, _)| position - terms[0].0)
+                .collect::<Vec<u32>>();
+            let positions = terms
+                .iter()
+                .map(|(_, term)| Some(occurrences.get(term)?.positions.as_slice()))
+                .collect::<Option<Vec<&[u32]>>>();
+
+            positions.is_some_and(|positions| contains_phrase(&offsets, &positions))
+        };
+
+        assert!(phrase_in("bar baz", "FooBar baz"));
+        assert!(phrase_in("foobar baz", "FooBar baz"));
+        assert!(phrase_in("FooBar baz", "FooBar baz"));
+        assert!(phrase_in("img2041 photo", "IMG2041 photo"));
+        assert!(!phrase_in("foo baz", "FooBar baz"));
+
+        let tokens = split_words_positioned_with("FooBar baz", &rules);
+        let (occurrences, length) = term_occurrences(&tokens, &[], &analyzer);
+        assert_eq!(length, 3);
+        assert_eq!(occurrences["baz"].positions, vec![2]);
+    }
+
+    #[test]
+    fn positions() {
+        let positions = vec![3, 4, 130, 20_000, 5_000_000];
+        let encoded = encode_positions(&positions);
+
+        assert_eq!(encoded.len(), 1 + 1 + 1 + 3 + 4);
+        assert_eq!(decode_positions(&encoded), positions);
+    }
+
+    #[test]
+    fn proximity_window() {
+        assert_eq!(smallest_window(&[vec![1, 40], vec![2, 90]]), 1);
+        assert_eq!(smallest_window(&[vec![10], vec![1, 30], vec![18]]), 17);
+    }
+
+    #[test]
+    fn phrases() {
+        assert_eq!(
+            split_phrases(r#"notes "quarterly budget review" 2024 "draft"#),
+            (
+                vec!["quarterly budget review".into(), "draft".into()],
+                "notes   2024".into()
+            )
+        );
+        assert_eq!(split_phrases("plain"), (vec![], "plain".into()));
+    }
 }
@@ -0,0 +1,324 @@
+// Glimpse - GNU/Linux launcher and file search utility.
+// Copyright (C) 2024 https://github.com/jaspwr
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+    config::{Tokenizer, CONF},
+    normalize::{compose, fold, fold_with},
+};
+
+/// Splits `str` into normalized words using the rules from the config.
+pub fn split_words(str: &str) -> Vec<String> {
+    split_words_with(&compose(str), &CONF.tokenizer)
+        .iter()
+        .map(|word| fold(word))
+        .collect()
+}
+
+/// Like `split_words` but keeping diacritics, for the analyzer to strip
+/// after stemming.
+pub fn split_terms(str: &str) -> Vec<String> {
+    split_terms_positioned(str)
+        .into_iter()
+        .map(|(_, word)| word)
+        .collect()
+}
+
+/// Like `split_terms`, along with the position of each word in `str`.
+pub fn split_terms_positioned(str: &str) -> Vec<(usize, String)> {
+    split_words_positioned_with(&compose(str), &CONF.tokenizer)
+        .into_iter()
+        .map(|(position, word)| (position, fold_with(&word, false)))
+        .collect()
+}
+
+/// Splits on any non-alphanumeric character or user defined separator, then
+/// on camelCase/PascalCase boundaries and letter/digit transitions. Words
+/// that were split up are also kept whole so "FooBar" gives "foo", "bar" and
+/// "foobar". Version strings like "v1.2.3" are kept as they are.
+pub fn split_words_with(str: &str, rules: &Tokenizer) -> Vec<String> {
+    split_words_positioned_with(str, rules)
+        .into_iter()
+        .map(|(_, word)| word)
+        .collect()
+}
+
+/// Words kept whole share the position of their last part, so "FooBar baz"
+/// has "bar", "foobar" and "baz" next to each other.
+pub fn split_words_positioned_with(str: &str, rules: &Tokenizer) -> Vec<(usize, String)> {
+    let mut str = str.to_string();
+    for separator in &rules.extra_separators {
+        if !separator.is_empty() {
+            str = str.replace(separator.as_str(), " ");
+        }
+    }
+
+    let mut words = vec![];
+    let mut position = 0;
+
+    for chunk in chunks(&str, &rules.word_characters) {
+        let lowercase = chunk.to_lowercase();
+
+        if is_version(&chunk)
+            || rules
+                .keep_whole
+                .iter()
+                .any(|w| w.to_lowercase() == lowercase)
+        {
+            words.push((position, lowercase));
+            position += 1;
+            continue;
+        }
+
+        let parts = split_chunk(&chunk);
+        let split = parts.len() > 1;
+
+        for part in parts {
+            words.push((position, part.to_lowercase()));
+            position += 1;
+        }
+
+        if split {
+            words.push((position - 1, lowercase));
+        }
+    }
+
+    words
+}
+
+/// Keywords for a file or directory name, used alongside the full name.
+pub fn file_name_keywords(file_name: &str) -> Vec<String> {
+    file_name_keywords_with(file_name, &CONF.tokenizer)
+}
+
+/// Like `file_name_keywords`, using the given rules rather than the config's.
+pub fn file_name_keywords_with(file_name: &str, rules: &Tokenizer) -> Vec<String> {
+    let mut words = split_words_with(&compose(strip_extension(file_name)), rules)
+        .iter()
+        .map(|word| fold_with(word, rules.strip_diacritics))
+        .collect::<Vec<String>>();
+    words.retain(|word| word.chars().count() > 1);
+
+    let mut seen = vec![];
+    words.retain(|word| {
+        if seen.contains(word) {
+            return false;
+        }
+        seen.push(word.clone());
+        true
+    });
+
+    words
+}
+
+/// The inner parts of compound extensions like ".tar.gz", which are
+/// stripped along with the outer one.
+const INNER_EXTENSIONS: [&str; 1] = ["tar"];
+
+fn strip_extension(file_name: &str) -> &str {
+    let stem = match extension_start(file_name) {
+        Some(index) => &file_name[..index],
+        None => return file_name,
+    };
+
+    match extension_start(stem) {
+        Some(index)
+            if INNER_EXTENSIONS
+                .iter()
+                .any(|inner| stem[index + 1..].eq_ignore_ascii_case(inner)) =>
+        {
+            &stem[..index]
+        }
+        _ => stem,
+    }
+}
+
+fn extension_start(file_name: &str) -> Option<usize> {
+    match file_name.rfind('.') {
+        Some(index) if index > 0 && is_extension(&file_name[index + 1..]) => Some(index),
+        _ => None,
+    }
+}
+
+/// Avoids treating the end of a version number as an extension.
+fn is_extension(ext: &str) -> bool {
+    !ext.is_empty()
+        && ext.len() <= 5
+        && ext.chars().all(|c| c.is_alphanumeric())
+        && ext.chars().any(|c| c.is_alphabetic())
+}
+
+/// Splits on non-alphanumeric characters other than `word_characters`. Dots
+/// between digits are kept so version numbers stay in one piece.
+fn chunks(str: &str, word_characters: &[char]) -> Vec<String> {
+    let chars = str.chars().collect::<Vec<char>>();
+
+    let mut chunks = vec![];
+    let mut current = String::new();
+
+    for (i, c) in chars.iter().enumerate() {
+        let is_version_dot = *c == '.'
+            && i > 0
+            && chars[i - 1].is_ascii_digit()
+            && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit());
+
+        if c.is_alphanumeric() || word_characters.contains(c) || is_version_dot {
+            current.push(*c);
+        } else if !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+fn is_version(chunk: &str) -> bool {
+    let (prefixed, digits) = match chunk.strip_prefix(['v', 'V']) {
+        Some(rest) => (true, rest),
+        None => (false, chunk),
+    };
+
+    !digits.is_empty()
+        && (prefixed || digits.contains('.'))
+        && digits.starts_with(|c: char| c.is_ascii_digit())
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+fn split_chunk(chunk: &str) -> Vec<String> {
+    let chars = chunk.chars().collect::<Vec<char>>();
+
+    let mut parts = vec![];
+    let mut current = String::new();
+
+    for (i, c) in chars.iter().enumerate() {
+        if i > 0 && is_boundary(chars[i - 1], *c, chars.get(i + 1).copied()) {
+            parts.push(std::mem::take(&mut current));
+        }
+        current.push(*c);
+    }
+
+    if !current.is_empty() {
+        parts.push(current);
+    }
+
+    parts
+}
+
+fn is_boundary(prev: char, c: char, next: Option<char>) -> bool {
+    // Word characters and version dots stick to whatever is next to them.
+    if !prev.is_alphanumeric() || !c.is_alphanumeric() {
+        return false;
+    }
+
+    if prev.is_alphabetic() != c.is_alphabetic() {
+        return true;
+    }
+
+    if prev.is_lowercase() && c.is_uppercase() {
+        return true;
+    }
+
+    // The end of an acronym, "HTMLParser" -> "HTML" "Parser"
+    prev.is_uppercase() && c.is_uppercase() && next.is_some_and(|n| n.is_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(str: &str) -> Vec<String> {
+        split_words_with(str, &Tokenizer::default())
+    }
+
+    #[test]
+    fn separators() {
+        assert_eq!(
+            split("My-Project_v2 final.tar"),
+            vec!["my", "project", "v2", "final", "tar"]
+        );
+    }
+
+    #[test]
+    fn case_boundaries() {
+        assert_eq!(
+            split("FooBarController"),
+            vec!["foo", "bar", "controller", "foobarcontroller"]
+        );
+        assert_eq!(split("HTMLParser"), vec!["html", "parser", "htmlparser"]);
+        assert_eq!(split("parse_config"), vec!["parse", "config"]);
+    }
+
+    #[test]
+    fn positions() {
+        assert_eq!(
+            split_words_positioned_with("FooBar baz", &Tokenizer::default()),
+            vec![
+                (0, "foo".to_string()),
+                (1, "bar".to_string()),
+                (1, "foobar".to_string()),
+                (2, "baz".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn digits_and_versions() {
+        assert_eq!(split("IMG2041"), vec!["img", "2041", "img2041"]);
+        assert_eq!(split("release-1.2.3"), vec!["release", "1.2.3"]);
+        assert_eq!(split("v1.2"), vec!["v1.2"]);
+    }
+
+    #[test]
+    fn user_rules() {
+        let rules = Tokenizer {
+            extra_separators: vec!["x".to_string()],
+            word_characters: vec!['+', '#'],
+            keep_whole: vec!["iPhone".to_string()],
+            ..Tokenizer::default()
+        };
+
+        assert_eq!(split_words_with("iPhone", &rules), vec!["iphone"]);
+        assert_eq!(split_words_with("1920x1080", &rules), vec!["1920", "1080"]);
+        assert_eq!(split_words_with("C++ notes", &rules), vec!["c++", "notes"]);
+        assert_eq!(split("C++ notes"), vec!["c", "notes"]);
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(strip_extension("archive.tar.gz"), "archive");
+        assert_eq!(strip_extension("notes-v1.2"), "notes-v1.2");
+        assert_eq!(strip_extension(".bashrc"), ".bashrc");
+        assert_eq!(strip_extension("FooBarController.java"), "FooBarController");
+        assert_eq!(strip_extension("budget.draft.docx"), "budget.draft");
+        assert_eq!(strip_extension("Backup.TAR.XZ"), "Backup");
+    }
+
+    #[test]
+    fn file_names() {
+        let rules = Tokenizer::default();
+        let keywords = |name| file_name_keywords_with(name, &rules);
+
+        assert_eq!(keywords("budget.draft.docx"), vec!["budget", "draft"]);
+        assert_eq!(
+            keywords("My-Project_v2 final.tar.gz"),
+            vec!["my", "project", "v2", "final"]
+        );
+        assert_eq!(keywords("Résumé Straße.pdf"), vec!["resume", "strasse"]);
+    }
+}